@@ -1,6 +1,9 @@
 fn main() {
     println!("cargo:return-if-changed=src/platform/linux/libwayland.c");
     println!("cargo:return-if-changed=src/platform/linux/xdg-shell-protocol.c");
+    println!("cargo:return-if-changed=src/platform/linux/appmenu-protocol.c");
+    println!("cargo:return-if-changed=src/platform/linux/xdg-foreign-unstable-v2-protocol.c");
+    println!("cargo:return-if-changed=src/platform/linux/xdg-activation-v1-protocol.c");
     println!("cargo:rustc-link-lib=wayland-client");
 
     #[cfg(all(target_os = "linux"))]
@@ -12,4 +15,19 @@ fn main() {
     cc::Build::new()
         .file("src/platform/linux/xdg-shell-protocol.c")
         .compile("xdg-shell-protocol");
+
+    #[cfg(all(target_os = "linux"))]
+    cc::Build::new()
+        .file("src/platform/linux/appmenu-protocol.c")
+        .compile("appmenu-protocol");
+
+    #[cfg(all(target_os = "linux"))]
+    cc::Build::new()
+        .file("src/platform/linux/xdg-foreign-unstable-v2-protocol.c")
+        .compile("xdg-foreign-unstable-v2-protocol");
+
+    #[cfg(all(target_os = "linux"))]
+    cc::Build::new()
+        .file("src/platform/linux/xdg-activation-v1-protocol.c")
+        .compile("xdg-activation-v1-protocol");
 }
@@ -38,66 +38,102 @@ compile_error!("no bare metal support");
 
 mod platform;
 mod events;
+mod notification;
+mod menu;
+mod tray;
+mod dialog;
 
 pub use events::Event;
-use platform::Wrapper;
+pub use notification::{Notification, Urgency, DEFAULT_ICON};
+pub use menu::{Accelerator, Menu, MenuItem, MenuItemKind};
+pub use tray::Tray;
+pub use dialog::FileDialog;
+use platform::{Wrapper, Desktop};
 use log::{warn, info, debug, error};
 
 //pub use nb;
 use dirty::{
+	Error,
+	Handle,
+	NativeWindow,
 	WResponse,
+	format,
 	void,
+	Box,
 	String,
 	Vec
 };
 
 pub use dirty::{SurfaceWrapper as Surface, Color};
+use core::cell::{RefCell, RefMut};
+use core::time::Duration;
 
 /// The default structure to handle and manage apps
 #[allow(dead_code)]
 pub struct App<H>
 where
-	H: EventHandler + Send + Sync,
+	H: EventHandler,
 {
 	/// List of the program windows
 	pub windows: Vec<Window>,
 	/// Cursor information
 	pub cursor: Cursor,
 	theme: ThemeDefault,
-	handler: H,
+	handler: Handle<RefCell<H>>,
+	/// Calls `handler`, given to the backends
+	events: Handler,
 	name: String,
+	desktop: Desktop,
+	menu: Menu,
 }
 
 /// This is the bridge between system events and the lib events
-pub trait EventHandler: Send + Sync
+pub trait EventHandler
 {
 	/// handle_events is the only function for the trait and it results a non blocking Event object
-	fn handle_events(event: Event); //-> nb::Result<(), nb::Error<()>>;
+	/// the handler can keep its own state, like the renderers of its windows
+	fn handle_events(&mut self, event: Event); //-> nb::Result<(), nb::Error<()>>;
 }
 
-impl<H: EventHandler> App<H>
+/// How the backends reach the `EventHandler`, the ones that keep it past a call (like the macOS menus) clone it
+pub(crate) type Handler = Handle<dyn Fn(Event)>;
+
+impl<H: EventHandler + 'static> App<H>
 {
 	/// Create a new `App`
 	#[must_use]
 	pub fn new(handler: H, name: &'static str) -> Self
 	{
-		let theme = ThemeDefault {
+		let mut desktop = Desktop::connect(name);
+		let theme = desktop.system_theme(ThemeDefault {
 			blur: false,
 			dark: false,
+			high_contrast: false,
 			accent_color: Color::from(255, 255, 255, 255),
 			background_color: Color::from(255, 255, 255, 255),
 			has_title: true,
-		};
+		});
+
+		let owned = Handle::new(RefCell::new(handler));
+		let shared = owned.clone();
+		let call: Box<dyn Fn(Event)> = Box::new(move |event| shared.borrow_mut().handle_events(event));
 
 		Self {
 			windows: Vec::new(),
 			cursor: Cursor::get_cursor(),
 			theme,
-			handler,
+			handler: owned,
+			events: Handler::from(call),
 			name: String::from(name),
+			desktop,
+			menu: Menu::app_default(name),
 		}
 	}
 
+	/// The handler given to `App::new`, to hand it what it keeps (like a renderer) before `App::init`
+	pub fn handler(&self) -> RefMut<'_, H>
+		{ self.handler.borrow_mut() }
+
 	/// Returns the global theme defined as Self::theme_get_default()
 	pub fn get_global_theme(&self) -> ThemeDefault
 		{ self.theme.clone() }
@@ -105,22 +141,178 @@ impl<H: EventHandler> App<H>
 	/// Modify the current window theme
 	/// If alread set as the value provided, it does nothing
 	pub fn set_global_theme(&mut self, theme: ThemeDefault)
-		{ self.theme = theme }
+	{
+		self.desktop.set_theme(theme.clone());
+		self.theme = theme;
+	}
+
+	/**
+	 * Shows a desktop notification, returns its id
+	 * the buttons and replies come back as `Event::NotificationAction`
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` if there is no notification server,
+	 * `BinarySpecificLimitation` if the platform has no support yet
+	 */
+	pub fn notify(&mut self, notification: &Notification) -> Result<u32, Error>
+		{ self.desktop.notify(notification) }
+
+	/**
+	 * Closes a notification sent with `App::notify`
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the notification is not visible anymore
+	 */
+	pub fn close_notification(&mut self, id: u32) -> Result<(), Error>
+		{ self.desktop.close_notification(id) }
+
+	/**
+	 * Shows the tray icon, calling it again updates the icon, title and menu
+	 * clicks come back as `Event::TrayActivated` and the menu items as `Event::MenuAction`
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` if the shell has no tray, the icon appears if one starts later,
+	 * `BinarySpecificLimitation` if the platform has no support yet
+	 */
+	pub fn set_tray(&mut self, tray: Tray) -> Result<(), Error>
+		{ self.desktop.set_tray(tray) }
+
+	/**
+	 * Changes the unread count shown on the tray icon
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if there is no tray icon
+	 */
+	pub fn set_unread(&mut self, unread: u32) -> Result<(), Error>
+		{ self.desktop.set_unread(unread) }
+
+	/**
+	 * Removes the tray icon
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if there is no tray icon
+	 */
+	pub fn remove_tray(&mut self) -> Result<(), Error>
+		{ self.desktop.remove_tray() }
+
+	/**
+	 * Makes this the only running instance of the app, call it before creating any window
+	 * if the app is already running, `args` are forwarded to it instead,
+	 * the running instance raises its window and emits `Event::ExternalRequest`
+	 *
+	 * # Errors
+	 *
+	 * `ChannelInUse` if another instance got the arguments, this one should exit,
+	 * `MissingDependencies` if there is no `XDG_RUNTIME_DIR`
+	 */
+	pub fn single_instance(&mut self, args: &[&str]) -> Result<(), Error>
+		{ self.desktop.single_instance(args) }
+
+	/**
+	 * Emits `Event::Timer { token }` once, after `delay`
+	 * timers belong to the app and not to a window, setting a `token` that is already armed restarts it
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` if the event loop couldn't be created,
+	 * `OutOfBounds` if the process ran out of descriptors
+	 */
+	pub fn set_timeout(&mut self, delay: Duration, token: u32) -> Result<(), Error>
+		{ self.desktop.set_timer(token, delay, None) }
+
+	/**
+	 * Emits `Event::Timer { token }` every `interval` until `App::clear_timer`
+	 * ticks missed while the app was busy are merged into one
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if `interval` is zero, the rest like `App::set_timeout`
+	 */
+	pub fn set_interval(&mut self, interval: Duration, token: u32) -> Result<(), Error>
+		{ self.desktop.set_timer(token, interval, Some(interval)) }
+
+	/// Cancels a timeout or interval, returns `false` if `token` wasn't armed
+	pub fn clear_timer(&mut self, token: u32) -> bool
+		{ self.desktop.cancel_timer(token) }
+
+	/**
+	 * Replaces the app menu of every window, the default is `Menu::app_default`
+	 * activations come back as `Event::MenuAction`
+	 *
+	 * # Errors
+	 *
+	 * `ProtocolNotSuported` if the desktop has no global menu
+	 */
+	pub fn set_app_menu(&mut self, menu: Menu) -> Result<(), Error>
+	{
+		self.menu = menu;
+		for window in &self.windows {
+			window.decoration.create_app_menu(&mut self.desktop, &self.menu, &self.events)?;
+		}
+		Ok(())
+	}
+
+	/**
+	 * Shows the native dialog to pick files, over the first window
+	 * returns an id, the paths come back on `Event::FileDialogResult` with the same id
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` if there is no session bus,
+	 * `InvalidRequest` if the portal refused the request
+	 */
+	pub fn open_file_dialog(&mut self, dialog: &FileDialog) -> Result<u32, Error>
+		{ self.file_dialog(dialog, false) }
+
+	/**
+	 * Shows the native dialog to pick where a file is saved, over the first window
+	 * returns an id, the path comes back on `Event::FileDialogResult` with the same id
+	 *
+	 * # Errors
+	 *
+	 * Same as `App::open_file_dialog`
+	 */
+	pub fn save_file_dialog(&mut self, dialog: &FileDialog) -> Result<u32, Error>
+		{ self.file_dialog(dialog, true) }
+
+	/// Dialogs without a parent still work, they just aren't attached to the window
+	fn file_dialog(&mut self, dialog: &FileDialog, save: bool) -> Result<u32, Error>
+	{
+		let parent = self.windows
+			.first()
+			.map(|window| window.decoration.export_handle())
+			.transpose()
+			.inspect_err(|e| debug!("the dialog has no parent: {e}"))
+			.unwrap_or_default()
+			.unwrap_or_default();
+
+		self.desktop.file_dialog(&parent, dialog, save)
+	}
 
 	/// Creates a new Window element and pushes to the App
 	pub fn new_window(
 		&mut self,
 		title: &'static str,
 		size: (f64, f64),
-	) -> Result<Window, WResponse>
+	) -> Result<Window, Error>
 	{
-		let window = Window::new(self.name.clone(), title, self.theme.clone(), size)?;
+		let window = Window::new(title, self.theme.clone(), size)?;
+
+		if let Err(response) = window.decoration.create_app_menu(&mut self.desktop, &self.menu, &self.events) {
+			debug!("no app menu for {}: {response:?}", self.name);
+		}
+
 		self.windows.push(window.clone());
 		Ok(window)
 	}
 
 	/// init event handler
-	pub fn init(&self)
+	pub fn init(&mut self)
 	{
 		/*let _event = thread::spawn(move || {
 			nb::block!(H::handle_events(Event::Generic)).unwrap();
@@ -129,7 +321,7 @@ impl<H: EventHandler> App<H>
 		//dirty::Thread::default(event_thread).run();
 
 		if let Some(window) = self.windows.first() {
-			window.decoration.run();
+			window.decoration.run(window, &mut self.desktop, &self.events);
 		};
 	}
 }
@@ -148,6 +340,8 @@ pub struct ThemeDefault {
 	pub blur: bool,
 	/// default color scheme dark/light
 	pub dark: bool,
+	/// the user asked for an increased contrast
+	pub high_contrast: bool,
 	/// the default accent color of higlight text, buttons, etc
 	pub accent_color: Color,
 	/// the background of the window (not of the renderer)
@@ -159,16 +353,19 @@ pub struct ThemeDefault {
 /// NativeDecoration provides the necessary abstraction used inside the `platform` modules
 pub trait NativeDecoration
 {
-	/// executes the application window
-	fn run(&self);
+	/// executes the application window, backend and desktop events are sent to `handler`
+	/// `window` is the one holding this decoration, its events carry a clone of it
+	fn run(&self, window: &Window, desktop: &mut Desktop, handler: &Handler);
 	/// creates a new decoration on the system
-	fn new(title: String, width: f64, height: f64, theme: ThemeDefault) -> Result<Self, WResponse> where Self: Sized;
+	fn new(title: String, width: f64, height: f64, theme: ThemeDefault) -> Result<Self, Error> where Self: Sized;
 	/// Apply blur to window
-	fn apply_blur(&mut self) -> Result<(), WResponse>;
+	fn apply_blur(&mut self) -> Result<(), Error>;
 	/// exit handler
-	fn exit(&self) -> Result<(), WResponse>;
-	/// App Menu Controls
-	fn create_app_menu(&self, app_name: String) -> Result<(), WResponse>;
+	fn exit(&self) -> Result<(), Error>;
+	/// Identifier other processes use to put dialogs over the window
+	fn export_handle(&self) -> Result<String, Error>;
+	/// Shows `menu` as the app menu of the window, activations are sent to `handler`
+	fn create_app_menu(&self, desktop: &mut Desktop, menu: &Menu, handler: &Handler) -> Result<(), Error>;
 }
 
 /// Detect if the current system prefers CSDs or SSDs
@@ -201,6 +398,8 @@ pub struct Window {
 	pub surface: Option<Surface>,
 	/// The native window frame
 	decoration: Decoration,
+	/// Native objects of the window, shared with the renderer
+	native: Handle<NativeWindow>,
 	resizable: bool,
 	position: (f32, f32),
 	active: bool,
@@ -210,13 +409,14 @@ pub struct Window {
 #[forbid(unsafe_code)]
 impl Window
 {
-	/// Get system specific window backend (for renderer)
+	/// Get system specific window backend (for renderer), every call shares the same handle
+	/// the native window is closed once every handle of it is dropped, the renderer can keep one
 	#[must_use]
-	pub fn get_backend(&self) -> *mut void
-		{ void::to_handle(self.decoration.backend.clone()) }
+	pub fn get_backend(&self) -> Handle<NativeWindow>
+		{ self.native.clone() }
 
 	/// Connects a specified vulkan surface with the current window
-	pub fn connect_surface(&mut self, surface: Surface) -> Result<(), WResponse>
+	pub fn connect_surface(&mut self, surface: Surface) -> Result<(), Error>
 	{
 		if !self.has_surface() {
 			self.surface = Some(surface);
@@ -224,7 +424,7 @@ impl Window
 		}
 		warn!("this window is already connected to a surface!");
 		info!("to connect to another surface, please remove the current one");
-		Err(WResponse::ChannelInUse)
+		Err(WResponse::ChannelInUse.into())
 	}
 
 	/// Returns if window does have a surface or not
@@ -241,17 +441,16 @@ impl Window
 }
 
 trait PrivateWindow {
-	fn new(app_name: String, title: &'static str, theme: ThemeDefault, size: (f64, f64)) ->
-		Result<Window, WResponse>;
+	fn new(title: &'static str, theme: ThemeDefault, size: (f64, f64)) ->
+		Result<Self, Error> where Self: Sized;
 }
 
 impl PrivateWindow for Window {
 	fn new(
-		app_name: String,
 		title: &'static str,
 		theme: ThemeDefault,
 		size: (f64, f64)
-	) -> Result<Self, WResponse>
+	) -> Result<Self, Error>
 	{
 		#[allow(unused_mut)]
 		let mut decoration = match Decoration::new(
@@ -262,19 +461,21 @@ impl PrivateWindow for Window {
 		) {
 			Ok(v) => v,
 			Err(e) => {
-				error!("Unexpected decoration error: {:?}", e);
-				return Err(WResponse::UnexpectedError)
+				error!("Unexpected decoration error: {e}");
+				let kind = e.kind();
+				return Err(e.context(kind, format!("couldn't create the window \"{title}\"")))
 			},
 		};
 
-		let _menu = decoration.create_app_menu(app_name);
-
 		if theme.blur
 		&& let Err(response) = decoration.apply_blur()
-			{ warn!("blur: {response:?}") }
+			{ warn!("blur: {response}") }
+
+		let native = Handle::new(decoration.native());
 
 		Ok(Window {
 			decoration,
+			native,
 			surface: None,
 			active: false,
 			resizable: true,
@@ -0,0 +1,88 @@
+use dirty::{String, Vec};
+
+/// Options of the native file dialogs, the result comes back as `Event::FileDialogResult`
+///
+/// ```rust
+/// let id = app.open_file_dialog(
+/// 	&FileDialog::new("Send a file")
+/// 		.filter("Images", &["*.png", "*.jpg", "image/webp"])
+/// 		.multiple(true)
+/// )?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FileDialog {
+	/// Title of the dialog
+	pub title: String,
+	/// Text of the confirmation button
+	pub accept_label: Option<String>,
+	/// Filters as `(name, patterns)`, patterns are globs like `*.png` or mime types like `image/png`
+	pub filters: Vec<(String, Vec<String>)>,
+	/// Allows picking more than one file, only for open dialogs
+	pub multiple: bool,
+	/// Picks folders instead of files, only for open dialogs
+	pub directory: bool,
+	/// Suggested file name, only for save dialogs
+	pub current_name: Option<String>,
+	/// Folder shown when the dialog opens
+	pub current_folder: Option<String>,
+}
+
+impl FileDialog {
+	/// Creates a dialog that accepts any single file
+	#[must_use]
+	pub fn new(title: &str) -> Self
+	{
+		Self {
+			title: String::from(title),
+			..Self::default()
+		}
+	}
+
+	/// Changes the text of the confirmation button
+	#[must_use]
+	pub fn accept_label(mut self, label: &str) -> Self
+	{
+		self.accept_label = Some(String::from(label));
+		self
+	}
+
+	/// Adds a filter, the first one is selected by default
+	#[must_use]
+	pub fn filter(mut self, name: &str, patterns: &[&str]) -> Self
+	{
+		self.filters.push((String::from(name), patterns.iter().map(|&p| String::from(p)).collect()));
+		self
+	}
+
+	/// Allows picking more than one file
+	#[must_use]
+	pub fn multiple(mut self, multiple: bool) -> Self
+	{
+		self.multiple = multiple;
+		self
+	}
+
+	/// Picks folders instead of files
+	#[must_use]
+	pub fn directory(mut self, directory: bool) -> Self
+	{
+		self.directory = directory;
+		self
+	}
+
+	/// Suggests a file name on save dialogs
+	#[must_use]
+	pub fn current_name(mut self, name: &str) -> Self
+	{
+		self.current_name = Some(String::from(name));
+		self
+	}
+
+	/// Opens the dialog on `folder`
+	#[must_use]
+	pub fn current_folder(mut self, folder: &str) -> Self
+	{
+		self.current_folder = Some(String::from(folder));
+		self
+	}
+}
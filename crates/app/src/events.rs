@@ -1,4 +1,4 @@
-use crate::{ThemeDefault, Window, Cursor};
+use crate::{ThemeDefault, Window, Cursor, Error, String, Vec};
 
 /// List of Events
 #[derive(Debug, PartialEq)]
@@ -50,6 +50,42 @@ pub enum Event {
 		/// The new theme
 		new_theme: ThemeDefault
 	},
+	/// The user clicked a button or replied to a notification
+	NotificationAction {
+		/// Id returned by `App::notify`
+		id: u32,
+		/// Key of the action, `inline-reply` for replies
+		action: String,
+		/// The text typed by the user
+		reply: Option<String>,
+	},
+	/// The user clicked the tray icon
+	TrayActivated {
+		/// Screen position of the click, `(0, 0)` if the shell doesn't know it
+		position: (i32, i32),
+	},
+	/// The user activated a menu item
+	MenuAction {
+		/// Id of the `MenuItem`
+		id: String,
+	},
+	/// The user closed a file dialog
+	FileDialogResult {
+		/// Id returned by `App::open_file_dialog` or `App::save_file_dialog`
+		id: u32,
+		/// Paths picked by the user, empty if the dialog was cancelled
+		files: Vec<String>,
+	},
+	/// The app was launched again, the running window was raised
+	ExternalRequest {
+		/// Arguments of the new launch, like a `matrix:` uri
+		args: Vec<String>,
+	},
+	/// A timer set with `App::set_timeout` or `App::set_interval` fired
+	Timer {
+		/// Token given when the timer was set
+		token: u32,
+	},
 	/// Redraw frame
 	RedrawRequest {
 		/// The specified window
@@ -62,6 +98,11 @@ pub enum Event {
 	},
 	/// User wants to leave
 	CloseRequest,
+	/// The connection with the compositor was lost, the windows are no longer valid
+	ConnectionLost {
+		/// What caused the disconnection
+		reason: Error
+	},
 	/// Temporary argument to handle with the impossibility of implementation (todo)
 	Generic,
 }
@@ -0,0 +1,156 @@
+use dirty::{format, String, Vec};
+
+/// How the item is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItemKind {
+	/// Plain clickable item
+	Standard,
+	/// Item with a checkbox, with its current state
+	Check(bool),
+	/// Item of a group where only one can be selected, with its current state
+	Radio(bool),
+	/// A line between items
+	Separator,
+}
+
+/// Keyboard shortcut of a menu item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accelerator {
+	/// `Ctrl` on Linux, `Cmd` on macOS
+	pub primary: bool,
+	/// `Shift`
+	pub shift: bool,
+	/// `Alt`, `Option` on macOS
+	pub alt: bool,
+	/// The key, always lowercase
+	pub key: char,
+}
+
+impl Accelerator {
+	/// Parses shortcuts like `Ctrl+Shift+Q`, `Cmd` is accepted as an alias of `Ctrl`
+	#[must_use]
+	pub fn parse(shortcut: &str) -> Option<Self>
+	{
+		let (modifiers, name) = shortcut.rsplit_once('+').unwrap_or(("", shortcut));
+		let mut chars = name.chars();
+		let (Some(key), None) = (chars.next(), chars.next()) else { return None };
+
+		let mut accelerator = Self { primary: false, shift: false, alt: false, key: key.to_ascii_lowercase() };
+		for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+			match modifier {
+				"Ctrl" | "Cmd" => accelerator.primary = true,
+				"Shift" => accelerator.shift = true,
+				"Alt" | "Option" => accelerator.alt = true,
+				_ => return None,
+			}
+		}
+		Some(accelerator)
+	}
+}
+
+/// Menu entry, if it has children it opens a submenu
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+	/// Sent back with `Event::MenuAction` when the item is activated
+	pub id: String,
+	/// Text of the item
+	pub label: String,
+	/// How the item is drawn
+	pub kind: MenuItemKind,
+	/// Disabled items are shown but can't be activated
+	pub enabled: bool,
+	/// Keyboard shortcut
+	pub accelerator: Option<Accelerator>,
+	/// Items of the submenu
+	pub children: Vec<MenuItem>,
+}
+
+impl MenuItem {
+	/// Creates a new clickable item
+	#[must_use]
+	pub fn new(id: &str, label: &str) -> Self
+	{
+		Self {
+			id: String::from(id),
+			label: String::from(label),
+			kind: MenuItemKind::Standard,
+			enabled: true,
+			accelerator: None,
+			children: Vec::new(),
+		}
+	}
+
+	/// Creates a new submenu
+	#[must_use]
+	pub fn submenu(label: &str, children: Vec<MenuItem>) -> Self
+	{
+		Self {
+			children,
+			..Self::new("", label)
+		}
+	}
+
+	/// Creates a separator
+	#[must_use]
+	pub fn separator() -> Self
+	{
+		Self {
+			kind: MenuItemKind::Separator,
+			..Self::new("", "")
+		}
+	}
+
+	/// Turns the item into a checkbox
+	#[must_use]
+	pub fn checked(mut self, checked: bool) -> Self
+	{
+		self.kind = MenuItemKind::Check(checked);
+		self
+	}
+
+	/// Turns the item into a radio button
+	#[must_use]
+	pub fn radio(mut self, selected: bool) -> Self
+	{
+		self.kind = MenuItemKind::Radio(selected);
+		self
+	}
+
+	/// Enables or disables the item
+	#[must_use]
+	pub fn enabled(mut self, enabled: bool) -> Self
+	{
+		self.enabled = enabled;
+		self
+	}
+
+	/// Sets the keyboard shortcut, like `Ctrl+Q`, invalid shortcuts are ignored
+	#[must_use]
+	pub fn accelerator(mut self, shortcut: &str) -> Self
+	{
+		self.accelerator = Accelerator::parse(shortcut);
+		self
+	}
+}
+
+/// A tree of menu items
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Menu {
+	/// Top level items
+	pub items: Vec<MenuItem>,
+}
+
+impl Menu {
+	/// Creates a menu with the provided items
+	#[must_use]
+	pub fn new(items: Vec<MenuItem>) -> Self { Self { items } }
+
+	/// The menu every app gets until `App::set_app_menu` is called,
+	/// a submenu named after the app with a `quit` item
+	#[must_use]
+	pub fn app_default(app_name: &str) -> Self
+	{
+		let quit = MenuItem::new("quit", &format!("Quit {app_name}")).accelerator("Ctrl+Q");
+		Self::new(Vec::from([MenuItem::submenu(app_name, Vec::from([quit]))]))
+	}
+}
@@ -0,0 +1,102 @@
+use dirty::{String, Vec};
+
+/// Themed icon used when the notification or tray doesn't set one
+/// packages install `sources/icons/wt.svg` as `share/icons/hicolor/scalable/apps/walkie-talkie.svg`
+pub const DEFAULT_ICON: &str = "walkie-talkie";
+
+/// How much the notification should interrupt the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+	/// Can be shown silently
+	Low = 0,
+	/// Default value
+	Normal = 1,
+	/// Stays until the user dismisses it, like mentions
+	Critical = 2,
+}
+
+/// Desktop notification
+///
+/// ```rust
+/// let id = app.notify(
+/// 	&Notification::new("@alice:matrix.org", "are you there?")
+/// 		.action("open", "Open")
+/// 		.action("read", "Mark as read")
+/// 		.inline_reply("Reply to alice")
+/// )?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+	/// Title of the notification
+	pub summary: String,
+	/// Content, some servers accept basic markup
+	pub body: String,
+	/// Path of an image or name of a themed icon
+	pub icon: String,
+	/// Buttons of the notification, as `(key, label)`
+	/// the key is sent back with `Event::NotificationAction`
+	pub actions: Vec<(String, String)>,
+	/// Placeholder of the reply field, `None` disables the inline reply
+	pub reply_placeholder: Option<String>,
+	/// Id of a previous notification to be replaced by this one
+	pub replaces: Option<u32>,
+	/// How much the notification should interrupt the user
+	pub urgency: Urgency,
+}
+
+impl Notification {
+	/// Creates a new notification with the default icon
+	#[must_use]
+	pub fn new(summary: &str, body: &str) -> Self
+	{
+		Self {
+			summary: String::from(summary),
+			body: String::from(body),
+			icon: String::from(DEFAULT_ICON),
+			actions: Vec::new(),
+			reply_placeholder: None,
+			replaces: None,
+			urgency: Urgency::Normal,
+		}
+	}
+
+	/// Sets the icon, as a path or themed icon name
+	#[must_use]
+	pub fn icon(mut self, icon: &str) -> Self
+	{
+		self.icon = String::from(icon);
+		self
+	}
+
+	/// Adds a button
+	#[must_use]
+	pub fn action(mut self, key: &str, label: &str) -> Self
+	{
+		self.actions.push((String::from(key), String::from(label)));
+		self
+	}
+
+	/// Allows the user to reply from the notification, if the server supports it
+	#[must_use]
+	pub fn inline_reply(mut self, placeholder: &str) -> Self
+	{
+		self.reply_placeholder = Some(String::from(placeholder));
+		self
+	}
+
+	/// Replaces a notification that is still visible
+	#[must_use]
+	pub fn replaces(mut self, id: u32) -> Self
+	{
+		self.replaces = Some(id);
+		self
+	}
+
+	/// Changes the urgency
+	#[must_use]
+	pub fn urgency(mut self, urgency: Urgency) -> Self
+	{
+		self.urgency = urgency;
+		self
+	}
+}
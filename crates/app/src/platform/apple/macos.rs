@@ -1,6 +1,8 @@
 #![allow(unused_imports, unused_doc_comments, clippy::tabs_in_doc_comments)]
 use log::debug;
-use crate::{void, String};
+use core::cell::RefCell;
+use crate::{void, String, Vec};
+use dirty::{NativeWindow, RawWindow};
 
 use objc2::{
 	rc::{Retained, Allocated},
@@ -19,7 +21,7 @@ use objc2_app_kit::{
 	NSBackingStoreType, NSColor, NSFont, NSTextAlignment, NSTextField, NSWindow, NSWindowDelegate,
 	NSWindowStyleMask, NSView, NSWindowTitleVisibility, NSVisualEffectBlendingMode,
 	NSVisualEffectView, NSVisualEffectMaterial, NSVisualEffectState, NSAutoresizingMaskOptions,
-	NSMenu, NSMenuItem
+	NSMenu, NSMenuItem, NSEventModifierFlags, NSControlStateValueOn
 };
 
 use objc2_foundation::{
@@ -27,21 +29,35 @@ use objc2_foundation::{
 	NSSize, NSString,
 };
 
-use crate::{DecorationMode, Decoration, WResponse, Color, ThemeDefault, NativeDecoration};
+use crate::{DecorationMode, Decoration, Error, WResponse, Color, ThemeDefault, NativeDecoration};
 
 /// Wrapper struct
 #[derive(PartialEq, Debug, Clone)]
 pub struct Wrapper {
 	pub ns_view: *mut void,		// NSView
-	pub rect:  *const void,		// NSRect
+	pub rect: NSRect,
+}
+
+impl Decoration {
+	/// What the renderer needs to create its surface, the `NSWindow` is closed with it
+	pub(crate) fn native(&self) -> NativeWindow
+	{
+		let window = self.frame.cast::<NSWindow>();
+		NativeWindow::new(RawWindow::AppKit { ns_view: self.backend.ns_view }, move || {
+			// the delegate keeps the window alive, it isn't released when closed
+			if let Some(window) = unsafe { window.as_ref() } { window.close() }
+		})
+	}
 }
 
 impl NativeDecoration for Decoration
 {
 	/// Creates the native window frame decoration for macOS
-	fn new(title: String, width: f64, height: f64, theme: ThemeDefault) -> Result<Self, WResponse>
+	fn new(title: String, width: f64, height: f64, theme: ThemeDefault) -> Result<Self, Error>
 	{
-		let Some(mtm) = MainThreadMarker::new() else { return Err(WResponse::UnexpectedError) };
+		let Some(mtm) = MainThreadMarker::new() else {
+			return Err(Error::new(WResponse::UnexpectedError).with_message("AppKit must be used from the main thread"))
+		};
 
 		let origin = NSPoint::new(10.0, -2.3);
 		let size = NSSize::new(width, height);
@@ -75,13 +91,13 @@ impl NativeDecoration for Decoration
 		window.makeKeyAndOrderFront(None);
 		unsafe { window.setReleasedWhenClosed(false) };
 
-		let Some(view) = window.contentView() else { return Err(WResponse::UnexpectedError) };
+		let Some(view) = window.contentView() else { return Err(WResponse::UnexpectedError.into()) };
 
 		window.center();
 		window.setContentMinSize(NSSize::new(width, height));
 
 		let Some(delegate) =
-			Delegate::new(window.clone()) else { return Err(WResponse::UnexpectedError) };
+			Delegate::new(window.clone()) else { return Err(WResponse::UnexpectedError.into()) };
 
 		window.setDelegate(Some(ProtocolObject::from_ref(&*delegate)));
 		window.makeKeyAndOrderFront(None);
@@ -91,26 +107,27 @@ impl NativeDecoration for Decoration
 		app.activate();
 
 		let backend = Wrapper {
-			ns_view: void::to_handle(Retained::<NSView>::as_ptr(&view).cast_mut()),
-			rect: void::to_handle(&rect),
+			ns_view: Retained::<NSView>::as_ptr(&view).cast_mut().cast::<void>(),
+			rect,
 		};
 
 		debug!("Creating NativeDecoration object");
 
 		Ok(Decoration {
 			mode: DecorationMode::ServerSide,
-			frame: void::to_handle(Retained::<NSWindow>::as_ptr(&window).cast_mut()),
+			frame: Retained::<NSWindow>::as_ptr(&window).cast::<void>(),
 			backend,
 		})
 	}
 
 	/// Apply blur effect on the window
-	fn apply_blur(&mut self) -> Result<(), WResponse>
+	fn apply_blur(&mut self) -> Result<(), Error>
 	{
-		let Some(mtm) = MainThreadMarker::new() else { return Err(WResponse::UnexpectedError) };
+		let Some(mtm) = MainThreadMarker::new() else {
+			return Err(Error::new(WResponse::UnexpectedError).with_message("AppKit must be used from the main thread"))
+		};
 
-		let backend = self.backend.clone();
-		let rect: NSRect = void::from_handle(backend.rect);
+		let rect = self.backend.rect;
 		/**
 		 * Blur view configs
 		 * Not using liquid glass for this part in specific
@@ -118,11 +135,12 @@ impl NativeDecoration for Decoration
 		 */
 		let alloc: Allocated<NSVisualEffectView> = NSVisualEffectView::alloc(mtm);
 		let blur_view_ptr = NSVisualEffectView::initWithFrame(alloc, rect);
-		let window: &NSWindow = void::from_handle(self.frame);
+		// the frame is only released when the app exits
+		let window: &NSWindow = unsafe { &*self.frame.cast::<NSWindow>() };
 
 		let Some(content) = window.contentView() else {
 			log::warn!("couldn't set blur");
-			return Err(WResponse::UnexpectedError);
+			return Err(WResponse::UnexpectedError.into());
 		};
 
 		let blur_view = blur_view_ptr.retain();
@@ -143,36 +161,40 @@ impl NativeDecoration for Decoration
 	}
 
 	// this is way easier in swift...
-	fn create_app_menu(&self, app_name: String) -> Result<(), WResponse>
+	/// The first submenu becomes the application menu, the `quit` item terminates the app as macOS expects
+	fn create_app_menu(
+		&self,
+		_desktop: &mut crate::Desktop,
+		menu: &crate::Menu,
+		handler: &crate::Handler,
+	) -> Result<(), Error>
 	{
-		let Some(mtm) = MainThreadMarker::new() else { return Err(WResponse::UnexpectedError) };
+		let Some(mtm) = MainThreadMarker::new() else {
+			return Err(Error::new(WResponse::UnexpectedError).with_message("AppKit must be used from the main thread"))
+		};
 		let app = NSApplication::sharedApplication(mtm);
+		let Some(target) = MenuTarget::new(handler) else { return Err(WResponse::UnexpectedError.into()) };
 
-		let item_menu = NSMenuItem::alloc(mtm);
-		let quit_item = unsafe { NSMenuItem::initWithTitle_action_keyEquivalent(
-			item_menu,
-			&NSString::from_str(dirty::format!("Quit {}", app_name).as_str()),
-			Some(sel!(terminate:)),
-			&NSString::from_str("q")
-		) };
-		unsafe { quit_item.setTarget(Some(&app)) };
-
-		let app_menu = NSMenu::new(mtm);
-			app_menu.addItem(&quit_item);
-
-		let app_menu_item = NSMenuItem::new(mtm);
 		let menubar = NSMenu::new(mtm);
-			menubar.addItem(&app_menu_item);
-
-		app_menu_item.setSubmenu(Some(&app_menu));
+			menubar.setAutoenablesItems(false);
+		for item in &menu.items {
+			menubar.addItem(&ns_menu_item(mtm, item, &target, &app));
+		}
 		app.setMainMenu(Some(&menubar));
 
+		// menu items only keep a weak reference to their target
+		core::mem::forget(target);
+
 		debug!("creating app menu");
 		Ok(())
 	}
 
+	/// Not implemented, the file dialogs will use `NSOpenPanel` directly
+	fn export_handle(&self) -> Result<String, Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
 	/// The default function to run the program, since it's required on macOS
-	fn run(&self)
+	fn run(&self, _window: &crate::Window, _desktop: &mut crate::Desktop, _handler: &crate::Handler)
 	{
 		use objc2::{class, runtime::AnyObject};
 		let raw: *mut NSApplication = unsafe {
@@ -189,7 +211,7 @@ impl NativeDecoration for Decoration
 	}
 
 	#[inline]
-	fn exit(&self) -> Result<(), WResponse>
+	fn exit(&self) -> Result<(), Error>
 	{
 		use objc2::{class, runtime::AnyObject};
 		let raw: *mut NSApplication = unsafe {
@@ -198,7 +220,7 @@ impl NativeDecoration for Decoration
 
 		unsafe { let _: *mut AnyObject = msg_send![raw, retain]; }
 		let Some(app) = (unsafe { Retained::from_raw(raw) }) else {
-			 return Err(WResponse::UnexpectedError);
+			 return Err(WResponse::UnexpectedError.into());
 		};
 
 		app.terminate(None);
@@ -211,6 +233,106 @@ impl NativeDecoration for Decoration
 	}*/
 }
 
+/// Converts a `MenuItem` and its children into a `NSMenuItem`
+fn ns_menu_item(
+	mtm: MainThreadMarker,
+	item: &crate::MenuItem,
+	target: &MenuTarget,
+	app: &NSApplication,
+) -> Retained<NSMenuItem>
+{
+	use crate::MenuItemKind;
+
+	if item.kind == MenuItemKind::Separator { return NSMenuItem::separatorItem(mtm) }
+
+	let quit = item.id == "quit";
+	let action = match (quit, item.children.is_empty()) {
+		(true, _) => Some(sel!(terminate:)),
+		(false, true) => Some(sel!(menuAction:)),
+		(false, false) => None,
+	};
+	let key = item.accelerator.map(|a| String::from(a.key)).unwrap_or_default();
+
+	let ns_item = unsafe { NSMenuItem::initWithTitle_action_keyEquivalent(
+		NSMenuItem::alloc(mtm),
+		&NSString::from_str(item.label.as_str()),
+		action,
+		&NSString::from_str(key.as_str()),
+	) };
+
+	if let Some(accelerator) = item.accelerator {
+		let mut mask = NSEventModifierFlags::empty();
+		if accelerator.primary { mask |= NSEventModifierFlags::Command }
+		if accelerator.shift { mask |= NSEventModifierFlags::Shift }
+		if accelerator.alt { mask |= NSEventModifierFlags::Option }
+		ns_item.setKeyEquivalentModifierMask(mask);
+	}
+
+	ns_item.setEnabled(item.enabled);
+	if let MenuItemKind::Check(true) | MenuItemKind::Radio(true) = item.kind {
+		ns_item.setState(NSControlStateValueOn);
+	}
+
+	if quit {
+		unsafe { ns_item.setTarget(Some(app)) };
+	} else if item.children.is_empty() {
+		ns_item.setTag(target.push(&item.id));
+		unsafe { ns_item.setTarget(Some(target)) };
+	} else {
+		let submenu = NSMenu::initWithTitle(NSMenu::alloc(mtm), &NSString::from_str(item.label.as_str()));
+			submenu.setAutoenablesItems(false);
+		for child in &item.children {
+			submenu.addItem(&ns_menu_item(mtm, child, target, app));
+		}
+		ns_item.setSubmenu(Some(&submenu));
+	}
+
+	ns_item
+}
+
+/// Ids of the menu items, the item tag is the position of its id
+struct MenuTargetIvars {
+	ids: RefCell<Vec<String>>,
+	/// The menu outlives `create_app_menu`, so it keeps its own handle of the handler
+	handler: crate::Handler,
+}
+
+define_class!(
+	#[unsafe(super = NSObject)]
+	#[thread_kind = MainThreadOnly]
+	#[ivars = MenuTargetIvars]
+	struct MenuTarget;
+
+	unsafe impl NSObjectProtocol for MenuTarget {}
+
+	impl MenuTarget {
+		#[unsafe(method(menuAction:))]
+		fn menu_action(&self, sender: &NSMenuItem)
+		{
+			let ids = self.ivars().ids.borrow();
+			let Some(id) = usize::try_from(sender.tag()).ok().and_then(|tag| ids.get(tag)) else { return };
+			(self.ivars().handler)(crate::Event::MenuAction { id: id.clone() });
+		}
+	}
+);
+
+impl MenuTarget {
+	fn new(handler: &crate::Handler) -> Option<Retained<Self>>
+	{
+		let mtm = MainThreadMarker::new()?;
+		let this = Self::alloc(mtm).set_ivars(MenuTargetIvars { ids: RefCell::new(Vec::new()), handler: handler.clone() });
+		Some(unsafe { msg_send![super(this), init] })
+	}
+
+	/// Keeps the id of an item, returns its tag
+	fn push(&self, id: &str) -> isize
+	{
+		let mut ids = self.ivars().ids.borrow_mut();
+		ids.push(String::from(id));
+		isize::try_from(ids.len().saturating_sub(1)).unwrap_or(isize::MAX)
+	}
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 struct AppDelegateIvars {
@@ -0,0 +1,102 @@
+/*!
+ * Default arrow cursor, wayland clients draw their own and without one the cursor over
+ * the window is whatever the compositor left there
+ */
+use dirty::{env, Error, WResponse, void};
+use log::debug;
+use super::shm::ShmBuffers;
+
+unsafe extern "C" {
+	fn request_wl_cursor(x: i32, y: i32) -> *mut void;
+}
+
+/// The arrow, `#` is the black outline, `.` the white fill and spaces are transparent
+const ARROW: [&str; 19] = [
+	"#",
+	"##",
+	"#.#",
+	"#..#",
+	"#...#",
+	"#....#",
+	"#.....#",
+	"#......#",
+	"#.......#",
+	"#........#",
+	"#.........#",
+	"#......#####",
+	"#...#..#",
+	"#..##..#",
+	"#.# #..#",
+	"##   #..#",
+	"#    #..#",
+	"      #..#",
+	"       ##",
+];
+/// `XCURSOR_SIZE` the arrow is drawn for, bigger sizes scale it by whole pixels
+const BASE_SIZE: u32 = 24;
+/// Biggest scale, cursors bigger than that are an accessibility setting we can't match with pixel art
+const MAX_SCALE: u32 = 8;
+/// Outline of the arrow, opaque black in premultiplied ARGB8888
+const OUTLINE: u32 = 0xff00_0000;
+/// Fill of the arrow, opaque white
+const FILL: u32 = 0xffff_ffff;
+
+/// The arrow shown over our surfaces, the compositor reads its buffer while it lives
+#[derive(Debug)]
+pub(crate) struct Cursor {
+	/// Attached to the cursor surface
+	_buffers: ShmBuffers,
+}
+
+impl Cursor {
+	/**
+	 * Draws the arrow at the size asked by `XCURSOR_SIZE` and gives it to the pointer
+	 *
+	 * # Errors
+	 *
+	 * `ProtocolNotSuported` without `wl_shm`, `UnexpectedError` without the cursor surface,
+	 * check `ShmBuffers::new` for the rest
+	 */
+	pub(crate) fn new() -> Result<Self, Error>
+	{
+		let scale = env::var("XCURSOR_SIZE")
+			.and_then(|size| size.parse::<u32>().ok())
+			.and_then(|size| size.checked_div(BASE_SIZE))
+			.map_or(1, |scale| scale.clamp(1, MAX_SCALE));
+
+		let columns = ARROW.iter().map(|row| row.len()).max().unwrap_or_default();
+		let width = u32::try_from(columns).unwrap_or_default().saturating_mul(scale);
+		let height = u32::try_from(ARROW.len()).unwrap_or_default().saturating_mul(scale);
+
+		// the hotspot is the tip, top left
+		let surface = unsafe { request_wl_cursor(0, 0) };
+		if surface.is_null() { return Err(WResponse::UnexpectedError.into()) }
+
+		// drawn once, a single buffer is enough
+		let mut buffers = ShmBuffers::new(1, width, height)?;
+		let mut frame = buffers.acquire().ok_or(WResponse::UnexpectedError)?;
+		let stride = frame.stride();
+		draw(frame.pixels(), stride, usize::try_from(scale).unwrap_or(1));
+		frame.present(surface);
+
+		debug!("cursor drawn at {:?}", buffers.size());
+		Ok(Self { _buffers: buffers })
+	}
+}
+
+/// Draws `ARROW` into `pixels`, every character becomes `scale` x `scale` pixels
+fn draw(pixels: &mut [u8], stride: usize, scale: usize)
+{
+	for (y, row) in pixels.chunks_exact_mut(stride).enumerate() {
+		let line = y.checked_div(scale).and_then(|line| ARROW.get(line)).map_or(&[][..], |line| line.as_bytes());
+
+		for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+			let color = match x.checked_div(scale).and_then(|column| line.get(column)) {
+				Some(b'#') => OUTLINE,
+				Some(b'.') => FILL,
+				_ => 0,
+			};
+			pixel.copy_from_slice(&color.to_ne_bytes());
+		}
+	}
+}
@@ -0,0 +1,239 @@
+/*!
+ * `com.canonical.dbusmenu` server, exports a `Menu` so the shell can draw it
+ * <https://github.com/AyatanaIndicators/libdbusmenu/blob/master/libdbusmenu-glib/dbus-menu.xml>
+ */
+use log::warn;
+use dirty::dbus::{BusSocket, Connection, Message, Value};
+use crate::{Accelerator, Event, Handler, Menu, MenuItem, MenuItemKind, Error, String, Vec};
+use super::serve_properties;
+
+/// The menu interface
+const DBUSMENU: &str = "com.canonical.dbusmenu";
+
+/// `aas` shortcut, like `[["Control", "Shift", "Q"]]`
+fn shortcut(accelerator: Accelerator) -> Value
+{
+	let modifiers = [
+		(accelerator.primary, "Control"),
+		(accelerator.alt, "Alt"),
+		(accelerator.shift, "Shift"),
+	];
+
+	let mut keys: Vec<Value> = modifiers
+		.into_iter()
+		.filter_map(|(pressed, name)| pressed.then_some(Value::from(name)))
+		.collect();
+	keys.push(Value::from(String::from(accelerator.key.to_ascii_uppercase())));
+
+	Value::Array(String::from("as"), Vec::from([Value::Array(String::from("s"), keys)]))
+}
+
+/// A menu item flattened, its dbusmenu id is the position on `MenuExporter::nodes`
+#[derive(Debug)]
+struct Node {
+	/// The item, without its children
+	item: MenuItem,
+	/// Ids of the children
+	children: Vec<i32>,
+}
+
+/// A menu exported on the bus
+#[derive(Debug)]
+pub(crate) struct MenuExporter {
+	/// Object path of the menu
+	path: &'static str,
+	/// Increased every time the layout changes
+	revision: u32,
+	/// Every item, the root is the first one
+	nodes: Vec<Node>,
+}
+
+impl MenuExporter {
+	/// Exports `menu` on `path`, calls are only answered after being routed by `handle`
+	pub(crate) fn new(path: &'static str, menu: &Menu) -> Self
+	{
+		let mut exporter = Self { path, revision: 1, nodes: Vec::new() };
+		exporter.flatten(menu);
+		exporter
+	}
+
+	/// Object path of the menu
+	pub(crate) fn path(&self) -> &'static str { self.path }
+
+	/// Rebuilds the nodes from `menu`, the root has the id `0`
+	fn flatten(&mut self, menu: &Menu)
+	{
+		self.nodes.clear();
+		let _ = self.push(&MenuItem::submenu("", menu.items.clone()));
+	}
+
+	/// Adds `item` and its children to the nodes, returns its id
+	fn push(&mut self, item: &MenuItem) -> i32
+	{
+		let index = self.nodes.len();
+		let id = i32::try_from(index).unwrap_or(i32::MAX);
+		self.nodes.push(Node {
+			item: MenuItem { children: Vec::new(), ..item.clone() },
+			children: Vec::new(),
+		});
+
+		let children = item.children.iter().map(|child| self.push(child)).collect();
+		if let Some(node) = self.nodes.get_mut(index) { node.children = children }
+		id
+	}
+
+	/// Returns the node with the dbusmenu `id`
+	fn node(&self, id: i32) -> Option<&Node>
+		{ usize::try_from(id).ok().and_then(|index| self.nodes.get(index)) }
+
+	/**
+	 * Replaces the exported menu and tells the shell to read it again
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::send`
+	 */
+	pub(crate) fn update(&mut self, bus: &mut Connection<BusSocket>, menu: &Menu) -> Result<(), Error>
+	{
+		self.flatten(menu);
+		self.revision = self.revision.wrapping_add(1);
+
+		let signal = Message::signal(self.path, DBUSMENU, "LayoutUpdated")
+			.arg(self.revision)
+			.arg(0i32);
+		bus.send(&signal).map(|_| ())
+	}
+
+	/// dbusmenu properties of the item `id`, filtered by `names` unless it is empty
+	fn properties(&self, id: i32, names: &[Value]) -> Value
+	{
+		let Some(node) = self.node(id) else { return Value::properties(Vec::new()) };
+		let item = &node.item;
+		let mut properties = Vec::new();
+
+		if !node.children.is_empty() || id == 0 {
+			properties.push(("children-display", Value::from("submenu")));
+		}
+
+		match item.kind {
+			MenuItemKind::Separator => properties.push(("type", Value::from("separator"))),
+			MenuItemKind::Check(checked) => {
+				properties.push(("toggle-type", Value::from("checkmark")));
+				properties.push(("toggle-state", Value::from(i32::from(checked))));
+			},
+			MenuItemKind::Radio(selected) => {
+				properties.push(("toggle-type", Value::from("radio")));
+				properties.push(("toggle-state", Value::from(i32::from(selected))));
+			},
+			MenuItemKind::Standard => {},
+		}
+
+		if id != 0 && item.kind != MenuItemKind::Separator {
+			properties.push(("label", Value::from(item.label.as_str())));
+			properties.push(("enabled", Value::from(item.enabled)));
+		}
+
+		if let Some(accelerator) = item.accelerator {
+			properties.push(("shortcut", shortcut(accelerator)));
+		}
+
+		properties.retain(|&(key, _)| names.is_empty() || names.iter().any(|n| n.as_str() == Some(key)));
+		Value::properties(properties)
+	}
+
+	/// `(ia{sv}av)` layout of the item `id`, `depth` levels of children are included, `-1` for all
+	fn layout(&self, id: i32, depth: i32, names: &[Value]) -> Value
+	{
+		let children = match self.node(id) {
+			Some(node) if depth != 0 => {
+				let next = if depth < 0 { depth } else { depth.saturating_sub(1) };
+				node.children.iter().map(|&child| self.layout(child, next, names).variant()).collect()
+			},
+			_ => Vec::new(),
+		};
+
+		Value::Struct(Vec::from([
+			Value::from(id),
+			self.properties(id, names),
+			Value::Array(String::from("v"), children),
+		]))
+	}
+
+	/// Sends `Event::MenuAction` if `event` is a click on an item with an id
+	fn activate(&self, id: i32, event: &str, handler: &Handler)
+	{
+		let Some(node) = self.node(id) else { return };
+		if event == "clicked" && node.item.enabled && !node.item.id.is_empty() {
+			handler(Event::MenuAction { id: node.item.id.clone() });
+		}
+	}
+
+	/// Answers the shell calls on the menu, returns `true` if `call` was for us
+	pub(crate) fn handle(&self, bus: &mut Connection<BusSocket>, call: &Message, handler: &Handler) -> bool
+	{
+		if call.path.as_deref() != Some(self.path) { return false }
+
+		let properties = Vec::from([
+			("Version", Value::from(3u32)),
+			("TextDirection", Value::from("ltr")),
+			("Status", Value::from("normal")),
+			("IconThemePath", Value::Array(String::from("s"), Vec::new())),
+		]);
+		if serve_properties(bus, call, DBUSMENU, properties) { return true }
+
+		let arg_i32 = |index| call.get(index).and_then(Value::as_i32).unwrap_or_default();
+		let arg_array = |index| call.get(index).and_then(Value::as_array).unwrap_or_default();
+		let ids = || arg_array(0).iter().filter_map(Value::as_i32);
+
+		let result = match call.member.as_deref().filter(|_| call.interface.as_deref() == Some(DBUSMENU)) {
+			Some("GetLayout") => {
+				let (id, depth) = (arg_i32(0), arg_i32(1));
+				if self.node(id).is_some() {
+					let layout = self.layout(id, depth, arg_array(2));
+					bus.reply(call, Vec::from([Value::from(self.revision), layout]))
+				} else {
+					bus.reply_error(call, "org.freedesktop.DBus.Error.InvalidArgs", "unknown menu item")
+				}
+			},
+			Some("GetGroupProperties") => {
+				let items = ids()
+					.filter(|&id| self.node(id).is_some())
+					.map(|id| Value::Struct(Vec::from([Value::from(id), self.properties(id, arg_array(1))])))
+					.collect();
+				bus.reply(call, Vec::from([Value::Array(String::from("(ia{sv})"), items)]))
+			},
+			Some("GetProperty") => {
+				let name: Vec<Value> = call.get(1).cloned().into_iter().collect();
+				match self.properties(arg_i32(0), &name).as_array().and_then(<[Value]>::first) {
+					Some(Value::DictEntry(_, value)) => bus.reply(call, Vec::from([Value::clone(value)])),
+					_ => bus.reply_error(call, "org.freedesktop.DBus.Error.InvalidArgs", "unknown property"),
+				}
+			},
+			Some("Event") => {
+				self.activate(arg_i32(0), call.get(1).and_then(Value::as_str).unwrap_or_default(), handler);
+				bus.reply(call, Vec::new())
+			},
+			Some("EventGroup") => {
+				let mut errors = Vec::new();
+				for event in arg_array(0).iter().filter_map(Value::as_struct) {
+					let id = event.first().and_then(Value::as_i32).unwrap_or(-1);
+					if self.node(id).is_none() { errors.push(Value::from(id)); continue }
+					self.activate(id, event.get(1).and_then(Value::as_str).unwrap_or_default(), handler);
+				}
+				bus.reply(call, Vec::from([Value::Array(String::from("i"), errors)]))
+			},
+			Some("AboutToShow") => bus.reply(call, Vec::from([Value::from(false)])),
+			Some("AboutToShowGroup") => {
+				let errors = ids().filter(|&id| self.node(id).is_none()).map(Value::from).collect();
+				bus.reply(call, Vec::from([
+					Value::Array(String::from("i"), Vec::new()),
+					Value::Array(String::from("i"), errors),
+				]))
+			},
+			_ => bus.reply_error(call, "org.freedesktop.DBus.Error.UnknownMethod", "unknown method"),
+		};
+
+		if let Err(e) = result { warn!("couldn't reply to the menu call {:?}: {e}", call.member) }
+		true
+	}
+}
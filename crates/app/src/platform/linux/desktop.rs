@@ -0,0 +1,329 @@
+use core::time::Duration;
+use log::warn;
+use dirty::dbus::{self, BusSocket, Connection, MessageType};
+use dirty::reactor::{self, Interest, Reactor, Token};
+use crate::{Event, Handler, FileDialog, Menu, Notification, Tray, ThemeDefault, Error, WResponse, String, Vec};
+use super::{
+	portal::Appearance,
+	single_instance::InstanceServer,
+	notifications::Notifications,
+	status_notifier::StatusNotifier,
+	dbusmenu::MenuExporter,
+	file_chooser::FileChooser,
+};
+
+/// Desktop integration services, all of them share the same session bus connection
+#[derive(Debug)]
+pub struct Desktop {
+	bus: Option<Connection<BusSocket>>,
+	app_name: String,
+	/// Last theme given to the app, `OsThemeChange` is relative to it
+	theme: Option<ThemeDefault>,
+	appearance: Appearance,
+	notifications: Notifications,
+	tray: Option<StatusNotifier>,
+	app_menu: Option<MenuExporter>,
+	file_chooser: FileChooser,
+	/// Set while this is the single running instance
+	instance: Option<InstanceServer>,
+	/// Another launch asked for the window to be raised, with its activation token if it had one
+	raise: Option<String>,
+	/// Everything the event loop waits on, `None` if it couldn't be created
+	reactor: Option<Reactor>,
+}
+
+/// Object path of the global menu
+const APP_MENU_PATH: &str = "/AppMenu";
+
+/// Reactor token of the display connection, added by the backend with `watch_display`
+pub const DISPLAY: Token = Token(0);
+/// Reactor token of the session bus
+const BUS: Token = Token(1);
+/// Reactor token of the single instance socket
+const INSTANCE: Token = Token(2);
+/// Launches sending their arguments use this bit plus their descriptor
+const INSTANCE_CLIENT: u64 = 1 << 33;
+/// Timers use this bit plus the token given by the app
+const TIMER: u64 = 1 << 32;
+
+impl Desktop {
+	/// Connects to the session bus
+	/// without it the app still works, but without any of the integrations
+	pub fn connect(app_name: &str) -> Self
+	{
+		let mut bus = dbus::session()
+			.inspect_err(|e| warn!("couldn't connect to the session bus: {e}"))
+			.ok();
+
+		let (appearance, notifications) = match bus.as_mut() {
+			Some(b) => (Appearance::subscribe(b), Notifications::subscribe(b)),
+			None => (Appearance::default(), Notifications::default()),
+		};
+
+		let mut reactor = Reactor::new()
+			.inspect_err(|e| warn!("couldn't create the event loop: {e}"))
+			.ok();
+
+		if let (Some(r), Some(b)) = (reactor.as_mut(), bus.as_ref())
+			&& let Err(e) = r.register(b.transport().fd(), BUS, Interest::READABLE)
+		{
+			warn!("the session bus won't be dispatched: {e}");
+		}
+
+		Self {
+			bus,
+			app_name: String::from(app_name),
+			theme: None,
+			appearance,
+			notifications,
+			tray: None,
+			app_menu: None,
+			file_chooser: FileChooser::default(),
+			instance: None,
+			raise: None,
+			reactor,
+		}
+	}
+
+	/// Applies the appearance set by the user (dark mode, accent color) on `theme`
+	pub fn system_theme(&mut self, mut theme: ThemeDefault) -> ThemeDefault
+	{
+		self.appearance.apply(&mut theme);
+		self.theme = Some(theme.clone());
+		theme
+	}
+
+	/// Keeps track of the theme set by the app
+	pub fn set_theme(&mut self, theme: ThemeDefault)
+		{ self.theme = Some(theme) }
+
+	/**
+	 * Adds the display connection to the event loop, it is reported with the `DISPLAY` token
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` if there is no event loop, the rest like `Reactor::register`
+	 */
+	pub fn watch_display(&mut self, fd: i32) -> Result<(), Error>
+	{
+		let Some(ref mut reactor) = self.reactor else { return Err(WResponse::MissingDependencies.into()) };
+		reactor.register(fd, DISPLAY, Interest::READABLE)
+	}
+
+	/**
+	 * Waits until the display, a desktop service or a timer is ready
+	 * returns right away if the bus already has messages, `dispatch_pending` handles them
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` if there is no event loop, the rest like `Reactor::poll`
+	 */
+	pub fn wait(&mut self, events: &mut Vec<reactor::Event>) -> Result<(), Error>
+	{
+		let Some(ref mut reactor) = self.reactor else { return Err(WResponse::MissingDependencies.into()) };
+		// messages read while waiting for a reply are off the socket, the reactor won't report them
+		let pending = self.bus.as_ref().is_some_and(Connection::has_pending);
+		reactor.poll(events, pending.then_some(Duration::ZERO))
+	}
+
+	/// Handles the bus messages that were read without being dispatched, call it after `dispatch`
+	pub fn dispatch_pending(&mut self, handler: &Handler)
+	{
+		if self.bus.as_ref().is_some_and(Connection::has_pending) { let _ = self.dispatch_bus(handler); }
+	}
+
+	/// Handles the desktop service or timer behind `token`, the results are sent to `handler`
+	pub fn dispatch(&mut self, token: Token, handler: &Handler)
+	{
+		if token.0 & TIMER != 0 {
+			let Ok(id) = u32::try_from(token.0 & !TIMER) else { return };
+			return handler(Event::Timer { token: id });
+		}
+
+		if token == INSTANCE && let Some(ref mut instance) = self.instance {
+			for fd in instance.accept() {
+				let Some(ref mut reactor) = self.reactor else { break };
+				let client = Token(INSTANCE_CLIENT | u64::from(fd.cast_unsigned()));
+				if let Err(e) = reactor.register(fd, client, Interest::READABLE) {
+					warn!("dropped a request from another instance: {e}");
+					instance.close(fd);
+				}
+			}
+			return;
+		}
+
+		if token.0 & INSTANCE_CLIENT != 0 && let Some(ref mut instance) = self.instance {
+			let Ok(fd) = i32::try_from(token.0 & !INSTANCE_CLIENT) else { return };
+			let Some(request) = instance.read(fd) else { return };
+			self.raise = Some(request.token.unwrap_or_default());
+			return handler(Event::ExternalRequest { args: request.args });
+		}
+
+		// a lost bus is dropped, closing its socket also takes it out of the reactor
+		if token == BUS { let _ = self.dispatch_bus(handler); }
+	}
+
+	/**
+	 * Emits `Event::Timer` with `token` after `after`, and then every `interval` if there is one
+	 * setting a token that is already armed restarts it
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` if there is no event loop, `InvalidRequest` if `interval` is zero,
+	 * `OutOfBounds` if the process ran out of descriptors
+	 */
+	pub fn set_timer(&mut self, token: u32, after: Duration, interval: Option<Duration>) -> Result<(), Error>
+	{
+		let Some(ref mut reactor) = self.reactor else { return Err(WResponse::MissingDependencies.into()) };
+		reactor.set_timer(Token(TIMER | u64::from(token)), after, interval)
+	}
+
+	/// Stops the timer of `token`, returns `false` if it wasn't armed
+	pub fn cancel_timer(&mut self, token: u32) -> bool
+	{
+		self.reactor.as_mut().is_some_and(|reactor| reactor.cancel_timer(Token(TIMER | u64::from(token))))
+	}
+
+	/// Returns `Some` once after another launch asked for the window to be raised,
+	/// with the activation token of that launch, empty if it didn't have one
+	pub fn take_raise(&mut self) -> Option<String>
+		{ self.raise.take() }
+
+	/**
+	 * Becomes the single running instance of the app
+	 *
+	 * # Errors
+	 *
+	 * `ChannelInUse` if another instance is running, `args` were forwarded to it
+	 */
+	pub fn single_instance(&mut self, args: &[&str]) -> Result<(), Error>
+	{
+		if self.instance.is_some() { return Ok(()) }
+		let instance = InstanceServer::acquire(&self.app_name, args)?.ok_or(WResponse::ChannelInUse)?;
+
+		if let Some(ref mut reactor) = self.reactor
+			&& let Err(e) = reactor.register(instance.fd(), INSTANCE, Interest::READABLE)
+		{
+			warn!("other launches won't be noticed: {e}");
+		}
+		self.instance = Some(instance);
+		Ok(())
+	}
+
+	/// Handles everything that arrived on the bus
+	/// if the bus is lost, the integrations stop working and `ConnectionLost` is returned
+	fn dispatch_bus(&mut self, handler: &Handler) -> Result<(), Error>
+	{
+		let Some(ref mut bus) = self.bus else { return Err(WResponse::ConnectionLost.into()) };
+
+		loop {
+			let message = match bus.poll() {
+				Ok(Some(m)) => m,
+				Ok(None) => return Ok(()),
+				Err(e) => {
+					warn!("lost connection with the session bus: {e}");
+					self.bus = None;
+					return Err(e);
+				},
+			};
+
+			if self.appearance.handle(&message) {
+				let Some(ref mut theme) = self.theme else { continue };
+				let mut new_theme = theme.clone();
+				self.appearance.apply(&mut new_theme);
+
+				if new_theme != *theme {
+					*theme = new_theme.clone();
+					handler(Event::OsThemeChange { new_theme });
+				}
+				continue;
+			}
+
+			if let Some(event) = self.notifications.handle(&message) {
+				handler(event);
+				continue;
+			}
+
+			if let Some(event) = self.file_chooser.handle(bus, &message) {
+				handler(event);
+				continue;
+			}
+
+			if let Some(ref mut tray) = self.tray && tray.handle(bus, &message, handler) { continue }
+			if let Some(ref menu) = self.app_menu && menu.handle(bus, &message, handler) { continue }
+
+			// nobody else should be calling us, but the caller would wait for a reply until it times out
+			if message.kind == MessageType::MethodCall {
+				let unknown = bus.reply_error(&message, "org.freedesktop.DBus.Error.UnknownMethod", "unknown method");
+				if let Err(e) = unknown { warn!("couldn't reply to {:?}: {e}", message.member) }
+			}
+		}
+	}
+
+	/// Sends a notification, returns its id
+	pub fn notify(&mut self, notification: &Notification) -> Result<u32, Error>
+	{
+		let Some(ref mut bus) = self.bus else { return Err(WResponse::MissingDependencies.into()) };
+		self.notifications.notify(bus, &self.app_name, notification)
+	}
+
+	/// Closes a notification sent by `notify`
+	pub fn close_notification(&mut self, id: u32) -> Result<(), Error>
+	{
+		let Some(ref mut bus) = self.bus else { return Err(WResponse::MissingDependencies.into()) };
+		self.notifications.close(bus, id)
+	}
+
+	/// Shows the tray icon, or updates it if it is already visible
+	pub fn set_tray(&mut self, tray: Tray) -> Result<(), Error>
+	{
+		let Some(ref mut bus) = self.bus else { return Err(WResponse::MissingDependencies.into()) };
+		if let Some(ref mut notifier) = self.tray { return notifier.update(bus, tray) }
+
+		let notifier = StatusNotifier::new(bus, &self.app_name, tray);
+		let exported = notifier.export(bus);
+
+		// without a watcher the item is kept, it is registered once a watcher starts
+		if exported.as_ref().map_or_else(|e| *e == WResponse::MissingDependencies, |()| true) { self.tray = Some(notifier) }
+		exported
+	}
+
+	/// Changes the unread count shown on the tray icon
+	pub fn set_unread(&mut self, unread: u32) -> Result<(), Error>
+	{
+		let (Some(bus), Some(notifier)) = (self.bus.as_mut(), self.tray.as_mut()) else {
+			return Err(WResponse::InvalidRequest.into());
+		};
+		notifier.set_unread(bus, unread)
+	}
+
+	/// Removes the tray icon
+	pub fn remove_tray(&mut self) -> Result<(), Error>
+	{
+		let (Some(bus), Some(notifier)) = (self.bus.as_mut(), self.tray.take()) else {
+			return Err(WResponse::InvalidRequest.into());
+		};
+		notifier.unregister(bus)
+	}
+
+	/// Exports the global menu, or replaces it if it was already exported
+	/// returns the bus name and object path where it can be found
+	pub fn export_app_menu(&mut self, menu: &Menu) -> Result<(String, &'static str), Error>
+	{
+		let Some(ref mut bus) = self.bus else { return Err(WResponse::MissingDependencies.into()) };
+
+		match self.app_menu {
+			Some(ref mut exporter) => exporter.update(bus, menu)?,
+			None => self.app_menu = Some(MenuExporter::new(APP_MENU_PATH, menu)),
+		}
+		Ok((String::from(bus.unique_name()), APP_MENU_PATH))
+	}
+
+	/// Shows the portal file dialog, `parent` is the exported window handle or empty
+	/// returns the id sent back with `Event::FileDialogResult`
+	pub fn file_dialog(&mut self, parent: &str, dialog: &FileDialog, save: bool) -> Result<u32, Error>
+	{
+		let Some(ref mut bus) = self.bus else { return Err(WResponse::MissingDependencies.into()) };
+		self.file_chooser.open(bus, parent, dialog, save)
+	}
+}
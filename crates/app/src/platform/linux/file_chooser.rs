@@ -0,0 +1,173 @@
+/*!
+ * `org.freedesktop.portal.FileChooser` client, works inside and outside of Flatpak
+ * <https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.FileChooser.html>
+ */
+use log::{debug, warn};
+use dirty::dbus::{BusSocket, Connection, MatchRule, Message, Value};
+use dirty::format;
+use crate::{Event, FileDialog, Error, String, Vec};
+use super::portal::{PORTAL, PORTAL_PATH};
+
+/// The file chooser interface
+const FILE_CHOOSER: &str = "org.freedesktop.portal.FileChooser";
+/// Every portal call returns a request object that emits the result
+const REQUEST: &str = "org.freedesktop.portal.Request";
+
+/// Dialogs waiting for the user
+#[derive(Debug, Default)]
+pub(crate) struct FileChooser {
+	/// Open dialogs as `(id, request path)`
+	pending: Vec<(u32, String)>,
+	/// Last id given to a dialog
+	last_id: u32,
+}
+
+/// `Response` signal of the request object on `path`
+fn response_rule(path: &str) -> MatchRule
+	{ MatchRule::signal(REQUEST, "Response").path(path) }
+
+/// `a(sa(us))` filters, the `u` is `0` for globs and `1` for mime types
+fn filters(dialog: &FileDialog) -> Value
+{
+	let filters = dialog.filters.iter().map(|(name, patterns)| {
+		let entries = patterns.iter().map(|pattern| {
+			let kind = u32::from(pattern.contains('/'));
+			Value::Struct(Vec::from([Value::from(kind), Value::from(pattern.as_str())]))
+		}).collect();
+
+		Value::Struct(Vec::from([
+			Value::from(name.as_str()),
+			Value::Array(String::from("(us)"), entries),
+		]))
+	}).collect();
+
+	Value::Array(String::from("(sa(us))"), filters)
+}
+
+/// `ay` nul terminated path, the portal doesn't accept plain strings for folders
+fn path_bytes(path: &str) -> Value
+{
+	let bytes = path.bytes().chain([0]).map(Value::Byte).collect();
+	Value::Array(String::from("y"), bytes)
+}
+
+/// Converts a `file://` uri into a path, decoding the `%XX` escapes
+fn uri_to_path(uri: &str) -> Option<String>
+{
+	let encoded = uri.strip_prefix("file://")?;
+	let mut bytes = Vec::with_capacity(encoded.len());
+	let mut chars = encoded.bytes();
+
+	while let Some(b) = chars.next() {
+		if b != b'%' { bytes.push(b); continue }
+		let hex = [chars.next()?, chars.next()?];
+		let decoded = core::str::from_utf8(&hex).ok().and_then(|h| u8::from_str_radix(h, 16).ok())?;
+		bytes.push(decoded);
+	}
+
+	String::from_utf8(bytes).ok()
+}
+
+impl FileChooser {
+	/**
+	 * Opens a dialog, returns the id sent back with `Event::FileDialogResult`
+	 * `parent` is the exported window handle, empty if there is none
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the portal refused the request, or the bus errors
+	 */
+	pub(crate) fn open(
+		&mut self,
+		bus: &mut Connection<BusSocket>,
+		parent: &str,
+		dialog: &FileDialog,
+		save: bool,
+	) -> Result<u32, Error>
+	{
+		let id = self.last_id.wrapping_add(1);
+		self.last_id = id;
+
+		// the request path is predictable, subscribing before the call avoids missing a fast answer
+		let token = format!("wt{id}");
+		let sender = bus.unique_name().trim_start_matches(':').replace('.', "_");
+		let expected = format!("{PORTAL_PATH}/request/{sender}/{token}");
+		bus.add_match(&response_rule(&expected))?;
+
+		let mut options = Vec::from([
+			("handle_token", Value::from(token.as_str())),
+			("modal", Value::from(true)),
+		]);
+
+		if let Some(ref label) = dialog.accept_label {
+			options.push(("accept_label", Value::from(label.as_str())));
+		}
+		if !dialog.filters.is_empty() {
+			options.push(("filters", filters(dialog)));
+		}
+		if let Some(ref folder) = dialog.current_folder {
+			options.push(("current_folder", path_bytes(folder)));
+		}
+
+		if save {
+			if let Some(ref name) = dialog.current_name {
+				options.push(("current_name", Value::from(name.as_str())));
+			}
+		} else {
+			options.push(("multiple", Value::from(dialog.multiple)));
+			options.push(("directory", Value::from(dialog.directory)));
+		}
+
+		let method = if save { "SaveFile" } else { "OpenFile" };
+		let call = Message::method_call(PORTAL, PORTAL_PATH, FILE_CHOOSER, method)
+			.arg(parent)
+			.arg(dialog.title.as_str())
+			.arg(Value::properties(options));
+
+		let reply = match bus.call(&call) {
+			Ok(reply) => reply,
+			Err(e) => {
+				warn!("the file chooser portal refused the request: {e}");
+				let _ = bus.remove_match(&response_rule(&expected));
+				return Err(e);
+			},
+		};
+
+		// portals older than 0.9 ignore `handle_token` and pick their own path
+		let request = reply.get(0).and_then(Value::as_str).map_or(expected.clone(), String::from);
+		if request != expected {
+			let _ = bus.remove_match(&response_rule(&expected));
+			bus.add_match(&response_rule(&request))?;
+		}
+
+		debug!("file dialog {id} waiting on {request}");
+		self.pending.push((id, request));
+		Ok(id)
+	}
+
+	/// Converts the answer of a dialog into an event
+	/// returns `None` if the message was not for us
+	pub(crate) fn handle(&mut self, bus: &mut Connection<BusSocket>, message: &Message) -> Option<Event>
+	{
+		if !message.is_signal(REQUEST, "Response") { return None }
+
+		let index = self.pending.iter().position(|(_, path)| message.path.as_deref() == Some(path.as_str()))?;
+		let (id, path) = self.pending.swap_remove(index);
+		if let Err(e) = bus.remove_match(&response_rule(&path)) { warn!("couldn't unsubscribe from {path}: {e}") }
+
+		// 0: success, 1: cancelled by the user, 2: failed
+		let files = match message.get(0).and_then(Value::as_u32) {
+			Some(0) => message.get(1)
+				.and_then(|results| results.get("uris"))
+				.and_then(Value::as_array)
+				.unwrap_or_default()
+				.iter()
+				.filter_map(Value::as_str)
+				.filter_map(uri_to_path)
+				.collect(),
+			_ => Vec::new(),
+		};
+
+		Some(Event::FileDialogResult { id, files })
+	}
+}
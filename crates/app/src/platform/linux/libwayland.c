@@ -1,18 +1,125 @@
 #include <wayland-client.h>
+#include <errno.h>
 #include <stdio.h>
+#include <stdlib.h>
 #include <string.h>
+#include <sys/socket.h>
 #include "xdg-shell-client-protocol.h"
+#include "appmenu-client-protocol.h"
+#include "xdg-foreign-unstable-v2-client-protocol.h"
+#include "xdg-activation-v1-client-protocol.h"
 
-struct wl_compositor *compositor;
 struct wl_shm *shm;
-struct wl_shell *shell;
+// KDE global menu, NULL on other compositors
+struct org_kde_kwin_appmenu_manager *appmenu_manager;
+// used to give portal dialogs a parent window, NULL if unsupported
+struct zxdg_exporter_v2 *exporter;
+// used to raise the window when the app is launched again, NULL if unsupported
+struct xdg_activation_v1 *activation;
+// the pointer of the seat gets our cursor when it enters a surface, NULL without a mouse
+struct wl_seat *seat;
+static struct wl_pointer *pointer;
+// filled by `request_wl_cursor`, NULL leaves the cursor to the compositor
+static struct wl_surface *cursor_surface;
+static int32_t cursor_hotspot_x, cursor_hotspot_y;
+// registry names of the optional globals above, to tell which one a `global_remove` is about
+static uint32_t appmenu_manager_name, exporter_name, activation_name, seat_name;
 
+/*
+ * keep this in sync with `WlStatus` on wayland.rs
+ */
+enum wl_status {
+    WL_STATUS_OK = 0,
+    WL_STATUS_NO_DISPLAY = 1,
+    WL_STATUS_NO_COMPOSITOR = 2,
+    WL_STATUS_NO_WM_BASE = 3,
+    WL_STATUS_CONNECTION_ERROR = 4,
+};
+
+// the registry keeps a pointer to this, so it must outlive `request_wl_surface`
 struct state {
     struct wl_compositor *compositor;
     struct xdg_wm_base *wm_base;
     struct wl_registry * registry;
 };
 
+// what `request_wl_window_events` reports, keep in sync with `wayland.rs`
+enum window_event {
+    WINDOW_RESIZED = 1,
+    WINDOW_CLOSE = 2,
+};
+
+// the toplevel behind a `WindowSurface`, freed by `request_wl_close`
+struct window {
+    struct state *state;
+    struct xdg_surface *xdg_surface;
+    struct xdg_toplevel *toplevel;
+    // last size given by the compositor, 0 until it picks one
+    int32_t width, height;
+    // `enum window_event` bits not taken yet
+    int events;
+};
+
+// sets our cursor every time the pointer enters one of our surfaces, nothing else is used yet
+static void wl_pointer_enter(
+    void *data,
+    struct wl_pointer *wl_pointer,
+    uint32_t serial,
+    struct wl_surface *surface,
+    wl_fixed_t x,
+    wl_fixed_t y
+) {
+    if (cursor_surface)
+        wl_pointer_set_cursor(wl_pointer, serial, cursor_surface, cursor_hotspot_x, cursor_hotspot_y);
+}
+
+static void wl_pointer_leave(void *data, struct wl_pointer *wl_pointer, uint32_t serial, struct wl_surface *surface) {}
+
+static void wl_pointer_motion(void *data, struct wl_pointer *wl_pointer, uint32_t time, wl_fixed_t x, wl_fixed_t y) {}
+
+static void wl_pointer_button(
+    void *data,
+    struct wl_pointer *wl_pointer,
+    uint32_t serial,
+    uint32_t time,
+    uint32_t button,
+    uint32_t state
+) {}
+
+static void wl_pointer_axis(void *data, struct wl_pointer *wl_pointer, uint32_t time, uint32_t axis, wl_fixed_t value) {}
+
+static const struct wl_pointer_listener wl_pointer_listener = {
+    .enter = wl_pointer_enter,
+    .leave = wl_pointer_leave,
+    .motion = wl_pointer_motion,
+    .button = wl_pointer_button,
+    .axis = wl_pointer_axis
+};
+
+static void wl_seat_capabilities(void *data, struct wl_seat *wl_seat, uint32_t capabilities)
+{
+    int has_pointer = capabilities & WL_SEAT_CAPABILITY_POINTER;
+
+    if (has_pointer && !pointer) {
+        pointer = wl_seat_get_pointer(wl_seat);
+        wl_pointer_add_listener(pointer, &wl_pointer_listener, NULL);
+    } else if (!has_pointer && pointer) {
+        wl_pointer_destroy(pointer);
+        pointer = NULL;
+    }
+}
+
+static const struct wl_seat_listener wl_seat_listener = {
+    .capabilities = wl_seat_capabilities
+};
+
+// destroys `proxy` if it was bound, the globals are bound again on the next connection
+static void destroy_global(void *proxy)
+{
+    if (proxy)
+        wl_proxy_destroy(proxy);
+}
+
 void registry_global_handler
 (
     void *data,
@@ -33,15 +140,54 @@ void registry_global_handler
         state->wm_base =
             wl_registry_bind(registry, name,
                              &xdg_wm_base_interface, 1);
+    else if (strcmp(interface, "org_kde_kwin_appmenu_manager") == 0) {
+        appmenu_manager = wl_registry_bind(registry, name,
+                                &org_kde_kwin_appmenu_manager_interface, 1);
+        appmenu_manager_name = name;
+    } else if (strcmp(interface, "zxdg_exporter_v2") == 0) {
+        exporter = wl_registry_bind(registry, name,
+                                &zxdg_exporter_v2_interface, 1);
+        exporter_name = name;
+    } else if (strcmp(interface, "xdg_activation_v1") == 0) {
+        activation = wl_registry_bind(registry, name,
+                                &xdg_activation_v1_interface, 1);
+        activation_name = name;
+    }
+    // the first seat only, a second mouse gets the compositor cursor
+    else if (strcmp(interface, "wl_seat") == 0 && !seat) {
+        seat = wl_registry_bind(registry, name,
+                                &wl_seat_interface, 1);
+        seat_name = name;
+        wl_seat_add_listener(seat, &wl_seat_listener, NULL);
+    }
 }
 
+/*
+ * A global went away, like a seat unplugged or a compositor module unloaded
+ * the optional globals we bound are dropped and the features using them report they are unsupported,
+ * a seat announced later is bound again by `registry_global_handler`
+ */
 void registry_global_remove_handler
 (
     void *data,
     struct wl_registry *registry,
     uint32_t name
 ) {
-    printf("removed: %u\n", name);
+    if (seat && name == seat_name) {
+        destroy_global(pointer);
+        destroy_global(seat);
+        pointer = NULL;
+        seat = NULL;
+    } else if (appmenu_manager && name == appmenu_manager_name) {
+        destroy_global(appmenu_manager);
+        appmenu_manager = NULL;
+    } else if (exporter && name == exporter_name) {
+        destroy_global(exporter);
+        exporter = NULL;
+    } else if (activation && name == activation_name) {
+        destroy_global(activation);
+        activation = NULL;
+    }
 }
 
 struct wl_registry_listener registry_listener = {
@@ -49,6 +195,19 @@ struct wl_registry_listener registry_listener = {
     .global_remove = registry_global_remove_handler
 };
 
+// the compositor will flag us as unresponsive if the ping is ignored
+static void xdg_wm_base_ping(
+    void *data,
+    struct xdg_wm_base *wm_base,
+    uint32_t serial
+) {
+    xdg_wm_base_pong(wm_base, serial);
+}
+
+static const struct xdg_wm_base_listener xdg_wm_base_listener = {
+    .ping = xdg_wm_base_ping
+};
+
 static void xdg_surface_configure(
     void *data,
     struct xdg_surface *surface,
@@ -61,56 +220,482 @@ static const struct xdg_surface_listener xdg_surface_listener = {
     .configure = xdg_surface_configure
 };
 
+// a size of 0 leaves it to us, so only a new size the compositor picked is reported
+static void xdg_toplevel_configure(
+    void *data,
+    struct xdg_toplevel *toplevel,
+    int32_t width,
+    int32_t height,
+    struct wl_array *states
+) {
+    struct window *window = data;
+    if (width <= 0 || height <= 0 || (width == window->width && height == window->height))
+        return;
+
+    window->width = width;
+    window->height = height;
+    window->events |= WINDOW_RESIZED;
+}
+
+static void xdg_toplevel_close(void *data, struct xdg_toplevel *toplevel)
+{
+    struct window *window = data;
+    window->events |= WINDOW_CLOSE;
+}
+
+// `configure_bounds` and `wm_capabilities` are never sent, `xdg_wm_base` is bound at version 1
+static const struct xdg_toplevel_listener xdg_toplevel_listener = {
+    .configure = xdg_toplevel_configure,
+    .close = xdg_toplevel_close
+};
+
+/*
+ * Last error raised on the display connection
+ * `code` is the errno of the failure, if it is `EPROTO` then the compositor
+ * sent a protocol error and the other fields are filled
+ */
+struct WlError {
+    int code;
+    uint32_t protocol_code;
+    uint32_t object_id;
+    const char * interface;
+};
+
+/*
+ * on failure only `status` and `error` are set, the connection is already closed
+ */
 struct WindowSurface {
     struct wl_display * display;
-    struct wl_registry_listener * listener;
+    struct wl_registry * registry;
     struct wl_surface * surface;
-    struct xdg_toplevel * toplevel;
+    struct window * window;
+    int status;
+    struct WlError error;
 };
 
-struct WindowSurface request_wl_surface(int width, int height)
+struct WlError request_wl_error(struct wl_display *display)
 {
-    struct state state = {0};
+    struct WlError error = {0};
+
+    if (!display) {
+        error.code = errno;
+        return error;
+    }
+
+    error.code = wl_display_get_error(display);
+    if (error.code != EPROTO)
+        return error;
+
+    const struct wl_interface *interface = NULL;
+    error.protocol_code =
+        wl_display_get_protocol_error(display, &interface, &error.object_id);
+
+    if (interface)
+        error.interface = interface->name;
+
+    return error;
+}
+
+// destroys the globals of the connection, frees `state` and disconnects
+static void destroy_connection(struct wl_display *display, struct state *state)
+{
+    if (state) {
+        destroy_global(state->compositor);
+        destroy_global(state->wm_base);
+        if (state->registry)
+            wl_registry_destroy(state->registry);
+        free(state);
+    }
+
+    destroy_global(pointer);
+    destroy_global(seat);
+    destroy_global(shm);
+    destroy_global(appmenu_manager);
+    destroy_global(exporter);
+    destroy_global(activation);
+    pointer = NULL;
+    seat = NULL;
+    shm = NULL;
+    appmenu_manager = NULL;
+    exporter = NULL;
+    activation = NULL;
+
+    if (cursor_surface)
+        wl_surface_destroy(cursor_surface);
+    cursor_surface = NULL;
 
+    wl_display_disconnect(display);
+}
+
+// destroys the toplevel, its surface and frees `window`, the connection is left open
+static void destroy_window(struct wl_surface *surface, struct window *window)
+{
+    if (window && window->toplevel)
+        xdg_toplevel_destroy(window->toplevel);
+    if (window && window->xdg_surface)
+        xdg_surface_destroy(window->xdg_surface);
+    if (surface)
+        wl_surface_destroy(surface);
+    free(window);
+}
+
+/*
+ * Ends a failed `request_wl_surface`: keeps the error of the display, destroys what was created,
+ * frees `state` and closes the connection
+ */
+static struct WindowSurface wl_setup_failed(
+    struct WindowSurface wl_response,
+    struct state *state,
+    enum wl_status status
+) {
+    // the error is read before the display it belongs to is gone
+    wl_response.error = request_wl_error(wl_response.display);
+    wl_response.status = status;
+
+    destroy_window(wl_response.surface, wl_response.window);
+    destroy_connection(wl_response.display, state);
+
+    struct WindowSurface failed = {0};
+    failed.status = wl_response.status;
+    failed.error = wl_response.error;
+    return failed;
+}
+
+struct WindowSurface request_wl_surface(void)
+{
     struct WindowSurface wl_response = {0};
     wl_response.display = wl_display_connect(NULL);
 
-    if (!wl_response.display)
+    if (!wl_response.display) {
+        wl_response.status = WL_STATUS_NO_DISPLAY;
+        wl_response.error = request_wl_error(NULL);
         return wl_response;
+    }
+
+    struct state *state = calloc(1, sizeof(struct state));
+    if (!state)
+        return wl_setup_failed(wl_response, NULL, WL_STATUS_CONNECTION_ERROR);
+
+    state->registry = wl_display_get_registry(wl_response.display);
+    wl_response.registry = state->registry;
+    wl_registry_add_listener(state->registry, &registry_listener, state);
 
-    state.registry = wl_display_get_registry(wl_response.display);
-    wl_response.listener = &registry_listener;
-    wl_registry_add_listener(state.registry, &registry_listener, &state);
+    if (wl_display_roundtrip(wl_response.display) == -1)
+        return wl_setup_failed(wl_response, state, WL_STATUS_CONNECTION_ERROR);
 
-    wl_display_roundtrip(wl_response.display);
-    //printf("compositor=%p\n", state.compositor);
-    //printf("wm_base=%p\n", state.wm_base);
+    if (!state->compositor)
+        return wl_setup_failed(wl_response, state, WL_STATUS_NO_COMPOSITOR);
+
+    if (!state->wm_base)
+        return wl_setup_failed(wl_response, state, WL_STATUS_NO_WM_BASE);
+
+    xdg_wm_base_add_listener(state->wm_base, &xdg_wm_base_listener, NULL);
+
+    struct window *window = calloc(1, sizeof(struct window));
+    if (!window)
+        return wl_setup_failed(wl_response, state, WL_STATUS_CONNECTION_ERROR);
+
+    window->state = state;
+    wl_response.window = window;
 
     wl_response.surface =
-        wl_compositor_create_surface(state.compositor);
+        wl_compositor_create_surface(state->compositor);
+
+    window->xdg_surface =
+        xdg_wm_base_get_xdg_surface(state->wm_base, wl_response.surface);
 
-    struct xdg_surface *xdg_surface =
-        xdg_wm_base_get_xdg_surface(state.wm_base, wl_response.surface);
+    xdg_surface_add_listener(window->xdg_surface, &xdg_surface_listener, NULL);
 
-    xdg_surface_add_listener(xdg_surface, &xdg_surface_listener, NULL);
+    window->toplevel =
+        xdg_surface_get_toplevel(window->xdg_surface);
 
-    wl_response.toplevel =
-        xdg_surface_get_toplevel(xdg_surface);
+    xdg_toplevel_add_listener(window->toplevel, &xdg_toplevel_listener, window);
 
-    xdg_toplevel_set_title(wl_response.toplevel, "title");
+    xdg_toplevel_set_title(window->toplevel, "title");
     wl_surface_commit(wl_response.surface);
 
+    // a protocol error here would only be noticed on the next dispatch
+    if (wl_display_roundtrip(wl_response.display) == -1)
+        return wl_setup_failed(wl_response, state, WL_STATUS_CONNECTION_ERROR);
+
+    // gets its role and buffer from `request_wl_cursor`
+    cursor_surface = wl_compositor_create_surface(state->compositor);
     return wl_response;
 }
 
-void loop_wl_event(struct wl_display *display)
+/*
+ * Makes the surface returned the cursor over our surfaces, `x` and `y` are the hotspot in it
+ * draw on it with `request_wl_present`, returns NULL if the surface couldn't be created
+ */
+struct wl_surface *request_wl_cursor(int32_t x, int32_t y)
+{
+    cursor_hotspot_x = x;
+    cursor_hotspot_y = y;
+    return cursor_surface;
+}
+
+// errno of a broken connection, the display error has priority over the syscall one
+static int wl_failure(struct wl_display *display)
 {
-    while (1) {
-        wl_display_dispatch(display);
+    int code = wl_display_get_error(display);
+    return code ? code : errno;
+}
+
+int request_wl_fd(struct wl_display *display)
+    { return wl_display_get_fd(display); }
+
+/*
+ * First half of an event loop iteration, call it before waiting on the display fd
+ * dispatches what is already queued, takes the read intent and flushes the requests
+ * returns 0, or the errno that terminated the connection
+ */
+int prepare_wl_read(struct wl_display *display)
+{
+    while (wl_display_prepare_read(display) != 0)
+        if (wl_display_dispatch_pending(display) == -1)
+            return wl_failure(display);
+
+    if (wl_display_flush(display) == -1 && errno != EAGAIN) {
+        wl_display_cancel_read(display);
+        return wl_failure(display);
     }
+
+    return 0;
 }
 
-void request_wl_disconnect(struct wl_display *display)
+/*
+ * Second half, call it after the wait even if it failed
+ * reads the events if the display fd is `readable`, gives the read intent back otherwise, and dispatches them
+ * returns 0, or the errno that terminated the connection
+ */
+int finish_wl_read(struct wl_display *display, int readable)
 {
-    wl_display_disconnect(display);
+    if (readable) {
+        if (wl_display_read_events(display) == -1)
+            return wl_failure(display);
+    } else
+        wl_display_cancel_read(display);
+
+    return wl_display_dispatch_pending(display) == -1 ? wl_failure(display) : 0;
+}
+
+/*
+ * Takes the `enum window_event` bits received since the last call, `width` and `height`
+ * are set to the size of the last configure
+ */
+int request_wl_window_events(struct window *window, int32_t *width, int32_t *height)
+{
+    int events = window->events;
+    window->events = 0;
+    *width = window->width;
+    *height = window->height;
+    return events;
+}
+
+/*
+ * Tells the compositor where the dbusmenu of the surface is exported
+ * the appmenu object is kept as the surface user data, so calling it again only updates the address
+ * returns -1 if the compositor doesn't support `org_kde_kwin_appmenu_manager`
+ */
+int request_wl_appmenu(
+    struct wl_surface *surface,
+    const char *service,
+    const char *path
+) {
+    if (!appmenu_manager)
+        return -1;
+
+    struct org_kde_kwin_appmenu *appmenu = wl_surface_get_user_data(surface);
+    if (!appmenu) {
+        appmenu = org_kde_kwin_appmenu_manager_create(appmenu_manager, surface);
+        wl_surface_set_user_data(surface, appmenu);
+    }
+
+    org_kde_kwin_appmenu_set_address(appmenu, service, path);
+    return 0;
+}
+
+// the last exported toplevel, its handle is valid while `exported` lives
+static struct {
+    struct wl_surface *surface;
+    struct zxdg_exported_v2 *exported;
+    char handle[128];
+} export_state;
+
+static void zxdg_exported_handle(
+    void *data,
+    struct zxdg_exported_v2 *exported,
+    const char *handle
+) {
+    snprintf(export_state.handle, sizeof(export_state.handle), "%s", handle);
+}
+
+static const struct zxdg_exported_v2_listener zxdg_exported_listener = {
+    .handle = zxdg_exported_handle
+};
+
+/*
+ * Exports the toplevel of `surface` so other clients (like the portal dialogs) can use it as parent
+ * the handle stays valid until another surface is exported
+ * returns NULL if the compositor doesn't support `zxdg_exporter_v2`
+ */
+const char *request_wl_export(struct wl_display *display, struct wl_surface *surface)
+{
+    if (!exporter)
+        return NULL;
+
+    if (export_state.surface == surface && export_state.handle[0])
+        return export_state.handle;
+
+    if (export_state.exported)
+        zxdg_exported_v2_destroy(export_state.exported);
+
+    export_state.surface = surface;
+    export_state.handle[0] = '\0';
+    export_state.exported = zxdg_exporter_v2_export_toplevel(exporter, surface);
+    zxdg_exported_v2_add_listener(export_state.exported, &zxdg_exported_listener, NULL);
+
+    // the handle is sent right after the export
+    if (wl_display_roundtrip(display) == -1 || !export_state.handle[0])
+        return NULL;
+
+    return export_state.handle;
+}
+
+static void xdg_activation_token_done(
+    void *data,
+    struct xdg_activation_token_v1 *token,
+    const char *name
+) {
+    xdg_activation_v1_activate(activation, name, data);
+    xdg_activation_token_v1_destroy(token);
+}
+
+static const struct xdg_activation_token_v1_listener xdg_activation_token_listener = {
+    .done = xdg_activation_token_done
+};
+
+/*
+ * Asks the compositor to raise the toplevel of `surface` with `token`, the one given to the launch
+ * that asked for it; without one the surface requests its own, so compositors with focus stealing
+ * prevention may only mark the window as demanding attention
+ * returns -1 if the compositor doesn't support `xdg_activation_v1`
+ */
+int request_wl_activate(struct wl_surface *surface, const char *token_name)
+{
+    if (!activation)
+        return -1;
+
+    if (token_name) {
+        xdg_activation_v1_activate(activation, token_name, surface);
+        return 0;
+    }
+
+    struct xdg_activation_token_v1 *token = xdg_activation_v1_get_activation_token(activation);
+    xdg_activation_token_v1_add_listener(token, &xdg_activation_token_listener, surface);
+    xdg_activation_token_v1_set_surface(token, surface);
+    xdg_activation_token_v1_commit(token);
+    return 0;
+}
+
+static void wl_buffer_release(void *data, struct wl_buffer *buffer)
+{
+    int *busy = data;
+    *busy = 0;
+}
+
+static const struct wl_buffer_listener wl_buffer_listener = {
+    .release = wl_buffer_release
+};
+
+/*
+ * Shares the memory of `fd` with the compositor, `size` can only grow later
+ * returns NULL if the compositor doesn't support `wl_shm`
+ */
+struct wl_shm_pool *request_wl_shm_pool(int fd, int32_t size)
+{
+    if (!shm)
+        return NULL;
+
+    return wl_shm_create_pool(shm, fd, size);
+}
+
+void request_wl_shm_pool_resize(struct wl_shm_pool *pool, int32_t size)
+{
+    wl_shm_pool_resize(pool, size);
+}
+
+void request_wl_shm_pool_destroy(struct wl_shm_pool *pool)
+{
+    wl_shm_pool_destroy(pool);
+}
+
+/*
+ * Creates an ARGB8888 buffer at `offset` of the pool, every format supports it
+ * `*busy` is cleared when the compositor releases the buffer, it must live as long as the buffer
+ * returns NULL if libwayland couldn't allocate the proxy
+ */
+struct wl_buffer *request_wl_buffer(
+    struct wl_shm_pool *pool,
+    int32_t offset,
+    int32_t width,
+    int32_t height,
+    int32_t stride,
+    int *busy
+) {
+    struct wl_buffer *buffer = wl_shm_pool_create_buffer(pool, offset, width, height,
+                                stride, WL_SHM_FORMAT_ARGB8888);
+    if (buffer)
+        wl_buffer_add_listener(buffer, &wl_buffer_listener, busy);
+    return buffer;
+}
+
+void request_wl_buffer_destroy(struct wl_buffer *buffer)
+{
+    wl_buffer_destroy(buffer);
+}
+
+// shows `buffer` on `surface`, all of it is damaged
+void request_wl_present(
+    struct wl_surface *surface,
+    struct wl_buffer *buffer,
+    int32_t width,
+    int32_t height
+) {
+    wl_surface_attach(surface, buffer, 0, 0);
+    wl_surface_damage_buffer(surface, 0, 0, width, height);
+    wl_surface_commit(surface);
+}
+
+/*
+ * Breaks the connection without freeing anything, the next read fails and the event loop returns
+ * the window can still be closed with `request_wl_close` afterwards
+ */
+void request_wl_shutdown(struct wl_display *display)
+{
+    shutdown(wl_display_get_fd(display), SHUT_RDWR);
+}
+
+/*
+ * Closes the window of `request_wl_surface`: drops what was attached to the surface,
+ * destroys the toplevel and its surface, then closes the connection
+ */
+void request_wl_close(struct wl_display *display, struct wl_surface *surface, struct window *window)
+{
+    // bound at version 1, there is no `release` request to send
+    struct org_kde_kwin_appmenu *appmenu = wl_surface_get_user_data(surface);
+    if (appmenu)
+        org_kde_kwin_appmenu_destroy(appmenu);
+
+    if (export_state.surface == surface) {
+        if (export_state.exported)
+            zxdg_exported_v2_destroy(export_state.exported);
+        memset(&export_state, 0, sizeof(export_state));
+    }
+
+    struct state *state = window->state;
+    destroy_window(surface, window);
+    // the destroy requests only reach the compositor if the connection still works
+    wl_display_flush(display);
+    destroy_connection(display, state);
 }
@@ -4,12 +4,66 @@ mod wayland;
 #[cfg(feature = "wayland")]
 pub use wayland::Wrapper;
 
+#[cfg(feature = "wayland")]
+mod shm;
+
+#[cfg(feature = "wayland")]
+mod cursor;
+
 #[cfg(feature = "x11")]
 mod x11;
 
 #[cfg(feature = "x11")]
 pub use x11::Wrapper;
 
+mod portal;
+mod notifications;
+mod dbusmenu;
+mod file_chooser;
+mod status_notifier;
+mod single_instance;
+mod desktop;
+
+pub use desktop::Desktop;
+
+use log::warn;
+use dirty::dbus::{BusSocket, Connection, Message, Value};
+use dirty::Vec;
+
+/// Standard interface to read the properties of exported objects
+const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+
+/// Answers `org.freedesktop.DBus.Properties` calls for an exported object,
+/// `properties` are the current values of `interface`
+/// returns `true` if `call` was a properties call
+fn serve_properties(
+	bus: &mut Connection<BusSocket>,
+	call: &Message,
+	interface: &str,
+	properties: Vec<(&str, Value)>,
+) -> bool
+{
+	let requested = call.get(0).and_then(Value::as_str);
+	let name = call.get(1).and_then(Value::as_str);
+
+	let result = if call.is_call(PROPERTIES, "GetAll") {
+		let all = if requested == Some(interface) { properties } else { Vec::new() };
+		bus.reply(call, Vec::from([Value::properties(all)]))
+	} else if call.is_call(PROPERTIES, "Get") {
+		match properties.into_iter().find(|&(key, _)| requested == Some(interface) && Some(key) == name) {
+			Some((_, value)) => bus.reply(call, Vec::from([value.variant()])),
+			None => bus.reply_error(call, "org.freedesktop.DBus.Error.UnknownProperty", "unknown property"),
+		}
+	} else if call.is_call(PROPERTIES, "Set") {
+		bus.reply_error(call, "org.freedesktop.DBus.Error.PropertyReadOnly", "properties are read only")
+	} else {
+		return false;
+	};
+
+	if let Err(e) = result { warn!("couldn't reply to {:?}: {e}", call.member) }
+	true
+}
+
 /// List of supported DEs/WMs
 #[derive(Debug, PartialEq)]
 #[allow(dead_code)]
@@ -30,21 +84,33 @@ pub enum DE {
 	Unknown,
 }
 
-//use log::warn;
-use crate::WResponse;
+use crate::{Error, WResponse};
 
-/// Detect the current DE/WM that the program is beeing executed
-pub fn get_de() -> Result<DE, WResponse>
+/**
+ * Detect the current DE/WM that the program is beeing executed
+ * `XDG_CURRENT_DESKTOP` is a list like `KDE` or `ubuntu:GNOME`
+ *
+ * # Errors
+ *
+ * `MissingDependencies` if `XDG_CURRENT_DESKTOP` is not set
+ */
+pub fn get_de() -> Result<DE, Error>
 {
-	/*let desktop = env::var("XDG_CURRENT_DESKTOP")
-		.unwrap_or_else(|_| {
-			warn!("missing XDG_CURRENT_DESKTOP");
-			String::from("")
-		});
-
-	if desktop == "" { return Fail(WResponse::NotImplementedInCompositor) }
-	if desktop.contains("KDE") { return Success(DE::Kde) }
-	if desktop.contains("Hyprland") { return Success(DE::Hyprland) }*/
+	let desktop = dirty::env::var("XDG_CURRENT_DESKTOP").filter(|desktop| !desktop.is_empty()).ok_or_else(|| {
+		warn!("missing XDG_CURRENT_DESKTOP");
+		Error::new(WResponse::MissingDependencies).with_message("XDG_CURRENT_DESKTOP is not set")
+	})?;
+
+	for name in desktop.split(':') {
+		match name.to_ascii_lowercase().as_str() {
+			"kde" => return Ok(DE::Kde),
+			"hyprland" => return Ok(DE::Hyprland),
+			"sway" => return Ok(DE::Sway),
+			"gnome" => return Ok(DE::Gnome),
+			"xfce" => return Ok(DE::Xfce),
+			_ => {},
+		}
+	}
 
 	Ok(DE::Other)
 }
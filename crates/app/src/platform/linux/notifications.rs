@@ -0,0 +1,147 @@
+/*!
+ * `org.freedesktop.Notifications` client
+ * <https://specifications.freedesktop.org/notification-spec/latest/>
+ */
+use log::{debug, warn};
+use dirty::dbus::{BusSocket, Connection, MatchRule, Message, Value};
+use crate::{Event, Notification, Error, WResponse, String, Vec};
+
+/// Bus name, path and interface of the notification server
+const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+/// Object path of the notification server
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+/// Action key used by KDE for the reply field
+const INLINE_REPLY: &str = "inline-reply";
+/// Signals we care about
+const SIGNALS: [&str; 3] = ["ActionInvoked", "NotificationReplied", "NotificationClosed"];
+
+/// State of the notifications sent by this app
+#[derive(Debug, Default)]
+pub(crate) struct Notifications {
+	/// The server shows buttons
+	actions: bool,
+	/// The server has a reply field, KDE extension
+	inline_reply: bool,
+	/// Notifications that are still visible
+	active: Vec<u32>,
+}
+
+impl Notifications {
+	/// Reads the server capabilities and subscribes to the action signals
+	pub(crate) fn subscribe(bus: &mut Connection<BusSocket>) -> Self
+	{
+		let mut notifications = Self::default();
+
+		for member in SIGNALS {
+			let rule = MatchRule::signal(NOTIFICATIONS, member).path(NOTIFICATIONS_PATH);
+			if bus.add_match(&rule).is_err() { return notifications }
+		}
+
+		let call = Message::method_call(NOTIFICATIONS, NOTIFICATIONS_PATH, NOTIFICATIONS, "GetCapabilities");
+		let Ok(reply) = bus.call(&call) else {
+			warn!("no notification server avaliable");
+			return notifications;
+		};
+
+		for capability in reply.get(0).and_then(Value::as_array).unwrap_or_default() {
+			match capability.as_str() {
+				Some("actions") => notifications.actions = true,
+				Some(INLINE_REPLY) => notifications.inline_reply = true,
+				_ => {},
+			}
+		}
+
+		debug!("notification server: {notifications:?}");
+		notifications
+	}
+
+	/// Sends (or replaces) a notification, returns its id
+	pub(crate) fn notify(
+		&mut self,
+		bus: &mut Connection<BusSocket>,
+		app_name: &str,
+		notification: &Notification,
+	) -> Result<u32, Error>
+	{
+		let mut actions = Vec::new();
+		let mut hints = Vec::from([("urgency", Value::Byte(notification.urgency as u8))]);
+
+		if self.actions {
+			for (key, label) in &notification.actions {
+				actions.push(Value::from(key.as_str()));
+				actions.push(Value::from(label.as_str()));
+			}
+		}
+
+		if self.inline_reply && let Some(ref placeholder) = notification.reply_placeholder {
+			actions.push(Value::from(INLINE_REPLY));
+			actions.push(Value::from("Reply"));
+			hints.push(("x-kde-reply-placeholder-text", Value::from(placeholder.as_str())));
+		}
+
+		// paths go on the `image-path` hint, themed icon names on `app_icon`
+		let app_icon = if notification.icon.starts_with('/') {
+			hints.push(("image-path", Value::from(notification.icon.as_str())));
+			""
+		} else {
+			notification.icon.as_str()
+		};
+
+		let call = Message::method_call(NOTIFICATIONS, NOTIFICATIONS_PATH, NOTIFICATIONS, "Notify")
+			.arg(app_name)
+			.arg(notification.replaces.unwrap_or_default())
+			.arg(app_icon)
+			.arg(notification.summary.as_str())
+			.arg(notification.body.as_str())
+			.arg(Value::Array(String::from("s"), actions))
+			.arg(Value::properties(hints))
+			.arg(-1i32);
+
+		let reply = bus.call(&call)?;
+		let id = reply.get(0).and_then(Value::as_u32).ok_or(WResponse::UnexpectedError)?;
+
+		if !self.active.contains(&id) { self.active.push(id) }
+		Ok(id)
+	}
+
+	/// Closes a notification that is still visible
+	pub(crate) fn close(&mut self, bus: &mut Connection<BusSocket>, id: u32) -> Result<(), Error>
+	{
+		if !self.active.contains(&id) { return Err(WResponse::InvalidRequest.into()) }
+
+		let call = Message::method_call(NOTIFICATIONS, NOTIFICATIONS_PATH, NOTIFICATIONS, "CloseNotification")
+			.arg(id);
+		bus.call(&call).map(|_| ())
+	}
+
+	/// Converts the server signals about our notifications into events
+	/// returns `None` if the message was not for us
+	pub(crate) fn handle(&mut self, message: &Message) -> Option<Event>
+	{
+		let member = message.member.as_deref()?;
+		if !SIGNALS.iter().any(|s| MatchRule::signal(NOTIFICATIONS, s).matches(message)) { return None }
+
+		// the signals are broadcasted to every client
+		let id = message.get(0).and_then(Value::as_u32)?;
+		if !self.active.contains(&id) { return None }
+
+		let text = message.get(1).and_then(Value::as_str).map(String::from);
+
+		match member {
+			"NotificationClosed" => {
+				self.active.retain(|&active| active != id);
+				None
+			},
+			"NotificationReplied" => Some(Event::NotificationAction {
+				id,
+				action: String::from(INLINE_REPLY),
+				reply: text,
+			}),
+			_ => Some(Event::NotificationAction {
+				id,
+				action: text.unwrap_or_default(),
+				reply: None,
+			}),
+		}
+	}
+}
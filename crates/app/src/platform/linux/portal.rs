@@ -0,0 +1,112 @@
+/*!
+ * `org.freedesktop.portal.Settings` client
+ * <https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Settings.html>
+ */
+use log::{debug, warn};
+use dirty::dbus::{BusSocket, Connection, MatchRule, Message, Value};
+use crate::{Color, ThemeDefault, String, Vec};
+
+/// Bus name of xdg-desktop-portal
+pub(super) const PORTAL: &str = "org.freedesktop.portal.Desktop";
+/// Object implementing every portal interface
+pub(super) const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+/// The settings interface
+const SETTINGS: &str = "org.freedesktop.portal.Settings";
+/// The only namespace we care about
+const APPEARANCE: &str = "org.freedesktop.appearance";
+
+/// Values of the `org.freedesktop.appearance` namespace
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Appearance {
+	/// 0: no preference, 1: prefer dark, 2: prefer light
+	color_scheme: u32,
+	/// 0: no preference, 1: high contrast
+	contrast: u32,
+	/// sRGB accent color, if the user picked one
+	accent: Option<Color>,
+}
+
+impl Appearance {
+	/// Signal sent by the portal when any setting changes
+	fn rule() -> MatchRule
+		{ MatchRule::signal(SETTINGS, "SettingChanged").arg0(APPEARANCE) }
+
+	/// Reads the current appearance and subscribes to its changes
+	/// if the portal is not running the default (no preference) is returned
+	pub(crate) fn subscribe(bus: &mut Connection<BusSocket>) -> Self
+	{
+		let mut appearance = Self::default();
+
+		if bus.add_match(&Self::rule()).is_err() { return appearance }
+
+		// a{sa{sv}}
+		let call = Message::method_call(PORTAL, PORTAL_PATH, SETTINGS, "ReadAll")
+			.arg(Value::Array(String::from("s"), Vec::from([Value::from(APPEARANCE)])));
+
+		let Ok(reply) = bus.call(&call) else {
+			warn!("xdg-desktop-portal Settings is not avaliable");
+			return appearance;
+		};
+
+		if let Some(keys) = reply.get(0).and_then(|namespaces| namespaces.get(APPEARANCE)) {
+			for entry in keys.as_array().unwrap_or_default() {
+				if let Value::DictEntry(key, value) = entry {
+					appearance.set(key.as_str().unwrap_or_default(), value);
+				}
+			}
+		}
+
+		debug!("portal appearance: {appearance:?}");
+		appearance
+	}
+
+	/// Updates a single key of the namespace
+	fn set(&mut self, key: &str, value: &Value)
+	{
+		match key {
+			"color-scheme" => self.color_scheme = value.as_u32().unwrap_or_default(),
+			"contrast" => self.contrast = value.as_u32().unwrap_or_default(),
+			"accent-color" => {
+				// out of range values mean that the accent color is not set
+				let channels = value.as_struct().and_then(|rgb| match rgb {
+					[r, g, b] => Some([r.as_f64()?, g.as_f64()?, b.as_f64()?]),
+					_ => None,
+				});
+				self.accent = channels
+					.filter(|rgb| rgb.iter().all(|c| (0.0..=1.0).contains(c)))
+					.map(|[r, g, b]| Color::from_default(r, g, b, 1.0));
+			},
+			_ => {},
+		}
+	}
+
+	/// Handles a `SettingChanged` signal
+	/// returns `true` if the message was for us
+	pub(crate) fn handle(&mut self, message: &Message) -> bool
+	{
+		if !Self::rule().matches(message) { return false }
+
+		if let (Some(key), Some(value)) = (message.get(1).and_then(Value::as_str), message.get(2)) {
+			self.set(key, value);
+		}
+		true
+	}
+
+	/// Applies the system appearance on top of `theme`
+	pub(crate) fn apply(&self, theme: &mut ThemeDefault)
+	{
+		match self.color_scheme {
+			1 => theme.dark = true,
+			2 => theme.dark = false,
+			_ => {},
+		}
+
+		theme.high_contrast = self.contrast == 1;
+
+		// the accent is also used for text, keep it readable over the background
+		if let Some(ref accent) = self.accent {
+			let ratio = if theme.high_contrast { Color::CONTRAST_AAA } else { Color::CONTRAST_AA };
+			theme.accent_color = accent.ensure_contrast(&theme.background_color, ratio);
+		}
+	}
+}
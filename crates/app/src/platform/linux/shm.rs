@@ -0,0 +1,247 @@
+/*!
+ * Software drawn ARGB8888 `wl_buffer`s, for cursors, client side title bars and the fallback renderer
+ *
+ * the buffers live side by side in one `ShmPool`, a buffer attached to a surface belongs to the
+ * compositor until it sends `wl_buffer.release`, so two or three of them are kept to draw the next frame meanwhile
+ *
+ * ```rust
+ * let mut buffers = ShmBuffers::new(2, 640, 480)?;
+ * if let Some(mut frame) = buffers.acquire() {
+ * 	frame.pixels().fill(0xff);
+ * 	frame.present(backend.wl_surface);
+ * }
+ * ```
+ */
+use core::cell::Cell;
+use core::ffi::c_int;
+use dirty::shm::ShmPool;
+use dirty::{Box, Error, Vec, void, WResponse};
+use log::debug;
+
+unsafe extern "C" {
+	fn request_wl_shm_pool(fd: c_int, size: i32) -> *mut void;
+	fn request_wl_shm_pool_resize(pool: *mut void, size: i32);
+	fn request_wl_shm_pool_destroy(pool: *mut void);
+	fn request_wl_buffer(pool: *mut void, offset: i32, width: i32, height: i32, stride: i32, busy: *mut c_int) -> *mut void;
+	fn request_wl_buffer_destroy(buffer: *mut void);
+	fn request_wl_present(surface: *mut void, buffer: *mut void, width: i32, height: i32);
+}
+
+/// Bytes of an ARGB8888 pixel
+const PIXEL: u32 = 4;
+
+/// Dimensions of every buffer, checked to fit the `int`s of the protocol
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Layout {
+	width: i32,
+	height: i32,
+	/// Bytes per row
+	stride: i32,
+	/// Bytes per buffer
+	size: usize,
+}
+
+impl Layout {
+	/// `None` if a dimension is zero or the pool wouldn't fit an `i32` with `count` buffers
+	fn new(width: u32, height: u32, count: usize) -> Option<Self>
+	{
+		if width == 0 || height == 0 { return None }
+
+		let stride = width.checked_mul(PIXEL)?;
+		let size = usize::try_from(stride.checked_mul(height)?).ok()?;
+		let _ = i32::try_from(size.checked_mul(count)?).ok()?;
+
+		Some(Self {
+			width: i32::try_from(width).ok()?,
+			height: i32::try_from(height).ok()?,
+			stride: i32::try_from(stride).ok()?,
+			size,
+		})
+	}
+}
+
+/// A `wl_buffer` and where its pixels are in the pool
+#[derive(Debug)]
+struct Buffer {
+	wl_buffer: *mut void,
+	offset: usize,
+	/// Bytes taken in the pool from `offset`
+	len: usize,
+	/// Set when presented, cleared by `wl_buffer.release`, boxed so the listener pointer stays valid
+	busy: Box<Cell<c_int>>,
+}
+
+impl Buffer {
+	/// Creates a buffer of `layout` at `offset` of the pool
+	fn new(pool: *mut void, layout: Layout, offset: usize) -> Result<Self, Error>
+	{
+		let raw_offset = i32::try_from(offset).map_err(|_| WResponse::OutOfBounds)?;
+		let busy = Box::new(Cell::new(0));
+
+		let wl_buffer = unsafe {
+			request_wl_buffer(pool, raw_offset, layout.width, layout.height, layout.stride, busy.as_ptr())
+		};
+		if wl_buffer.is_null() { return Err(WResponse::OutOfBounds.into()) }
+		Ok(Self { wl_buffer, offset, len: layout.size, busy })
+	}
+
+	/// Whether the compositor still reads the pixels
+	fn is_busy(&self) -> bool { self.busy.get() != 0 }
+
+	/// Whether the pixels overlap `start..end` of the pool
+	fn overlaps(&self, start: usize, end: usize) -> bool
+		{ self.offset < end && start < self.offset.saturating_add(self.len) }
+}
+
+/// Lowest offset where `len` bytes don't overlap any of `taken`
+fn free_region(taken: &[Buffer], len: usize) -> usize
+{
+	core::iter::once(0)
+		.chain(taken.iter().map(|buffer| buffer.offset.saturating_add(buffer.len)))
+		.filter(|&start| !taken.iter().any(|buffer| buffer.overlaps(start, start.saturating_add(len))))
+		.min()
+		.unwrap_or_default()
+}
+
+impl Drop for Buffer {
+	// no release event is dispatched after this, so `busy` can be freed
+	fn drop(&mut self)
+		{ unsafe { request_wl_buffer_destroy(self.wl_buffer) } }
+}
+
+/// Single, double or triple buffered ARGB8888 buffers, see the module docs
+#[derive(Debug)]
+pub(crate) struct ShmBuffers {
+	memory: ShmPool,
+	/// The `wl_shm_pool` sharing `memory` with the compositor
+	pool: *mut void,
+	buffers: Vec<Buffer>,
+	/// Buffers of an older size the compositor didn't release yet, their memory can't be reused
+	retired: Vec<Buffer>,
+	layout: Layout,
+}
+
+impl ShmBuffers {
+	/**
+	 * Creates `count` buffers of `width` x `height` pixels, one is enough for images drawn once like cursors
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` unless `count` is 1 to 3 and the size isn't empty, `OutOfBounds` if it is too big,
+	 * `ProtocolNotSuported` without `wl_shm`, check `ShmPool::new` for the rest
+	 */
+	pub(crate) fn new(count: usize, width: u32, height: u32) -> Result<Self, Error>
+	{
+		if !(1..=3).contains(&count) { return Err(WResponse::InvalidRequest.into()) }
+		if width == 0 || height == 0 { return Err(WResponse::InvalidRequest.into()) }
+
+		let layout = Layout::new(width, height, count).ok_or(WResponse::OutOfBounds)?;
+		let len = layout.size.checked_mul(count).ok_or(WResponse::OutOfBounds)?;
+		let memory = ShmPool::new(c"wt-shm", len)?;
+
+		let pool = unsafe { request_wl_shm_pool(memory.fd(), i32::try_from(len).unwrap_or(i32::MAX)) };
+		if pool.is_null() { return Err(WResponse::ProtocolNotSuported.into()) }
+
+		let mut buffers = Self { memory, pool, buffers: Vec::with_capacity(count), retired: Vec::new(), layout };
+		for index in 0..count { buffers.buffers.push(Buffer::new(pool, layout, layout.size.saturating_mul(index))?) }
+		Ok(buffers)
+	}
+
+	/// Width and height in pixels
+	pub(crate) fn size(&self) -> (u32, u32)
+		{ (self.layout.width.unsigned_abs(), self.layout.height.unsigned_abs()) }
+
+	/// How many buffers are free to draw
+	// the title bars will check it before drawing, the cursor is drawn once
+	#[allow(dead_code)]
+	pub(crate) fn released(&self) -> usize
+		{ self.buffers.iter().filter(|buffer| !buffer.is_busy()).count() }
+
+	/// A free buffer to draw the next frame, `None` while the compositor holds all of them
+	pub(crate) fn acquire(&mut self) -> Option<ShmFrame<'_>>
+	{
+		self.retired.retain(Buffer::is_busy);
+		let index = self.buffers.iter().position(|buffer| !buffer.is_busy())?;
+		Some(ShmFrame { buffers: self, index })
+	}
+
+	/**
+	 * Recreates the buffers with a new size, their contents are lost
+	 * buffers the compositor still holds are kept until it releases them, the new ones go where they
+	 * don't overlap, the pool only grows, shrinking keeps the memory for the next resize
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` for an empty size, `OutOfBounds` if it is too big or the memory couldn't grow
+	 */
+	// the title bars will follow the window size, the cursor doesn't change
+	#[allow(dead_code)]
+	pub(crate) fn resize(&mut self, width: u32, height: u32) -> Result<(), Error>
+	{
+		if (width, height) == self.size() { return Ok(()) }
+		if width == 0 || height == 0 { return Err(WResponse::InvalidRequest.into()) }
+
+		let count = self.buffers.len();
+		let layout = Layout::new(width, height, count).ok_or(WResponse::OutOfBounds)?;
+		let len = layout.size.checked_mul(count).ok_or(WResponse::OutOfBounds)?;
+
+		// released buffers are destroyed now, busy ones keep their contents on screen until released
+		self.retired.append(&mut self.buffers);
+		self.retired.retain(Buffer::is_busy);
+
+		let base = free_region(&self.retired, len);
+		let end = base.checked_add(len).ok_or(WResponse::OutOfBounds)?;
+		let pool_len = i32::try_from(end).map_err(|_| WResponse::OutOfBounds)?;
+		if end > self.memory.len() {
+			self.memory.grow(end)?;
+			unsafe { request_wl_shm_pool_resize(self.pool, pool_len) }
+		}
+
+		self.layout = layout;
+		for index in 0..count {
+			let offset = layout.size.saturating_mul(index).saturating_add(base);
+			self.buffers.push(Buffer::new(self.pool, layout, offset)?);
+		}
+		debug!("shm buffers resized to {width}x{height}");
+		Ok(())
+	}
+}
+
+impl Drop for ShmBuffers {
+	fn drop(&mut self)
+	{
+		self.buffers.clear();
+		self.retired.clear();
+		unsafe { request_wl_shm_pool_destroy(self.pool) }
+	}
+}
+
+/// A released buffer being drawn, it goes back to the compositor on `present`
+#[derive(Debug)]
+pub(crate) struct ShmFrame<'a> {
+	buffers: &'a mut ShmBuffers,
+	index: usize,
+}
+
+impl ShmFrame<'_> {
+	/// Premultiplied ARGB8888 pixels in native endian, `stride` bytes per row
+	pub(crate) fn pixels(&mut self) -> &mut [u8]
+	{
+		let Some(buffer) = self.buffers.buffers.get(self.index) else { return &mut [] };
+		let end = buffer.offset.saturating_add(self.buffers.layout.size);
+		self.buffers.memory.as_mut_slice().get_mut(buffer.offset..end).unwrap_or_default()
+	}
+
+	/// Bytes per row
+	pub(crate) fn stride(&self) -> usize
+		{ usize::try_from(self.buffers.layout.stride).unwrap_or_default() }
+
+	/// Attaches the buffer to `surface` and commits it, the whole buffer is damaged
+	pub(crate) fn present(self, surface: *mut void)
+	{
+		let Some(buffer) = self.buffers.buffers.get(self.index) else { return };
+		let layout = self.buffers.layout;
+		buffer.busy.set(1);
+		unsafe { request_wl_present(surface, buffer.wl_buffer, layout.width, layout.height) }
+	}
+}
@@ -0,0 +1,201 @@
+/*!
+ * Single instance enforcement, the first instance locks `$XDG_RUNTIME_DIR/<app>.lock`
+ * and listens on `$XDG_RUNTIME_DIR/<app>.sock`, the next launches forward their arguments to it
+ * instead of opening another window
+ *
+ * A request is the activation token of the launch followed by the arguments, all separated by nul bytes,
+ * the token is empty if the launcher didn't give one, the client closes the connection when done
+ */
+use log::{debug, warn};
+use dirty::fs::File;
+use dirty::socket::{self, UnixListener, UnixStream};
+use dirty::xdg::BaseDir;
+use dirty::{env, format, syscall};
+use crate::{Error, WResponse, String, Vec};
+
+/// Requests bigger than this are dropped, only the same user can reach the socket
+const MAX_REQUEST: usize = 64 * 1024;
+/// Launches read at the same time, the rest are dropped
+const MAX_CLIENTS: usize = 16;
+/// Connection attempts while the running instance is still binding its socket
+const CONNECT_TRIES: u32 = 20;
+/// Wait between connection attempts
+const CONNECT_DELAY: core::time::Duration = core::time::Duration::from_millis(50);
+
+/// `$XDG_RUNTIME_DIR/<app>`, with the app name in lower case and without spaces
+fn runtime_path(app_name: &str) -> Result<String, Error>
+{
+	let dir = BaseDir::Runtime.path()
+		.inspect_err(|_| warn!("XDG_RUNTIME_DIR is not set, can't tell if the app is already running"))?;
+	let name: String = app_name.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+		.collect();
+
+	Ok(format!("{dir}/{name}"))
+}
+
+/// Token given by the launcher to raise our window, `xdg_activation_v1` first and startup notification after
+fn activation_token() -> String
+{
+	env::var("XDG_ACTIVATION_TOKEN")
+		.or_else(|| env::var("DESKTOP_STARTUP_ID"))
+		.unwrap_or_default()
+}
+
+/// Arguments of another launch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Request {
+	/// Activation token of the launch, `None` if the launcher didn't give one
+	pub(crate) token: Option<String>,
+	/// Arguments of the launch
+	pub(crate) args: Vec<String>,
+}
+
+/// A launch still sending its request
+#[derive(Debug)]
+struct Client {
+	/// Read without waiting whenever the event loop says it is readable
+	stream: UnixStream,
+	/// What was received so far
+	data: Vec<u8>,
+}
+
+/// Listens for the arguments of later launches
+#[derive(Debug)]
+pub(crate) struct InstanceServer {
+	/// Held while this is the running instance, the kernel releases it even if the app crashes
+	_lock: File,
+	/// Non blocking listener, the socket file is removed when the app exits
+	listener: UnixListener,
+	/// Launches whose request is still arriving
+	clients: Vec<Client>,
+}
+
+impl InstanceServer {
+	/**
+	 * Becomes the running instance, or forwards `args` to the one that already is
+	 * returns `None` if the arguments were forwarded
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` without `XDG_RUNTIME_DIR`, the file and socket errors otherwise
+	 */
+	pub(crate) fn acquire(app_name: &str, args: &[&str]) -> Result<Option<Self>, Error>
+	{
+		let base = runtime_path(app_name)?;
+		let path = format!("{base}.sock");
+		let lock = File::create(&format!("{base}.lock"))?;
+
+		if !lock.try_lock()? {
+			forward(&path, args)?;
+			debug!("{app_name} is already running, arguments forwarded to {path}");
+			return Ok(None);
+		}
+
+		// only the lock holder gets here, so a socket file left behind by a crash can't be in use
+		if socket::remove(&path).is_ok() { debug!("removed the stale socket {path}") }
+		let listener = UnixListener::bind(&path)?;
+		listener.set_nonblocking(true)?;
+		debug!("listening for other instances on {path}");
+		Ok(Some(Self { _lock: lock, listener, clients: Vec::new() }))
+	}
+
+	/// The listener file descriptor, to be watched by an event loop
+	pub(crate) fn fd(&self) -> i32 { self.listener.fd() }
+
+	/// Takes every launch waiting on the socket, returns their descriptors to be watched by the event loop
+	pub(crate) fn accept(&mut self) -> Vec<i32>
+	{
+		let mut accepted = Vec::new();
+
+		loop {
+			let stream = match self.listener.accept() {
+				Ok(Some(stream)) => stream,
+				Ok(None) => break,
+				Err(e) => {
+					warn!("couldn't accept another instance: {e}");
+					break;
+				},
+			};
+
+			// left in the backlog they would keep the listener readable
+			if self.clients.len() >= MAX_CLIENTS {
+				warn!("too many launches at once, dropped a request from another instance");
+				continue;
+			}
+			accepted.push(stream.fd());
+			self.clients.push(Client { stream, data: Vec::new() });
+		}
+
+		accepted
+	}
+
+	/// Drops the launch behind `fd` without reading the rest of its request
+	pub(crate) fn close(&mut self, fd: i32)
+		{ self.clients.retain(|client| client.stream.fd() != fd) }
+
+	/**
+	 * Reads what the launch behind `fd` sent, returns its request once it closed the connection
+	 * finished or failed clients are closed, which also takes them out of the event loop
+	 */
+	pub(crate) fn read(&mut self, fd: i32) -> Option<Request>
+	{
+		let index = self.clients.iter().position(|client| client.stream.fd() == fd)?;
+		let client = self.clients.get_mut(index)?;
+
+		let finished = match read_available(client) {
+			Ok(false) => return None,
+			Ok(true) => parse(&client.data),
+			Err(e) => Err(e),
+		};
+
+		let _ = self.clients.swap_remove(index);
+		finished.inspect_err(|e| warn!("dropped a request from another instance: {e}")).ok()
+	}
+}
+
+/// Sends `args` to the running instance, it may still be binding its socket
+fn forward(path: &str, args: &[&str]) -> Result<(), Error>
+{
+	let mut request = activation_token();
+	for arg in args {
+		request.push('\0');
+		request.push_str(arg);
+	}
+
+	let mut tries = 0..CONNECT_TRIES;
+	let mut stream = loop {
+		match UnixStream::connect(path) {
+			Ok(stream) => break stream,
+			Err(e) if tries.next().is_none() => return Err(e),
+			Err(_) => { let _ = syscall::nanosleep(CONNECT_DELAY); },
+		}
+	};
+	stream.send_all(request.as_bytes())
+}
+
+/// Reads until the client would block, returns `true` once it closed the connection
+fn read_available(client: &mut Client) -> Result<bool, Error>
+{
+	let mut buf = [0u8; 1024];
+
+	loop {
+		match client.stream.try_recv(&mut buf)? {
+			None => return Ok(false),
+			Some(0) => return Ok(true),
+			Some(read) => {
+				client.data.extend_from_slice(buf.get(..read).unwrap_or_default());
+				if client.data.len() > MAX_REQUEST { return Err(WResponse::OutOfBounds.into()) }
+			},
+		}
+	}
+}
+
+/// Splits a request into the activation token and the arguments
+fn parse(data: &[u8]) -> Result<Request, Error>
+{
+	let text = core::str::from_utf8(data).map_err(|_| WResponse::UnexpectedError)?;
+	let mut fields = text.split('\0');
+	let token = fields.next().filter(|token| !token.is_empty()).map(String::from);
+	Ok(Request { token, args: fields.map(String::from).collect() })
+}
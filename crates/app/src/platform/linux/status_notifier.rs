@@ -0,0 +1,240 @@
+/*!
+ * `org.kde.StatusNotifierItem` server, the tray icon
+ * <https://www.freedesktop.org/wiki/Specifications/StatusNotifierItem/>
+ */
+use log::{debug, warn};
+use dirty::dbus::{BusSocket, Connection, MatchRule, Message, Value};
+use crate::{Event, Handler, Tray, Error, WResponse, String, Vec};
+use super::{serve_properties, dbusmenu::MenuExporter};
+
+/// The item interface
+const ITEM: &str = "org.kde.StatusNotifierItem";
+/// Object path of the item, the watcher assumes it when registering a bus name
+const ITEM_PATH: &str = "/StatusNotifierItem";
+/// Object path of the context menu
+const MENU_PATH: &str = "/MenuBar";
+/// Bus name, path and interface of the service that tracks the items
+const WATCHER: &str = "org.kde.StatusNotifierWatcher";
+/// Object path of the watcher
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+/// The bus daemon
+const DBUS: &str = "org.freedesktop.DBus";
+/// Themed icon drawn over the tray icon when there are unread messages
+const UNREAD_OVERLAY: &str = "mail-unread";
+
+/// The tray item exported on the bus
+#[derive(Debug)]
+pub(crate) struct StatusNotifier {
+	/// Well known name owned while the item exists, the watcher drops the item when it is released
+	name: String,
+	/// Used as the item id
+	app_name: String,
+	/// Current state
+	tray: Tray,
+	/// Context menu
+	menu: MenuExporter,
+}
+
+impl StatusNotifier {
+	/// Sent by the daemon when the watcher starts or stops
+	fn watcher_rule() -> MatchRule
+		{ MatchRule::signal(DBUS, "NameOwnerChanged").sender(DBUS).arg0(WATCHER) }
+
+	/// Creates the item, it is only visible after `export`
+	pub(crate) fn new(bus: &mut Connection<BusSocket>, app_name: &str, tray: Tray) -> Self
+	{
+		// the convention is `org.kde.StatusNotifierItem-<pid>-<n>`
+		let unique_name = String::from(bus.unique_name());
+		let call = Message::method_call(DBUS, "/org/freedesktop/DBus", DBUS, "GetConnectionUnixProcessID")
+			.arg(unique_name.as_str());
+		let pid = bus.call(&call).ok().and_then(|reply| reply.get(0).and_then(Value::as_u32)).unwrap_or_default();
+
+		Self {
+			name: dirty::format!("{ITEM}-{pid}-1"),
+			app_name: String::from(app_name),
+			menu: MenuExporter::new(MENU_PATH, &tray.menu),
+			tray,
+		}
+	}
+
+	/**
+	 * Takes the item bus name and registers it on the watcher
+	 * if there is no watcher yet the item is registered when one appears
+	 *
+	 * # Errors
+	 *
+	 * `ChannelInUse` if the name is owned by someone else,
+	 * `MissingDependencies` if no watcher is running
+	 */
+	pub(crate) fn export(&self, bus: &mut Connection<BusSocket>) -> Result<(), Error>
+	{
+		bus.add_match(&Self::watcher_rule())?;
+		if !bus.request_name(&self.name)? { return Err(WResponse::ChannelInUse.into()) }
+		self.announce(bus)
+	}
+
+	/// Tells the watcher that the item exists
+	fn announce(&self, bus: &mut Connection<BusSocket>) -> Result<(), Error>
+	{
+		let call = Message::method_call(WATCHER, WATCHER_PATH, WATCHER, "RegisterStatusNotifierItem")
+			.arg(self.name.as_str());
+
+		bus.call(&call)
+			.map(|_| debug!("tray item registered as {}", self.name))
+			.map_err(|e| {
+				warn!("no StatusNotifierWatcher, the tray icon is not visible");
+				e.context(WResponse::MissingDependencies, "no StatusNotifierWatcher on the session bus")
+			})
+	}
+
+	/**
+	 * Releases the bus name, the watcher removes the item
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::call`
+	 */
+	pub(crate) fn unregister(self, bus: &mut Connection<BusSocket>) -> Result<(), Error>
+	{
+		bus.remove_match(&Self::watcher_rule())?;
+		bus.release_name(&self.name)
+	}
+
+	/**
+	 * Replaces the tray state, only what changed is signaled to the shell
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::send`
+	 */
+	pub(crate) fn update(&mut self, bus: &mut Connection<BusSocket>, tray: Tray) -> Result<(), Error>
+	{
+		let changes = [
+			(tray.icon != self.tray.icon, ["NewIcon", "NewAttentionIcon"]),
+			(tray.title != self.tray.title, ["NewTitle", "NewToolTip"]),
+			(tray.unread != self.tray.unread, ["NewOverlayIcon", "NewToolTip"]),
+		];
+		let mut signals = Vec::new();
+		for member in changes.into_iter().filter(|&(changed, _)| changed).flat_map(|(_, members)| members) {
+			if !signals.contains(&member) { signals.push(member) }
+		}
+
+		if tray.menu != self.tray.menu { self.menu.update(bus, &tray.menu)? }
+		let status_changed = (tray.unread == 0) != (self.tray.unread == 0);
+		self.tray = tray;
+
+		for member in signals {
+			let _ = bus.send(&Message::signal(ITEM_PATH, ITEM, member))?;
+		}
+		if status_changed {
+			let _ = bus.send(&Message::signal(ITEM_PATH, ITEM, "NewStatus").arg(self.status()))?;
+		}
+		Ok(())
+	}
+
+	/**
+	 * Changes the unread count shown on the icon and tooltip
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::send`
+	 */
+	pub(crate) fn set_unread(&mut self, bus: &mut Connection<BusSocket>, unread: u32) -> Result<(), Error>
+	{
+		let tray = Tray { unread, ..self.tray.clone() };
+		self.update(bus, tray)
+	}
+
+	/// `NeedsAttention` moves the item out of the hidden section of the tray
+	fn status(&self) -> &'static str
+		{ if self.tray.unread == 0 { "Active" } else { "NeedsAttention" } }
+
+	/// Themed icon name and theme path, paths are split as the spec only accepts names
+	fn icon(&self) -> (&str, &str)
+	{
+		let icon = self.tray.icon.as_str();
+		if !icon.starts_with('/') { return (icon, "") }
+
+		let (dir, file) = icon.rsplit_once('/').unwrap_or_default();
+		let name = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+		(name, dir)
+	}
+
+	/// Tooltip description, like `3 unread messages`
+	fn description(&self) -> String
+	{
+		match self.tray.unread {
+			0 => String::from("No unread messages"),
+			1 => String::from("1 unread message"),
+			n => dirty::format!("{n} unread messages"),
+		}
+	}
+
+	/// Every property of the item
+	fn properties(&self) -> Vec<(&'static str, Value)>
+	{
+		let (icon, theme_path) = self.icon();
+		let pixmaps = || Value::Array(String::from("(iiay)"), Vec::new());
+		let overlay = if self.tray.unread == 0 { "" } else { UNREAD_OVERLAY };
+
+		// (sa(iiay)ss), icon, pixmaps, title and description
+		let tooltip = Value::Struct(Vec::from([
+			Value::from(icon),
+			pixmaps(),
+			Value::from(self.tray.title.as_str()),
+			Value::from(self.description()),
+		]));
+
+		Vec::from([
+			("Category", Value::from("Communications")),
+			("Id", Value::from(self.app_name.as_str())),
+			("Title", Value::from(self.tray.title.as_str())),
+			("Status", Value::from(self.status())),
+			("WindowId", Value::from(0i32)),
+			("IconThemePath", Value::from(theme_path)),
+			("IconName", Value::from(icon)),
+			("IconPixmap", pixmaps()),
+			("OverlayIconName", Value::from(overlay)),
+			("OverlayIconPixmap", pixmaps()),
+			("AttentionIconName", Value::from(icon)),
+			("AttentionIconPixmap", pixmaps()),
+			("AttentionMovieName", Value::from("")),
+			("ToolTip", tooltip),
+			("ItemIsMenu", Value::from(false)),
+			("Menu", Value::ObjectPath(String::from(self.menu.path()))),
+		])
+	}
+
+	/// Answers the shell calls on the item and its menu, returns `true` if `message` was for us
+	pub(crate) fn handle(&mut self, bus: &mut Connection<BusSocket>, message: &Message, handler: &Handler) -> bool
+	{
+		if Self::watcher_rule().matches(message) {
+			// a new watcher doesn't know about the items registered on the previous one
+			let owner = message.get(2).and_then(Value::as_str).unwrap_or_default();
+			if !owner.is_empty() { let _ = self.announce(bus); }
+			return true;
+		}
+
+		if self.menu.handle(bus, message, handler) { return true }
+		if message.path.as_deref() != Some(ITEM_PATH) { return false }
+		if serve_properties(bus, message, ITEM, self.properties()) { return true }
+
+		let position = (
+			message.get(0).and_then(Value::as_i32).unwrap_or_default(),
+			message.get(1).and_then(Value::as_i32).unwrap_or_default(),
+		);
+
+		let result = match message.member.as_deref().filter(|_| message.interface.as_deref() == Some(ITEM)) {
+			Some("Activate") => {
+				handler(Event::TrayActivated { position });
+				bus.reply(message, Vec::new())
+			},
+			// the menu is drawn by the shell, middle click and scroll have no meaning for us
+			Some("ContextMenu" | "SecondaryActivate" | "Scroll") => bus.reply(message, Vec::new()),
+			_ => bus.reply_error(message, "org.freedesktop.DBus.Error.UnknownMethod", "unknown method"),
+		};
+
+		if let Err(e) = result { warn!("couldn't reply to the tray call {:?}: {e}", message.member) }
+		true
+	}
+}
@@ -4,23 +4,95 @@ use crate::{
 	NativeDecoration,
 	Decoration,
 	ThemeDefault,
+	Event,
+	Handler,
+	Menu,
+	Window,
+	Error,
 	WResponse::{self, ProtocolNotSuported},
-	platform::linux::{DE, get_de},
+	platform::linux::{DE, get_de, Desktop, cursor::Cursor, desktop::DISPLAY},
 	void,
 	String,
 };
 
-use log::debug;
-use dirty::{format, Vec};
+use core::ffi::{c_char, c_int, CStr};
+use log::{debug, error, warn};
+use dirty::{format, Detail, NativeWindow, RawWindow, Vec};
+use dirty::syscall::Errno;
 
+/// `EPROTO`, set by libwayland when the compositor sent a protocol error
+const EPROTO: c_int = 71;
+
+/// `WINDOW_RESIZED` of `libwayland.c`, the compositor picked a new size
+const WINDOW_RESIZED: c_int = 1;
+/// `WINDOW_CLOSE` of `libwayland.c`, the user asked to close the window
+const WINDOW_CLOSE: c_int = 2;
+
+/// Result of the connection setup, keep in sync with `enum wl_status` on `libwayland.c`
 #[repr(C)]
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+enum WlStatus {
+	Ok = 0,
+	/// `wl_display_connect` failed, there is no compositor to talk to
+	NoDisplay = 1,
+	/// the compositor doesn't advertise `wl_compositor`
+	NoCompositor = 2,
+	/// the compositor doesn't advertise `xdg_wm_base`
+	NoWmBase = 3,
+	/// the connection broke during the setup
+	ConnectionError = 4,
+}
+
+/// Result of `request_wl_surface`, on failure the connection is already closed and only `error` is set
+#[repr(C)]
+#[derive(Debug)]
 pub(crate) struct WindowSurface {
 	display: *mut void,
 	registry: *mut void,
-	registry_listener: *mut void,
 	surface: *mut void,
-	toplevel: *mut void,
+	/// `struct window` of `libwayland.c`, freed by `request_wl_close`
+	window: *mut void,
+	status: WlStatus,
+	error: WlError,
+}
+
+/// Last error of a display, check `struct WlError` on `libwayland.c`
+#[repr(C)]
+#[derive(Debug)]
+pub(crate) struct WlError {
+	code: c_int,
+	protocol_code: u32,
+	object_id: u32,
+	interface: *const c_char,
+}
+
+impl WlError {
+	/// Maps the raw wayland error into an `Error`, the protocol error or the errno is kept as detail
+	fn response(&self) -> Error
+	{
+		if self.code != EPROTO {
+			return Error::new(WResponse::ConnectionLost)
+				.with_message("the connection with the compositor broke")
+				.with_detail(Detail::Errno(Errno(self.code)));
+		}
+
+		let interface = if self.interface.is_null() { "unknown" } else {
+			unsafe { CStr::from_ptr(self.interface) }.to_str().unwrap_or("unknown")
+		};
+
+		error!(
+			"wayland protocol error {} on {}@{}",
+			self.protocol_code, interface, self.object_id
+		);
+		Error::new(WResponse::ForbiddenByCompositor)
+			.with_message("the compositor raised a protocol error")
+			.with_detail(Detail::Wayland {
+				code: self.protocol_code,
+				object_id: self.object_id,
+				interface: String::from(interface),
+			})
+	}
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -29,18 +101,113 @@ pub struct Wrapper {
 	pub wl_display: *mut void,
 }
 
+impl Decoration {
+	/// What the renderer needs to create its surface, the toplevel and its connection are closed with it
+	pub(crate) fn native(&self) -> NativeWindow
+	{
+		let (display, surface, window) = (self.backend.wl_display, self.backend.wl_surface, self.frame.cast_mut());
+		NativeWindow::new(
+			RawWindow::Wayland { display, surface },
+			move || unsafe { request_wl_close(display, surface, window) },
+		)
+	}
+}
+
 unsafe extern "C" {
 	pub(crate) fn request_wl_surface() -> WindowSurface;
-	pub(crate) fn request_wl_disconnect(display: *mut void);
-	pub(crate) fn loop_wl_event(display: *mut void);
+	pub(crate) fn request_wl_error(display: *mut void) -> WlError;
+	pub(crate) fn request_wl_shutdown(display: *mut void);
+	pub(crate) fn request_wl_close(display: *mut void, surface: *mut void, window: *mut void);
+	pub(crate) fn request_wl_appmenu(surface: *mut void, service: *const c_char, path: *const c_char) -> c_int;
+	pub(crate) fn request_wl_export(display: *mut void, surface: *mut void) -> *const c_char;
+	pub(crate) fn request_wl_activate(surface: *mut void, token: *const c_char) -> c_int;
+	pub(crate) fn request_wl_window_events(window: *mut void, width: *mut i32, height: *mut i32) -> c_int;
+	pub(crate) fn request_wl_fd(display: *mut void) -> c_int;
+	pub(crate) fn prepare_wl_read(display: *mut void) -> c_int;
+	pub(crate) fn finish_wl_read(display: *mut void, readable: c_int) -> c_int;
+}
+
+/**
+ * Dispatches the display, the desktop integrations and the timers until the connection breaks,
+ * the toplevel configures and close requests of `window` become events, returns the errno that broke it
+ *
+ * # Errors
+ *
+ * Check `Desktop::watch_display`, the loop couldn't start
+ */
+fn event_loop(
+	window: &Window,
+	desktop: &mut Desktop,
+	handler: &Handler,
+) -> Result<c_int, Error>
+{
+	let (display, surface) = (window.decoration.backend.wl_display, window.decoration.backend.wl_surface);
+	let toplevel = window.decoration.frame.cast_mut();
+	desktop.watch_display(unsafe { request_wl_fd(display) })?;
+
+	let mut events = Vec::new();
+	loop {
+		let prepared = unsafe { prepare_wl_read(display) };
+		if prepared != 0 { return Ok(prepared) }
+
+		// the read intent must be given back even if the wait failed
+		let waited = desktop.wait(&mut events);
+		let readable = events.iter().any(|event| event.token() == DISPLAY);
+		let finished = unsafe { finish_wl_read(display, c_int::from(readable)) };
+		waited?;
+		if finished != 0 { return Ok(finished) }
+
+		for event in events.iter().filter(|event| event.token() != DISPLAY) {
+			desktop.dispatch(event.token(), handler);
+		}
+		desktop.dispatch_pending(handler);
+
+		let (mut width, mut height) = (0, 0);
+		let happened = unsafe { request_wl_window_events(toplevel, &raw mut width, &raw mut height) };
+		if happened & WINDOW_RESIZED != 0 {
+			handler(Event::WindowResized { window: window.clone(), new_size: (f64::from(width), f64::from(height)) });
+		}
+		if happened & WINDOW_CLOSE != 0 { handler(Event::CloseRequest) }
+
+		if let Some(token) = desktop.take_raise() {
+			// the token of the launch is what lets compositors with focus stealing prevention raise us
+			let named = (!token.is_empty()).then(|| format!("{token}\0"));
+			let raw = named.as_ref().map_or(core::ptr::null(), |t| t.as_ptr().cast());
+			if unsafe { request_wl_activate(surface, raw) } != 0 {
+				debug!("the compositor doesn't support `xdg_activation_v1`, the window can't be raised");
+			}
+		}
+	}
 }
 
 impl NativeDecoration for Decoration
 {
-	fn new(title: String, width: f64, height: f64, theme: ThemeDefault) -> Result<Self, WResponse>
+	fn new(title: String, width: f64, height: f64, theme: ThemeDefault) -> Result<Self, Error>
 	{
 		let state = unsafe { request_wl_surface() };
-		let frame = state.toplevel;
+
+		let failure = match state.status {
+			WlStatus::Ok => None,
+			WlStatus::NoDisplay => {
+				error!("couldn't connect to the wayland compositor (errno {})", state.error.code);
+				Some(Error::new(WResponse::MissingDependencies)
+					.with_message("couldn't connect to the wayland compositor")
+					.with_detail(Detail::Errno(Errno(state.error.code))))
+			},
+			WlStatus::NoCompositor => {
+				error!("the compositor doesn't support `wl_compositor`");
+				Some(Error::new(ProtocolNotSuported).with_message("the compositor doesn't support `wl_compositor`"))
+			},
+			WlStatus::NoWmBase => {
+				error!("the compositor doesn't support `xdg_wm_base`");
+				Some(Error::new(ProtocolNotSuported).with_message("the compositor doesn't support `xdg_wm_base`"))
+			},
+			WlStatus::ConnectionError => Some(state.error.response()),
+		};
+
+		if let Some(response) = failure { return Err(response) }
+
+		let frame = state.window;
 
 		let backend = Wrapper {
 			wl_surface: state.surface,
@@ -54,21 +221,63 @@ impl NativeDecoration for Decoration
 		})
 	}
 
-	fn exit(&self) -> Result<(), WResponse>
+	/// Breaks the connection so `run` returns, the window itself is closed with its last handle
+	fn exit(&self) -> Result<(), Error>
 	{
-		unsafe { request_wl_disconnect(self.backend.wl_display) };
+		unsafe { request_wl_shutdown(self.backend.wl_display) };
 		Ok(())
 	}
 
-	fn run(&self)
+	fn run(&self, window: &Window, desktop: &mut Desktop, handler: &Handler)
 	{
-		unsafe { loop_wl_event(self.backend.wl_display) };
+		let display = self.backend.wl_display;
+		// kept until the loop ends, the compositor reads its buffer
+		let _cursor = Cursor::new().inspect_err(|e| warn!("couldn't draw the cursor: {e}")).ok();
+
+		let reason = match event_loop(window, desktop, handler) {
+			Ok(code) => {
+				debug!("wayland event loop stopped with errno {code}");
+				unsafe { request_wl_error(display) }.response()
+			},
+			Err(e) => {
+				error!("couldn't start the event loop: {e}");
+				e
+			},
+		};
+
+		handler(Event::ConnectionLost { reason });
+	}
+
+	/// The menu is exported on the session bus and linked to the surface with `org_kde_kwin_appmenu`,
+	/// compositors without the protocol don't have a global menu
+	fn create_app_menu(&self, desktop: &mut Desktop, menu: &Menu, _handler: &Handler) -> Result<(), Error>
+	{
+		let (name, object) = desktop.export_app_menu(menu)?;
+		debug!("global menu exported on {name}{object}");
+
+		let (service, path) = (format!("{name}\0"), format!("{object}\0"));
+		let status = unsafe {
+			request_wl_appmenu(self.backend.wl_surface, service.as_ptr().cast(), path.as_ptr().cast())
+		};
+		if status != 0 {
+			return Err(Error::new(ProtocolNotSuported).with_message("the compositor doesn't support `org_kde_kwin_appmenu`"));
+		}
+		Ok(())
 	}
 
-	fn create_app_menu(&self, _app_name: String) -> Result<(), WResponse>
-		{ Ok(()) }
+	/// `wayland:<handle>` from `xdg_foreign`, the format expected by the portals
+	fn export_handle(&self) -> Result<String, Error>
+	{
+		let raw = unsafe { request_wl_export(self.backend.wl_display, self.backend.wl_surface) };
+		if raw.is_null() {
+			return Err(Error::new(ProtocolNotSuported).with_message("the compositor doesn't support `xdg_foreign`"));
+		}
+
+		let handle = unsafe { CStr::from_ptr(raw) }.to_str().map_err(|_| WResponse::UnexpectedError)?;
+		Ok(format!("wayland:{handle}"))
+	}
 
-	fn apply_blur(&mut self) -> Result<(), WResponse>
+	fn apply_blur(&mut self) -> Result<(), Error>
 	{
 		/**
 		 * the `hyprland_surface_manager_v1` protocol already covers this, skip
@@ -87,6 +296,6 @@ impl NativeDecoration for Decoration
 			_ => {}
 		}
 
-		Err(ProtocolNotSuported)
+		Err(ProtocolNotSuported.into())
 	}
 }
@@ -25,8 +25,15 @@ pub use nt::Wrapper;
 
 // Linux -------------------
 #[cfg(target_os = "linux")]
-pub use linux::Wrapper;
+pub use linux::{Wrapper, Desktop};
 
 // BSD ---------------------
 #[cfg(target_os = "bsd")]
 pub use bsd::Wrapper;
+
+// Desktop integrations ----
+#[cfg(not(target_os = "linux"))]
+mod unsupported;
+
+#[cfg(not(target_os = "linux"))]
+pub use unsupported::Desktop;
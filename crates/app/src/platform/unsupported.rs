@@ -0,0 +1,55 @@
+/*!
+ * Fallback for the desktop integrations that weren't ported to the platform yet
+ */
+use core::time::Duration;
+use crate::{FileDialog, Notification, Tray, ThemeDefault, Error, WResponse};
+
+/// Desktop integration services, nothing is implemented on this platform
+#[derive(Debug, Default)]
+pub struct Desktop;
+
+impl Desktop {
+	/// Nothing to connect to
+	pub fn connect(_app_name: &str) -> Self { Self }
+
+	/// Returns `theme` as is
+	pub fn system_theme(&mut self, theme: ThemeDefault) -> ThemeDefault { theme }
+
+	/// Does nothing
+	pub fn set_theme(&mut self, _theme: ThemeDefault) {}
+
+	/// Not implemented
+	pub fn notify(&mut self, _notification: &Notification) -> Result<u32, Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
+	/// Not implemented
+	pub fn close_notification(&mut self, _id: u32) -> Result<(), Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
+	/// Not implemented
+	pub fn set_tray(&mut self, _tray: Tray) -> Result<(), Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
+	/// Not implemented
+	pub fn set_unread(&mut self, _unread: u32) -> Result<(), Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
+	/// Not implemented
+	pub fn remove_tray(&mut self) -> Result<(), Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
+	/// Not implemented
+	pub fn file_dialog(&mut self, _parent: &str, _dialog: &FileDialog, _save: bool) -> Result<u32, Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
+	/// Not implemented
+	pub fn single_instance(&mut self, _args: &[&str]) -> Result<(), Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
+	/// Not implemented
+	pub fn set_timer(&mut self, _token: u32, _after: Duration, _interval: Option<Duration>) -> Result<(), Error>
+		{ Err(WResponse::BinarySpecificLimitation.into()) }
+
+	/// Nothing to cancel
+	pub fn cancel_timer(&mut self, _token: u32) -> bool { false }
+}
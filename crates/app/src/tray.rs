@@ -0,0 +1,55 @@
+use dirty::String;
+use crate::{Menu, DEFAULT_ICON};
+
+/// Icon on the system tray, activations come back as `Event::TrayActivated`
+/// and the menu items as `Event::MenuAction`
+///
+/// ```rust
+/// app.set_tray(Tray::new("Walkie Talkie").menu(Menu::new(vec![
+/// 	MenuItem::new("toggle-window", "Show/Hide"),
+/// 	MenuItem::separator(),
+/// 	MenuItem::new("quit", "Quit"),
+/// ])))?;
+/// app.set_unread(3)?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tray {
+	/// Title of the tray item, shown on the tooltip
+	pub title: String,
+	/// Path of an image or name of a themed icon
+	pub icon: String,
+	/// Number of unread messages, when not zero an overlay is drawn over the icon
+	pub unread: u32,
+	/// Context menu
+	pub menu: Menu,
+}
+
+impl Tray {
+	/// Creates a tray item with the default icon and an empty menu
+	#[must_use]
+	pub fn new(title: &str) -> Self
+	{
+		Self {
+			title: String::from(title),
+			icon: String::from(DEFAULT_ICON),
+			unread: 0,
+			menu: Menu::default(),
+		}
+	}
+
+	/// Sets the icon, as a path or themed icon name
+	#[must_use]
+	pub fn icon(mut self, icon: &str) -> Self
+	{
+		self.icon = String::from(icon);
+		self
+	}
+
+	/// Sets the context menu
+	#[must_use]
+	pub fn menu(mut self, menu: Menu) -> Self
+	{
+		self.menu = menu;
+		self
+	}
+}
@@ -10,8 +10,17 @@ fn main() {
 	#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
 	targets.push(("exit", "src/core/linux/exit_x64.s"));
 
-	targets.push(("create_thread", "src/core/unix/thread.c"));
-	targets.push(("kill_thread", "src/core/unix/thread.c"));
+	#[cfg(target_family = "unix")]
+	targets.push(("thread", "src/core/unix/thread.c"));
+
+	#[cfg(target_family = "unix")]
+	targets.push(("bus", "src/core/unix/bus.c"));
+
+	#[cfg(target_family = "unix")]
+	targets.push(("socket", "src/core/unix/socket.c"));
+
+	#[cfg(target_os = "linux")]
+	targets.push(("reactor", "src/core/linux/reactor.c"));
 
 	for (name, file) in targets {
 		cc::Build::new()
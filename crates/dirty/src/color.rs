@@ -0,0 +1,473 @@
+/*!
+ * sRGB colors, their parsing and the color spaces used for theming
+ *
+ * ```rust
+ * let accent: Color = "#3584e4".parse()?;
+ * let hover = accent.lighten(0.1);
+ * let text = accent.ensure_contrast(&Color::WHITE, Color::CONTRAST_AA);
+ * assert_eq!(text.to_string(), "#2676d5");
+ * ```
+ *
+ * HSL is the one of CSS, OKLCH is perceptual: the same lightness looks as bright on every hue,
+ * so it is used to lighten, darken and fix contrast without shifting the hue
+ * <https://bottosson.github.io/posts/oklab/>
+ */
+use core::fmt;
+use core::str::FromStr;
+use crate::{math, Error, Vec, WResponse};
+
+// 0.0039215686274 <- I got here before realizing that this is just 1/255
+/// Just a simple constant to normalize the RGB (0-255) value to the normal shader value (0-1)
+/// In old days people used to this trick: `(x * 257) >> 16` (nice ✧ദ്ദി)
+const RGB_NORM: f64 = 1.0 / 255.0;
+
+/// RGB color implementation
+/// reference: <https://github.com/seancroach/hex_color/blob/main/src/lib.rs>
+#[derive(PartialEq, Clone, Debug)]
+#[allow(missing_docs, non_snake_case)]
+pub struct Color { pub R: u8, pub G: u8, pub B: u8, pub A: u8, }
+
+/// A color in HSL, as used by CSS
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsl {
+	/// Degrees, `0.0..360.0`
+	pub hue: f64,
+	/// `0.0..=1.0`
+	pub saturation: f64,
+	/// `0.0..=1.0`
+	pub lightness: f64,
+	/// `0.0..=1.0`
+	pub alpha: f64,
+}
+
+/// A color in OKLCH, the polar form of `OKLab`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Oklch {
+	/// Perceived lightness, `0.0..=1.0`
+	pub lightness: f64,
+	/// Colorfulness, `0.0` is gray and sRGB colors stay below `0.33`
+	pub chroma: f64,
+	/// Degrees, `0.0..360.0`
+	pub hue: f64,
+	/// `0.0..=1.0`
+	pub alpha: f64,
+}
+
+/// Decodes an sRGB channel (`0.0..=1.0`) into linear light, shaders blend in linear light
+#[must_use]
+pub fn srgb_to_linear(channel: f64) -> f64
+{
+	if channel <= 0.040_45 { return channel / 12.92 }
+	math::powf((channel + 0.055) / 1.055, 2.4)
+}
+
+/// Encodes a linear channel (`0.0..=1.0`) back into sRGB
+#[must_use]
+pub fn linear_to_srgb(channel: f64) -> f64
+{
+	if channel <= 0.003_130_8 { return channel * 12.92 }
+	1.055 * math::powf(channel, 1.0 / 2.4) - 0.055
+}
+
+/// Linear sRGB into `OKLab`, the matrices come from the reference above
+#[allow(clippy::unreadable_literal, clippy::many_single_char_names)]
+fn linear_to_oklab(r: f64, g: f64, b: f64) -> (f64, f64, f64)
+{
+	let l = math::cbrt(0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b);
+	let m = math::cbrt(0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b);
+	let s = math::cbrt(0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b);
+	(
+		0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+		1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+		0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+	)
+}
+
+/// `OKLab` into linear sRGB, channels can fall outside `0.0..=1.0` for colors out of the gamut
+#[allow(clippy::unreadable_literal, clippy::many_single_char_names)]
+fn oklab_to_linear(lightness: f64, a: f64, b: f64) -> (f64, f64, f64)
+{
+	let l = cube(lightness + 0.3963377774 * a + 0.2158037573 * b);
+	let m = cube(lightness - 0.1055613458 * a - 0.0638541728 * b);
+	let s = cube(lightness - 0.0894841775 * a - 1.2914855480 * b);
+	(
+		4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+		-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+		-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+	)
+}
+
+/// `x³`
+fn cube(x: f64) -> f64 { x * x * x }
+
+/// Linear channels of an OKLCH color
+fn oklch_to_linear(lightness: f64, chroma: f64, hue: f64) -> (f64, f64, f64)
+{
+	let (sin, cos) = math::sin_cos(hue.to_radians());
+	oklab_to_linear(lightness, chroma * cos, chroma * sin)
+}
+
+/// Linear channels that sRGB can show, with some room for rounding
+fn in_gamut((r, g, b): (f64, f64, f64)) -> bool
+	{ [r, g, b].iter().all(|channel| (-1e-4..=1.0 + 1e-4).contains(channel)) }
+
+impl Color {
+	/// Opaque black
+	pub const BLACK: Self = Self { R: 0, G: 0, B: 0, A: 255 };
+	/// Opaque white
+	pub const WHITE: Self = Self { R: 255, G: 255, B: 255, A: 255 };
+	/// Fully transparent black
+	pub const TRANSPARENT: Self = Self { R: 0, G: 0, B: 0, A: 0 };
+
+	/// WCAG AA contrast for normal text
+	pub const CONTRAST_AA: f64 = 4.5;
+	/// WCAG AA contrast for large text (18pt, or 14pt bold) and interface elements
+	pub const CONTRAST_AA_LARGE: f64 = 3.0;
+	/// WCAG AAA contrast for normal text
+	pub const CONTRAST_AAA: f64 = 7.0;
+
+	/// Create new color value
+	#[must_use]
+	#[allow(non_snake_case)]
+	pub fn from(R: u8, G: u8, B: u8, A: u8) -> Self { Self { R, G, B, A } }
+
+	/// Inverse of `Color::to_default`, the channels are clamped to `0.0..=1.0`
+	#[must_use]
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+	pub fn from_default(r: f64, g: f64, b: f64, a: f64) -> Self
+	{
+		let channel = |v: f64| math::round(v.clamp(0.0, 1.0) * 255.0) as u8;
+		Self::from(channel(r), channel(g), channel(b), channel(a))
+	}
+
+	/// Converts this to a functional method to be used inside functions
+	#[must_use]
+	pub fn to_default(&self) -> ( f64, f64, f64, f64 )
+	{(
+		f64::from(self.R) * RGB_NORM,
+		f64::from(self.G) * RGB_NORM,
+		f64::from(self.B) * RGB_NORM,
+		f64::from(self.A) * RGB_NORM,
+	)}
+
+	/**
+	 * Parses `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`, the `#` is optional and the case doesn't matter
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` for any other length or a character that isn't hexadecimal
+	 */
+	pub fn from_hex(hex: &str) -> Result<Self, Error>
+	{
+		let digits = hex.strip_prefix('#').unwrap_or(hex);
+		let nibbles = digits.chars()
+			.map(|c| c.to_digit(16).and_then(|digit| u8::try_from(digit).ok()))
+			.collect::<Option<Vec<u8>>>()
+			.ok_or(WResponse::InvalidRequest)?;
+
+		let channels: Vec<u8> = match nibbles.len() {
+			// `f` is `ff`
+			3 | 4 => nibbles.iter().map(|nibble| nibble * 17).collect(),
+			6 | 8 => nibbles.chunks_exact(2).map(|pair| match pair {
+				[high, low] => high << 4 | low,
+				_ => 0,
+			}).collect(),
+			_ => return Err(WResponse::InvalidRequest.into()),
+		};
+
+		match channels[..] {
+			[r, g, b] => Ok(Self::from(r, g, b, 255)),
+			[r, g, b, a] => Ok(Self::from(r, g, b, a)),
+			_ => Err(WResponse::InvalidRequest.into()),
+		}
+	}
+
+	/// Decodes linear light channels, see `srgb_to_linear`
+	#[must_use]
+	pub fn from_linear(r: f64, g: f64, b: f64, a: f64) -> Self
+		{ Self::from_default(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a) }
+
+	/// The color channels in linear light, alpha is left as it is
+	#[must_use]
+	pub fn to_linear(&self) -> (f64, f64, f64, f64)
+	{
+		let (r, g, b, a) = self.to_default();
+		(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+	}
+
+	/// Converts from HSL, the hue wraps around and the rest is clamped
+	#[must_use]
+	pub fn from_hsl(hsl: Hsl) -> Self
+	{
+		let saturation = hsl.saturation.clamp(0.0, 1.0);
+		let lightness = hsl.lightness.clamp(0.0, 1.0);
+		let hue = math::rem_euclid(hsl.hue, 360.0);
+
+		// <https://www.w3.org/TR/css-color-4/#hsl-to-rgb>
+		let range = saturation * lightness.min(1.0 - lightness);
+		let channel = |n: f64| {
+			let k = math::rem_euclid(n + hue / 30.0, 12.0);
+			lightness - range * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+		};
+		Self::from_default(channel(0.0), channel(8.0), channel(4.0), hsl.alpha)
+	}
+
+	/// Converts to HSL, grays have a hue of `0.0`
+	#[must_use]
+	pub fn to_hsl(&self) -> Hsl
+	{
+		let (r, g, b, alpha) = self.to_default();
+		let max = r.max(g).max(b);
+		let min = r.min(g).min(b);
+		let delta = max - min;
+		let lightness = f64::midpoint(max, min);
+
+		if delta < f64::EPSILON { return Hsl { hue: 0.0, saturation: 0.0, lightness, alpha } }
+
+		let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+		let sector = if r >= g && r >= b {
+			math::rem_euclid((g - b) / delta, 6.0)
+		} else if g >= b {
+			(b - r) / delta + 2.0
+		} else {
+			(r - g) / delta + 4.0
+		};
+
+		Hsl { hue: sector * 60.0, saturation, lightness, alpha }
+	}
+
+	/**
+	 * Converts from OKLCH, the hue wraps around
+	 * colors sRGB can't show keep their lightness and hue and lose chroma until they fit
+	 */
+	#[must_use]
+	pub fn from_oklch(oklch: Oklch) -> Self
+	{
+		let lightness = oklch.lightness.clamp(0.0, 1.0);
+		let hue = math::rem_euclid(oklch.hue, 360.0);
+		let mut chroma = oklch.chroma.max(0.0);
+
+		if !in_gamut(oklch_to_linear(lightness, chroma, hue)) {
+			// the gray of the same lightness always fits, search the most colorful one that does
+			let (mut low, mut high) = (0.0, chroma);
+			for _ in 0..24 {
+				let middle = f64::midpoint(low, high);
+				if in_gamut(oklch_to_linear(lightness, middle, hue)) { low = middle } else { high = middle }
+			}
+			chroma = low;
+		}
+
+		let (r, g, b) = oklch_to_linear(lightness, chroma, hue);
+		Self::from_linear(r, g, b, oklch.alpha)
+	}
+
+	/// Converts to OKLCH, grays have a hue of `0.0`
+	#[must_use]
+	pub fn to_oklch(&self) -> Oklch
+	{
+		let (r, g, b, alpha) = self.to_linear();
+		let (lightness, green_red, blue_yellow) = linear_to_oklab(r, g, b);
+		let chroma = math::hypot(green_red, blue_yellow);
+		let hue = if chroma < 1e-6 { 0.0 } else { math::rem_euclid(math::atan2(blue_yellow, green_red).to_degrees(), 360.0) };
+		Oklch { lightness, chroma, hue, alpha }
+	}
+
+	/// Raises the OKLCH lightness by `amount` (`0.1` is a visible step), the hue doesn't change
+	#[must_use]
+	pub fn lighten(&self, amount: f64) -> Self
+	{
+		let mut oklch = self.to_oklch();
+		oklch.lightness = (oklch.lightness + amount).clamp(0.0, 1.0);
+		Self::from_oklch(oklch)
+	}
+
+	/// Lowers the OKLCH lightness by `amount`, same as `lighten(-amount)`
+	#[must_use]
+	pub fn darken(&self, amount: f64) -> Self { self.lighten(-amount) }
+
+	/// The same color with another alpha, clamped to `0.0..=1.0`
+	#[must_use]
+	pub fn with_alpha(&self, alpha: f64) -> Self
+	{
+		let (r, g, b, _) = self.to_default();
+		Self::from_default(r, g, b, alpha)
+	}
+
+	/**
+	 * Puts `self` over `below` with the usual "source over" operator
+	 * it blends the sRGB values like compositors and browsers do, not in linear light
+	 */
+	#[must_use]
+	pub fn over(&self, below: &Self) -> Self
+	{
+		let (r, g, b, a) = self.to_default();
+		let (below_r, below_g, below_b, below_a) = below.to_default();
+
+		let alpha = a + below_a * (1.0 - a);
+		if alpha < f64::EPSILON { return Self::TRANSPARENT }
+
+		let blend = |top: f64, bottom: f64| (top * a + bottom * below_a * (1.0 - a)) / alpha;
+		Self::from_default(blend(r, below_r), blend(g, below_g), blend(b, below_b), alpha)
+	}
+
+	/// Premultiplied ARGB in a native endian `u32`, the layout of `WL_SHM_FORMAT_ARGB8888`
+	#[must_use]
+	pub fn to_argb8888(&self) -> u32
+	{
+		let alpha = u32::from(self.A);
+		// rounded `channel * alpha / 255`
+		let premultiply = |channel: u8| (u32::from(channel) * alpha + 127) / 255;
+		alpha << 24 | premultiply(self.R) << 16 | premultiply(self.G) << 8 | premultiply(self.B)
+	}
+
+	/// WCAG relative luminance, `0.0` for black and `1.0` for white, alpha is ignored
+	#[must_use]
+	pub fn relative_luminance(&self) -> f64
+	{
+		let (r, g, b, _) = self.to_linear();
+		0.2126 * r + 0.7152 * g + 0.0722 * b
+	}
+
+	/**
+	 * WCAG contrast ratio between two colors, from `1.0` (same luminance) to `21.0` (black on white)
+	 * alpha is ignored, put translucent colors `over` their background first
+	 */
+	#[must_use]
+	pub fn contrast_ratio(&self, other: &Self) -> f64
+	{
+		let (first, second) = (self.relative_luminance(), other.relative_luminance());
+		(first.max(second) + 0.05) / (first.min(second) + 0.05)
+	}
+
+	/**
+	 * Changes the lightness of `self` as little as possible so it has at least a contrast of `ratio`
+	 * with `background`, like `CONTRAST_AA`, hue and alpha are kept
+	 * if the ratio can't be reached it goes as far as black or white
+	 */
+	#[must_use]
+	pub fn ensure_contrast(&self, background: &Self, ratio: f64) -> Self
+	{
+		if self.contrast_ratio(background) >= ratio { return self.clone() }
+
+		// whichever extreme gives more room, black on light backgrounds and white on dark ones
+		let darker = Self::BLACK.contrast_ratio(background) >= Self::WHITE.contrast_ratio(background);
+		let oklch = self.to_oklch();
+		let with_lightness = |lightness: f64| Self::from_oklch(Oklch { lightness, ..oklch });
+
+		let (mut near, mut far) = (oklch.lightness, if darker { 0.0 } else { 1.0 });
+		if with_lightness(far).contrast_ratio(background) < ratio { return with_lightness(far) }
+
+		for _ in 0..24 {
+			let middle = f64::midpoint(near, far);
+			if with_lightness(middle).contrast_ratio(background) >= ratio { far = middle } else { near = middle }
+		}
+		with_lightness(far)
+	}
+}
+
+impl FromStr for Color {
+	type Err = Error;
+
+	fn from_str(hex: &str) -> Result<Self, Error> { Self::from_hex(hex) }
+}
+
+/// `#rrggbb`, or `#rrggbbaa` if it isn't opaque, in lowercase
+impl fmt::Display for Color {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		write!(f, "#{:02x}{:02x}{:02x}", self.R, self.G, self.B)?;
+		if self.A != 255 { write!(f, "{:02x}", self.A)?; }
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+	use alloc::string::ToString;
+	use super::{Color, Oklch};
+
+	/// `a` and `b` differ by less than `tolerance`
+	fn close(a: f64, b: f64, tolerance: f64) -> bool { (a - b).abs() < tolerance }
+
+	#[test]
+	fn from_hex_formats()
+	{
+		assert_eq!(Color::from_hex("#3584e4").unwrap(), Color::from(0x35, 0x84, 0xe4, 255));
+		assert_eq!(Color::from_hex("3584E4").unwrap(), Color::from(0x35, 0x84, 0xe4, 255));
+		assert_eq!(Color::from_hex("#fff").unwrap(), Color::WHITE);
+		assert_eq!(Color::from_hex("#0f08").unwrap(), Color::from(0, 255, 0, 0x88));
+		assert_eq!(Color::from_hex("#11223344").unwrap(), Color::from(0x11, 0x22, 0x33, 0x44));
+
+		for invalid in ["", "#", "#12345", "#1234567", "#xyz", "+fff", "#ff ff ff", "#ｆｆｆ"] {
+			assert!(Color::from_hex(invalid).is_err(), "{invalid:?}");
+		}
+	}
+
+	#[test]
+	fn display_round_trips()
+	{
+		assert_eq!(Color::from_hex("#3584E4").unwrap().to_string(), "#3584e4");
+		assert_eq!(Color::from_hex("#11223344").unwrap().to_string(), "#11223344");
+	}
+
+	#[test]
+	fn oklch_reference_values()
+	{
+		// from the reference implementation of the OKLab post
+		let red = Color::from(255, 0, 0, 255).to_oklch();
+		assert!(close(red.lightness, 0.627_955, 1e-4));
+		assert!(close(red.chroma, 0.257_683, 1e-4));
+		assert!(close(red.hue, 29.233_885, 1e-2));
+
+		let blue = Color::from(0, 0, 255, 255).to_oklch();
+		assert!(close(blue.lightness, 0.452_014, 1e-4));
+		assert!(close(blue.chroma, 0.313_214, 1e-4));
+		assert!(close(blue.hue, 264.052_02, 1e-2));
+
+		let white = Color::WHITE.to_oklch();
+		assert!(close(white.lightness, 1.0, 1e-4));
+		assert!(white.chroma < 1e-4);
+		assert!(Color::BLACK.to_oklch().lightness.abs() < 1e-6);
+	}
+
+	#[test]
+	fn oklch_round_trips()
+	{
+		for hex in ["#3584e4", "#e01b24", "#33d17a", "#f6d32d", "#9141ac", "#000000", "#ffffff", "#777777"] {
+			let color = Color::from_hex(hex).unwrap();
+			assert_eq!(Color::from_oklch(color.to_oklch()), color, "{hex}");
+		}
+
+		// too colorful for sRGB, it keeps the lightness and loses chroma
+		let clipped = Color::from_oklch(Oklch { lightness: 0.9, chroma: 0.4, hue: 30.0, alpha: 1.0 });
+		assert!(close(clipped.to_oklch().lightness, 0.9, 1e-2));
+	}
+
+	#[test]
+	fn ensure_contrast_on_white()
+	{
+		// the example of the module docs
+		let accent = Color::from_hex("#3584e4").unwrap();
+		let text = accent.ensure_contrast(&Color::WHITE, Color::CONTRAST_AA);
+		assert_eq!(text.to_string(), "#2676d5");
+		assert!(text.contrast_ratio(&Color::WHITE) >= Color::CONTRAST_AA);
+		assert!(close(text.to_oklch().hue, accent.to_oklch().hue, 1.0));
+	}
+
+	#[test]
+	fn ensure_contrast_limits()
+	{
+		let dark = Color::from(30, 30, 30, 255);
+		let accent = Color::from_hex("#3584e4").unwrap();
+		assert!(accent.ensure_contrast(&dark, Color::CONTRAST_AAA).contrast_ratio(&dark) >= Color::CONTRAST_AAA);
+
+		// already enough, nothing changes
+		assert_eq!(Color::BLACK.ensure_contrast(&Color::WHITE, Color::CONTRAST_AA), Color::BLACK);
+
+		// out of reach, it stops at the extreme
+		let gray = Color::from(128, 128, 128, 255);
+		assert_eq!(gray.ensure_contrast(&gray, 21.0), Color::BLACK);
+		assert!(close(Color::BLACK.contrast_ratio(&Color::WHITE), 21.0, 1e-9));
+	}
+}
@@ -0,0 +1,112 @@
+#include <errno.h>
+#include <stdint.h>
+#include <unistd.h>
+#include <sys/epoll.h>
+#include <sys/eventfd.h>
+#include <sys/timerfd.h>
+
+/*
+ * epoll, eventfd and timerfd for `reactor.rs`
+ * every function returns `-errno` on failure
+ */
+
+// keep these in sync with `Interest` and `Event` on reactor.rs
+#define REACTOR_READABLE 1
+#define REACTOR_WRITABLE 2
+#define REACTOR_CLOSED 4
+
+struct reactor_event {
+    uint64_t token;
+    uint32_t flags;
+};
+
+int reactor_create(void)
+{
+    int fd = epoll_create1(EPOLL_CLOEXEC);
+    return fd == -1 ? -errno : fd;
+}
+
+// `op` is 0 to add, 1 to modify and 2 to remove `fd`
+int reactor_control(int epfd, int op, int fd, uint32_t interest, uint64_t token)
+{
+    static const int ops[] = { EPOLL_CTL_ADD, EPOLL_CTL_MOD, EPOLL_CTL_DEL };
+    if (op < 0 || op > 2)
+        return -EINVAL;
+
+    struct epoll_event event = {0};
+    event.data.u64 = token;
+    event.events = EPOLLRDHUP;
+    if (interest & REACTOR_READABLE)
+        event.events |= EPOLLIN;
+    if (interest & REACTOR_WRITABLE)
+        event.events |= EPOLLOUT;
+
+    return epoll_ctl(epfd, ops[op], fd, &event) == -1 ? -errno : 0;
+}
+
+// waits up to `timeout_ms`, -1 waits forever, returns how many events were stored
+int reactor_wait(int epfd, struct reactor_event *out, int max, int timeout_ms)
+{
+    struct epoll_event events[64];
+    if (max > 64)
+        max = 64;
+
+    int count = epoll_wait(epfd, events, max, timeout_ms);
+    if (count == -1)
+        return -errno;
+
+    for (int i = 0; i < count; i++) {
+        uint32_t flags = 0;
+        if (events[i].events & EPOLLIN)
+            flags |= REACTOR_READABLE;
+        if (events[i].events & EPOLLOUT)
+            flags |= REACTOR_WRITABLE;
+        if (events[i].events & (EPOLLHUP | EPOLLRDHUP | EPOLLERR))
+            flags |= REACTOR_CLOSED;
+
+        out[i].token = events[i].data.u64;
+        out[i].flags = flags;
+    }
+
+    return count;
+}
+
+int reactor_eventfd(void)
+{
+    int fd = eventfd(0, EFD_CLOEXEC | EFD_NONBLOCK);
+    return fd == -1 ? -errno : fd;
+}
+
+int reactor_wake(int fd)
+{
+    uint64_t one = 1;
+    // a full counter is already waking the reactor
+    if (write(fd, &one, sizeof(one)) == -1 && errno != EAGAIN)
+        return -errno;
+    return 0;
+}
+
+// reads and resets the counter of an eventfd or timerfd, 0 if nothing happened
+long reactor_drain(int fd)
+{
+    uint64_t count = 0;
+    if (read(fd, &count, sizeof(count)) == -1)
+        return errno == EAGAIN ? 0 : -errno;
+    return (long)count;
+}
+
+int reactor_timer(void)
+{
+    int fd = timerfd_create(CLOCK_MONOTONIC, TFD_CLOEXEC | TFD_NONBLOCK);
+    return fd == -1 ? -errno : fd;
+}
+
+// fires after `after_ns` and then every `interval_ns`, 0 for a single shot, `after_ns` 0 disarms
+int reactor_timer_set(int fd, uint64_t after_ns, uint64_t interval_ns)
+{
+    struct itimerspec spec = {
+        .it_value = { .tv_sec = after_ns / 1000000000, .tv_nsec = after_ns % 1000000000 },
+        .it_interval = { .tv_sec = interval_ns / 1000000000, .tv_nsec = interval_ns % 1000000000 },
+    };
+    return timerfd_settime(fd, 0, &spec, NULL) == -1 ? -errno : 0;
+}
@@ -0,0 +1,26 @@
+#include <stdint.h>
+#include <stdlib.h>
+#include <time.h>
+#include <unistd.h>
+
+/*
+ * Credentials and environment for the D-Bus client on `dbus/mod.rs`,
+ * the transport itself is a `socket::UnixStream`
+ */
+
+unsigned int bus_uid(void)
+    { return getuid(); }
+
+// nanoseconds since boot, for the reply deadline of `Connection::call`
+uint64_t bus_clock(void)
+{
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    return (uint64_t)now.tv_sec * 1000000000u + (uint64_t)now.tv_nsec;
+}
+
+#ifndef __linux__
+/* Linux reads the environment with `dirty::env` */
+const char *bus_env(const char *name)
+    { return getenv(name); }
+#endif
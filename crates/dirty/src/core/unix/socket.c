@@ -1,43 +1,333 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stddef.h>
 #include <string.h>
 #include <unistd.h>
 #include <sys/socket.h>
 #include <sys/un.h>
 
-struct SocketResponse {
-	int status;
-	int server_socket;
+/*
+ * Unix domain sockets for `socket.rs`
+ * paths are not nul terminated, every function returns `-errno` on failure
+ */
+
+// what `socket_failure` sorts the errno numbers into, keep in sync with `socket.rs`
+enum socket_failure {
+    SOCKET_FAILED = 0,
+    SOCKET_INTERRUPTED = 1,
+    SOCKET_WOULD_BLOCK = 2,
+    SOCKET_DENIED = 3,
+    SOCKET_IN_USE = 4,
+    SOCKET_EXHAUSTED = 5,
+    SOCKET_LOST = 6,
+    SOCKET_INVALID = 7,
 };
 
-// the idea is to connect to wayland using sockets
-// https://wayland-book.com/protocol-design/interfaces-reqs-events.html
-struct SocketResponse create_socket(char* address)
+// the most descriptors a single message carries, same limit as libwayland
+#define SOCKET_MAX_FDS 28
+
+#ifdef MSG_NOSIGNAL
+#define SEND_FLAGS MSG_NOSIGNAL
+#else
+#define SEND_FLAGS 0
+#endif
+
+// fills `addr` with `path`, returns the address length or `-ENAMETOOLONG`
+static int socket_address(struct sockaddr_un *addr, const char *path, size_t len, int abstract_ns)
+{
+    memset(addr, 0, sizeof(*addr));
+    addr->sun_family = AF_UNIX;
+
+#ifndef __linux__
+    // only linux has the abstract namespace
+    if (abstract_ns)
+        return -EINVAL;
+#endif
+
+    // abstract names start with a nul byte, paths keep the last byte for the nul terminator
+    if (len == 0 || len >= sizeof(addr->sun_path))
+        return -ENAMETOOLONG;
+
+    memcpy(addr->sun_path + (abstract_ns ? 1 : 0), path, len);
+    return (int)(offsetof(struct sockaddr_un, sun_path) + len + 1);
+}
+
+// `SOCK_CLOEXEC` is not portable, the flag is set right after the socket is created
+static int socket_cloexec(int fd)
+{
+    if (fcntl(fd, F_SETFD, FD_CLOEXEC) == -1) {
+        int error = errno;
+        close(fd);
+        return -error;
+    }
+
+    return fd;
+}
+
+static int socket_create(int type)
+{
+    int fd = socket(AF_UNIX, type, 0);
+    return fd == -1 ? -errno : socket_cloexec(fd);
+}
+
+int socket_connect(const char *path, size_t len, int abstract_ns, int datagram)
+{
+    struct sockaddr_un addr;
+    int addr_len = socket_address(&addr, path, len, abstract_ns);
+    if (addr_len < 0)
+        return addr_len;
+
+    int fd = socket_create(datagram ? SOCK_DGRAM : SOCK_STREAM);
+    if (fd < 0)
+        return fd;
+
+    if (connect(fd, (struct sockaddr *)&addr, (socklen_t)addr_len) == -1) {
+        int error = errno;
+        close(fd);
+        return -error;
+    }
+
+    return fd;
+}
+
+// a stream socket listens on the address, a datagram socket only binds to it
+int socket_bind(const char *path, size_t len, int abstract_ns, int datagram, int backlog)
+{
+    struct sockaddr_un addr;
+    int addr_len = socket_address(&addr, path, len, abstract_ns);
+    if (addr_len < 0)
+        return addr_len;
+
+    int fd = socket_create(datagram ? SOCK_DGRAM : SOCK_STREAM);
+    if (fd < 0)
+        return fd;
+
+    if (bind(fd, (struct sockaddr *)&addr, (socklen_t)addr_len) == -1
+        || (!datagram && listen(fd, backlog) == -1)) {
+        int error = errno;
+        close(fd);
+        return -error;
+    }
+
+    return fd;
+}
+
+int socket_unbound(void)
+    { return socket_create(SOCK_DGRAM); }
+
+int socket_pair(int datagram, int fds[2])
 {
-	int server_socket;
-	struct sockaddr_un server_addr;
-	int connection_result;
+    if (socketpair(AF_UNIX, datagram ? SOCK_DGRAM : SOCK_STREAM, 0, fds) == -1)
+        return -errno;
 
-	server_socket = socket(AF_UNIX, SOCK_STREAM, 0);
+    int first = socket_cloexec(fds[0]);
+    if (first < 0) {
+        close(fds[1]);
+        return first;
+    }
 
-	server_addr.sun_family = AF_UNIX;
-	strcpy(server_addr.sun_path, address);
+    int second = socket_cloexec(fds[1]);
+    if (second < 0) {
+        close(fds[0]);
+        return second;
+    }
 
-	connection_result = connect(server_socket, (struct sockaddr *)&server_addr, sizeof(server_addr));
+    return 0;
+}
 
-	struct SocketResponse socket_return;
-	socket_return.server_socket = server_socket;
-	socket_return.status = connection_result;
+int socket_nonblocking(int fd, int enable)
+{
+    int flags = fcntl(fd, F_GETFL);
+    if (flags == -1)
+        return -errno;
 
-	return socket_return;
+    flags = enable ? flags | O_NONBLOCK : flags & ~O_NONBLOCK;
+    return fcntl(fd, F_SETFL, flags) == -1 ? -errno : 0;
 }
 
-/*char* recv_socket(int server_socket, char* buf)
+int socket_accept(int fd)
 {
-	recv(server_socket, &buf, strlen(buf), MSG_DONTWAIT);
-	return buf;
-}*/
+    int client = accept(fd, NULL, NULL);
+    if (client == -1)
+        return -errno;
+
+    // BSDs inherit `O_NONBLOCK` from the listener, clients are always blocking
+    if (fcntl(client, F_SETFD, FD_CLOEXEC) == -1 || socket_nonblocking(client, 0) < 0) {
+        int error = errno;
+        close(client);
+        return -error;
+    }
 
-void send_socket(int server_socket, char* ch)
-	{ send(server_socket, &ch, strlen(ch), MSG_DONTWAIT); }
+    return client;
+}
+
+// returns 1 once `fd` is readable or hung up, 0 if `timeout_ms` passed first
+int socket_wait(int fd, int timeout_ms)
+{
+    struct pollfd pfd = { .fd = fd, .events = POLLIN };
+    int ready = poll(&pfd, 1, timeout_ms);
+    return ready == -1 ? -errno : ready;
+}
 
-void close_socket(int server_socket)
-	{ close(server_socket); }
+long socket_send(int fd, const void *buf, size_t len)
+{
+    ssize_t sent = send(fd, buf, len, SEND_FLAGS);
+    return sent == -1 ? -errno : sent;
+}
+
+long socket_send_to(int fd, const void *buf, size_t len, const char *path, size_t path_len, int abstract_ns)
+{
+    struct sockaddr_un addr;
+    int addr_len = socket_address(&addr, path, path_len, abstract_ns);
+    if (addr_len < 0)
+        return addr_len;
+
+    ssize_t sent = sendto(fd, buf, len, SEND_FLAGS, (struct sockaddr *)&addr, (socklen_t)addr_len);
+    return sent == -1 ? -errno : sent;
+}
+
+long socket_recv(int fd, void *buf, size_t len, int nonblock)
+{
+    ssize_t received = recv(fd, buf, len, nonblock ? MSG_DONTWAIT : 0);
+    return received == -1 ? -errno : received;
+}
+
+// sends `buf` with `count` descriptors attached as `SCM_RIGHTS`, stream sockets need at least a byte of data
+long socket_send_fds(int fd, const void *buf, size_t len, const int *fds, size_t count)
+{
+    if (count > SOCKET_MAX_FDS)
+        return -EMSGSIZE;
+
+    union {
+        char buf[CMSG_SPACE(sizeof(int) * SOCKET_MAX_FDS)];
+        struct cmsghdr align;
+    } control;
+    memset(&control, 0, sizeof(control));
+
+    struct iovec iov = { .iov_base = (void *)buf, .iov_len = len };
+    struct msghdr msg = {0};
+    msg.msg_iov = &iov;
+    msg.msg_iovlen = 1;
+
+    if (count > 0) {
+        msg.msg_control = control.buf;
+        msg.msg_controllen = CMSG_SPACE(sizeof(int) * count);
+
+        struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+        cmsg->cmsg_level = SOL_SOCKET;
+        cmsg->cmsg_type = SCM_RIGHTS;
+        cmsg->cmsg_len = CMSG_LEN(sizeof(int) * count);
+        memcpy(CMSG_DATA(cmsg), fds, sizeof(int) * count);
+    }
+
+    ssize_t sent = sendmsg(fd, &msg, SEND_FLAGS);
+    return sent == -1 ? -errno : sent;
+}
+
+/*
+ * receives into `buf` and stores up to `SOCKET_MAX_FDS` descriptors in `fds`, `count` is set to how many arrived
+ * if the sender attached more than that they are all closed and `-EMSGSIZE` is returned
+ */
+long socket_recv_fds(int fd, void *buf, size_t len, int *fds, size_t *count, int nonblock)
+{
+    union {
+        char buf[CMSG_SPACE(sizeof(int) * SOCKET_MAX_FDS)];
+        struct cmsghdr align;
+    } control;
+
+    struct iovec iov = { .iov_base = buf, .iov_len = len };
+    struct msghdr msg = {0};
+    msg.msg_iov = &iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.buf;
+    msg.msg_controllen = sizeof(control.buf);
+
+    int flags = nonblock ? MSG_DONTWAIT : 0;
+#ifdef MSG_CMSG_CLOEXEC
+    flags |= MSG_CMSG_CLOEXEC;
+#endif
+
+    *count = 0;
+    ssize_t received = recvmsg(fd, &msg, flags);
+    if (received == -1)
+        return -errno;
+
+    for (struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg); cmsg != NULL; cmsg = CMSG_NXTHDR(&msg, cmsg)) {
+        if (cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_RIGHTS)
+            continue;
+
+        size_t found = (cmsg->cmsg_len - CMSG_LEN(0)) / sizeof(int);
+        for (size_t i = 0; i < found && *count < SOCKET_MAX_FDS; i++) {
+            int received_fd;
+            memcpy(&received_fd, CMSG_DATA(cmsg) + i * sizeof(int), sizeof(int));
+#ifndef MSG_CMSG_CLOEXEC
+            fcntl(received_fd, F_SETFD, FD_CLOEXEC);
+#endif
+            fds[(*count)++] = received_fd;
+        }
+    }
+
+    if (msg.msg_flags & MSG_CTRUNC) {
+        for (size_t i = 0; i < *count; i++)
+            close(fds[i]);
+        *count = 0;
+        return -EMSGSIZE;
+    }
+
+    return received;
+}
+
+int socket_unlink(const char *path, size_t len)
+{
+    struct sockaddr_un addr;
+    int addr_len = socket_address(&addr, path, len, 0);
+    if (addr_len < 0)
+        return addr_len;
+
+    return unlink(addr.sun_path) == -1 ? -errno : 0;
+}
+
+void socket_close(int fd)
+    { close(fd); }
+
+// the errno numbers differ between linux and macOS (`EADDRINUSE` is 98 on one, 48 on the other)
+int socket_failure(int error)
+{
+    switch (error) {
+    case EINTR:
+        return SOCKET_INTERRUPTED;
+    case EAGAIN:
+#if EWOULDBLOCK != EAGAIN
+    case EWOULDBLOCK:
+#endif
+        return SOCKET_WOULD_BLOCK;
+    case EACCES:
+    case EPERM:
+        return SOCKET_DENIED;
+    case EADDRINUSE:
+        return SOCKET_IN_USE;
+    case ENAMETOOLONG:
+    case EMSGSIZE:
+    case ENOBUFS:
+    case ENOMEM:
+    case EMFILE:
+    case ENFILE:
+        return SOCKET_EXHAUSTED;
+    case ENOENT:
+    case ECONNREFUSED:
+    case ECONNRESET:
+    case EPIPE:
+    case ENOTCONN:
+        return SOCKET_LOST;
+    case EBADF:
+    case EINVAL:
+    case ENOTSOCK:
+    case EDESTADDRREQ:
+    case EOPNOTSUPP:
+    case EISCONN:
+        return SOCKET_INVALID;
+    default:
+        return SOCKET_FAILED;
+    }
+}
@@ -1,22 +1,85 @@
+#define _GNU_SOURCE
+#include <errno.h>
 #include <pthread.h>
+#include <stdint.h>
+#include <string.h>
 
-struct c_Thread {
-	int id;
-	void* thread;
+/*
+ * Threads for `thread.rs`
+ * `pthread_t` is opaque (an integer on linux, a pointer on macOS), it is copied into a `uint64_t`
+ * every function returns `0` or the error number of pthread
+ */
+
+// what `thread_failure` sorts the error numbers into, keep in sync with `thread.rs`
+enum thread_failure {
+    THREAD_FAILED = 0,
+    THREAD_DENIED = 1,
+    THREAD_EXHAUSTED = 2,
+    THREAD_INVALID = 3,
 };
 
-struct c_Thread create_thread(void *(*function)(void *))
+_Static_assert(sizeof(pthread_t) <= sizeof(uint64_t), "pthread_t doesn't fit in 64 bits");
+
+int thread_spawn(void *(*start)(void *), void *arg, size_t stack_size, uint64_t *native)
 {
-	pthread_t thread;
-	int id = pthread_create(&thread, NULL, function, NULL);
+    pthread_attr_t attr;
+    int status = pthread_attr_init(&attr);
+    if (status != 0)
+        return status;
+
+    if (stack_size != 0)
+        status = pthread_attr_setstacksize(&attr, stack_size);
+
+    pthread_t thread;
+    if (status == 0)
+        status = pthread_create(&thread, &attr, start, arg);
 
-	struct c_Thread new_thread;
-	new_thread.id = id;
-	void *ptr = (void *)&thread;
-	new_thread.thread = ptr;
+    pthread_attr_destroy(&attr);
+    if (status != 0)
+        return status;
 
-	return new_thread;
+    *native = 0;
+    memcpy(native, &thread, sizeof(thread));
+    return 0;
 }
 
-void kill_thread(struct c_Thread thread)
-	{ pthread_cancel((pthread_t)thread.thread); }
+int thread_join(uint64_t native)
+{
+    pthread_t thread;
+    memcpy(&thread, &native, sizeof(thread));
+    return pthread_join(thread, NULL);
+}
+
+int thread_detach(uint64_t native)
+{
+    pthread_t thread;
+    memcpy(&thread, &native, sizeof(thread));
+    return pthread_detach(thread);
+}
+
+// the error numbers differ between linux and macOS (`EAGAIN` is 11 on one, 35 on the other)
+int thread_failure(int status)
+{
+    switch (status) {
+    case EPERM:
+        return THREAD_DENIED;
+    case EAGAIN:
+        return THREAD_EXHAUSTED;
+    case EINVAL:
+    case ESRCH:
+    case EDEADLK:
+        return THREAD_INVALID;
+    default:
+        return THREAD_FAILED;
+    }
+}
+
+// names are limited to 16 bytes with the nul terminator on linux
+int thread_set_name(const char *name)
+{
+#ifdef __APPLE__
+    return pthread_setname_np(name);
+#else
+    return pthread_setname_np(pthread_self(), name);
+#endif
+}
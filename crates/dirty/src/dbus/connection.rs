@@ -0,0 +1,432 @@
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::time::Duration;
+use log::{debug, warn};
+
+use crate::{Detail, Error, WResponse};
+use super::{Transport, MatchRule, Message, MessageType, Value};
+
+/// Well known name, path and interface of the bus daemon itself
+const DBUS: &str = "org.freedesktop.DBus";
+/// Object path of the bus daemon
+const DBUS_PATH: &str = "/org/freedesktop/DBus";
+/// How long `call` waits for a reply by default, same as libdbus
+const REPLY_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// An authenticated connection to a message bus
+#[derive(Debug)]
+pub struct Connection<T: Transport> {
+	/// The socket (or anything else) the messages go through
+	transport: T,
+	/// Serial of the last message sent
+	serial: u32,
+	/// Name assigned by the daemon on `Hello`
+	unique_name: String,
+	/// Bytes received that don't form a complete message yet
+	incoming: Vec<u8>,
+	/// Messages received while waiting for a method reply
+	queue: VecDeque<Message>,
+	/// How long `call` waits for a reply
+	timeout: Duration,
+}
+
+impl<T: Transport> Connection<T> {
+	/**
+	 * Authenticates with SASL `EXTERNAL` and registers on the bus
+	 *
+	 * # Errors
+	 *
+	 * `AccessDenied` if the daemon refused the credentials,
+	 * `ConnectionLost` if the transport broke during the handshake
+	 */
+	pub fn new(transport: T, uid: u32) -> Result<Self, Error>
+	{
+		let mut connection = Self {
+			transport,
+			serial: 0,
+			unique_name: String::new(),
+			incoming: Vec::new(),
+			queue: VecDeque::new(),
+			timeout: REPLY_TIMEOUT,
+		};
+
+		connection.authenticate(uid)?;
+
+		let reply = connection.call(&Message::method_call(DBUS, DBUS_PATH, DBUS, "Hello"))?;
+		connection.unique_name = reply
+			.get(0)
+			.and_then(Value::as_str)
+			.ok_or(WResponse::UnexpectedError)?
+			.to_string();
+
+		debug!("connected to the bus as {}", connection.unique_name);
+		Ok(connection)
+	}
+
+	/// <https://dbus.freedesktop.org/doc/dbus-specification.html#auth-protocol>
+	fn authenticate(&mut self, uid: u32) -> Result<(), Error>
+	{
+		// the uid is sent as the hex encoding of its ascii representation
+		let hex = uid.to_string().bytes().fold(String::new(), |mut hex, b| {
+			let _ = write!(hex, "{b:02x}");
+			hex
+		});
+
+		self.write_all(b"\0")?;
+		self.write_all(crate::format!("AUTH EXTERNAL {hex}\r\n").as_bytes())?;
+
+		let line = self.read_line()?;
+		if !line.starts_with("OK ") {
+			warn!("the bus rejected the authentication: {line}");
+			return Err(WResponse::AccessDenied.into());
+		}
+
+		self.write_all(b"BEGIN\r\n")
+	}
+
+	/// Reads a line of the auth protocol, byte by byte since the messages follow right after it
+	fn read_line(&mut self) -> Result<String, Error>
+	{
+		let mut line = Vec::new();
+		loop {
+			let mut byte = [0u8; 1];
+			match self.transport.recv(&mut byte, true)? {
+				Some(0) | None => return Err(WResponse::ConnectionLost.into()),
+				Some(_) => {},
+			}
+			let [b] = byte;
+			if b == b'\n' { break }
+			line.push(b);
+			if line.len() > 512 { return Err(WResponse::OutOfBounds.into()) }
+		}
+
+		if line.last() == Some(&b'\r') { let _ = line.pop(); }
+		String::from_utf8(line).map_err(|_| WResponse::InvalidRequest.into())
+	}
+
+	/// Sends the whole buffer
+	fn write_all(&mut self, mut data: &[u8]) -> Result<(), Error>
+	{
+		while !data.is_empty() {
+			let sent = self.transport.send(data)?;
+			if sent == 0 { return Err(WResponse::ConnectionLost.into()) }
+			data = data.get(sent..).unwrap_or_default();
+		}
+		Ok(())
+	}
+
+	/// The unique name of this connection, like `:1.42`
+	#[must_use]
+	pub fn unique_name(&self) -> &str { &self.unique_name }
+
+	/// Returns the underlying transport
+	#[must_use]
+	pub fn transport(&self) -> &T { &self.transport }
+
+	/// Sets how long `call` waits for a reply, 25 seconds by default
+	pub fn set_timeout(&mut self, timeout: Duration) { self.timeout = timeout; }
+
+	/**
+	 * Sends a message without waiting for a reply
+	 * returns the serial used
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the transport failed
+	 */
+	pub fn send(&mut self, message: &Message) -> Result<u32, Error>
+	{
+		self.serial = self.serial.wrapping_add(1).max(1);
+		let data = message.encode(self.serial)?;
+		self.write_all(&data)?;
+		Ok(self.serial)
+	}
+
+	/**
+	 * Calls a method and blocks until the reply arrives or the timeout passes,
+	 * other messages received in the meantime are kept for `poll`
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the remote replied with an error,
+	 * `ConnectionLost` if the transport failed or no reply came in time
+	 */
+	pub fn call(&mut self, message: &Message) -> Result<Message, Error>
+	{
+		let serial = self.send(message)?;
+		let mut left = self.timeout;
+
+		loop {
+			let Some(reply) = self.read_message(false)? else {
+				// the deadline holds for the whole call, the signals arriving meanwhile don't extend it
+				left = self.transport.wait(left)?.ok_or_else(|| Error::new(WResponse::ConnectionLost)
+					.with_message(crate::format!(
+						"no reply to {}.{} in time",
+						message.interface.as_deref().unwrap_or_default(),
+						message.member.as_deref().unwrap_or_default(),
+					))
+					.with_detail(Detail::DBus(String::from("org.freedesktop.DBus.Error.NoReply"))))?;
+				continue;
+			};
+
+			if reply.reply_serial != Some(serial) {
+				self.queue.push_back(reply);
+				continue;
+			}
+
+			if reply.kind == MessageType::Error {
+				warn!(
+					"{}.{}: {} {}",
+					message.interface.as_deref().unwrap_or_default(),
+					message.member.as_deref().unwrap_or_default(),
+					reply.error_name.as_deref().unwrap_or_default(),
+					reply.get(0).and_then(Value::as_str).unwrap_or_default(),
+				);
+				let name = reply.error_name.clone().unwrap_or_default();
+				let text = String::from(reply.get(0).and_then(Value::as_str).unwrap_or(name.as_str()));
+				return Err(Error::new(WResponse::InvalidRequest).with_message(text).with_detail(Detail::DBus(name)));
+			}
+
+			return Ok(reply);
+		}
+	}
+
+	/**
+	 * Returns the next message without blocking,
+	 * `None` if nothing arrived yet
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the transport failed
+	 */
+	pub fn poll(&mut self) -> Result<Option<Message>, Error>
+	{
+		if let Some(message) = self.queue.pop_front() { return Ok(Some(message)) }
+		self.read_message(false)
+	}
+
+	/**
+	 * Whether a message was already read from the transport and `poll` will return it,
+	 * the socket may not be readable anymore, so waiting on it would miss the message
+	 * an undecodable header also counts, `poll` reports it
+	 */
+	#[must_use]
+	pub fn has_pending(&self) -> bool
+	{
+		if !self.queue.is_empty() { return true }
+		Message::size(&self.incoming).map_or(true, |size| size.is_some_and(|len| self.incoming.len() >= len))
+	}
+
+	/**
+	 * Blocks until the next message arrives
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the transport failed
+	 */
+	pub fn recv(&mut self) -> Result<Message, Error>
+	{
+		if let Some(message) = self.queue.pop_front() { return Ok(message) }
+		self.read_message(true)?.ok_or(WResponse::ConnectionLost.into())
+	}
+
+	/// Reads until a complete message is buffered
+	fn read_message(&mut self, block: bool) -> Result<Option<Message>, Error>
+	{
+		loop {
+			if let Some(size) = Message::size(&self.incoming)?
+				&& self.incoming.len() >= size
+			{
+				let message = Message::decode(self.incoming.get(..size).unwrap_or_default())?;
+				let _ = self.incoming.drain(..size);
+				return Ok(Some(message));
+			}
+
+			let mut buf = [0u8; 4096];
+			match self.transport.recv(&mut buf, block)? {
+				None => return Ok(None),
+				Some(0) => return Err(WResponse::ConnectionLost.into()),
+				Some(len) => self.incoming.extend_from_slice(buf.get(..len).unwrap_or_default()),
+			}
+		}
+	}
+
+	/**
+	 * Asks the daemon to route the signals matching `rule` to this connection
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::call`
+	 */
+	pub fn add_match(&mut self, rule: &MatchRule) -> Result<(), Error>
+	{
+		let call = Message::method_call(DBUS, DBUS_PATH, DBUS, "AddMatch")
+			.arg(rule.to_string());
+		self.call(&call).map(|_| ())
+	}
+
+	/**
+	 * Stops receiving the signals matching `rule`
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::call`
+	 */
+	pub fn remove_match(&mut self, rule: &MatchRule) -> Result<(), Error>
+	{
+		let call = Message::method_call(DBUS, DBUS_PATH, DBUS, "RemoveMatch")
+			.arg(rule.to_string());
+		self.call(&call).map(|_| ())
+	}
+
+	/**
+	 * Requests a well known name, returns `true` if this connection is now the owner
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::call`
+	 */
+	pub fn request_name(&mut self, name: &str) -> Result<bool, Error>
+	{
+		// DBUS_NAME_FLAG_DO_NOT_QUEUE
+		let call = Message::method_call(DBUS, DBUS_PATH, DBUS, "RequestName")
+			.arg(name)
+			.arg(4u32);
+		let reply = self.call(&call)?;
+		// DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER
+		Ok(reply.get(0).and_then(Value::as_u32) == Some(1))
+	}
+
+	/**
+	 * Releases a well known name owned by this connection
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::call`
+	 */
+	pub fn release_name(&mut self, name: &str) -> Result<(), Error>
+	{
+		let call = Message::method_call(DBUS, DBUS_PATH, DBUS, "ReleaseName").arg(name);
+		self.call(&call).map(|_| ())
+	}
+
+	/**
+	 * Replies to a method call with a successful return
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::send`
+	 */
+	pub fn reply(&mut self, call: &Message, body: Vec<Value>) -> Result<(), Error>
+	{
+		if call.flags & super::NO_REPLY_EXPECTED != 0 { return Ok(()) }
+		let mut reply = Message::method_return(call);
+		reply.body = body;
+		self.send(&reply).map(|_| ())
+	}
+
+	/**
+	 * Replies to a method call with an error, like `org.freedesktop.DBus.Error.UnknownMethod`
+	 *
+	 * # Errors
+	 *
+	 * Same as `Connection::send`
+	 */
+	pub fn reply_error(&mut self, call: &Message, name: &str, text: &str) -> Result<(), Error>
+	{
+		if call.flags & super::NO_REPLY_EXPECTED != 0 { return Ok(()) }
+		self.send(&Message::error(call, name, text)).map(|_| ())
+	}
+}
+
+#[cfg(all(test, target_family = "unix"))]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	extern crate std;
+
+	use core::time::Duration;
+	use std::io::{BufRead, BufReader};
+	use std::process::{Child, Command, Stdio};
+	use crate::Detail;
+	use super::super::{unix, BusSocket, Connection, MatchRule, Message, Value};
+
+	/// A private session bus, killed on drop
+	struct Daemon {
+		/// The `dbus-daemon` process
+		child: Child,
+		/// Where it listens
+		address: std::string::String,
+	}
+
+	impl Daemon {
+		/// `None` if `dbus-daemon` isn't installed, the test is skipped then
+		fn spawn() -> Option<Self>
+		{
+			let mut child = Command::new("dbus-daemon")
+				.args(["--session", "--nofork", "--print-address"])
+				.stdout(Stdio::piped())
+				.stderr(Stdio::null())
+				.spawn()
+				.ok()?;
+
+			let mut address = std::string::String::new();
+			let _ = BufReader::new(child.stdout.take()?).read_line(&mut address).ok()?;
+			Some(Self { child, address: std::string::String::from(address.trim()) })
+		}
+
+		fn connect(&self) -> Connection<BusSocket>
+			{ Connection::new(BusSocket::connect(&self.address).unwrap(), unsafe { unix::bus_uid() }).unwrap() }
+	}
+
+	impl Drop for Daemon {
+		fn drop(&mut self)
+		{
+			let _ = self.child.kill();
+			let _ = self.child.wait();
+		}
+	}
+
+	#[test]
+	fn call_match_and_request_name()
+	{
+		let Some(daemon) = Daemon::spawn() else { return };
+		let mut service = daemon.connect();
+		let mut client = daemon.connect();
+
+		assert!(service.request_name("org.example.Dirty").unwrap());
+		assert!(!client.request_name("org.example.Dirty").unwrap());
+
+		let owner = client.call(&Message::method_call(super::DBUS, super::DBUS_PATH, super::DBUS, "GetNameOwner")
+			.arg("org.example.Dirty")).unwrap();
+		assert_eq!(owner.get(0).and_then(Value::as_str), Some(service.unique_name()));
+
+		// the quote has to survive the match rule syntax for the daemon to route the signal
+		let rule = MatchRule::signal("org.example.Dirty", "Changed").arg0("it's");
+		client.add_match(&rule).unwrap();
+		let _ = service.send(&Message::signal("/org/example/Dirty", "org.example.Dirty", "Changed").arg("its")).unwrap();
+		let _ = service.send(&Message::signal("/org/example/Dirty", "org.example.Dirty", "Changed").arg("it's")).unwrap();
+
+		// `NameAcquired` for the unique name comes first, the unmatched signal never does
+		let signal = core::iter::repeat_with(|| client.recv().unwrap())
+			.find(|message| message.is_signal("org.example.Dirty", "Changed"))
+			.unwrap();
+		assert!(rule.matches(&signal));
+		assert_eq!(signal.get(0).and_then(Value::as_str), Some("it's"));
+	}
+
+	#[test]
+	fn call_gives_up_after_the_timeout()
+	{
+		let Some(daemon) = Daemon::spawn() else { return };
+		let service = daemon.connect();
+		let mut client = daemon.connect();
+		client.set_timeout(Duration::from_millis(100));
+
+		// the service never reads its messages, so the call is never answered
+		let call = Message::method_call(service.unique_name(), "/", "org.example.Dirty", "Hang");
+		let error = client.call(&call).unwrap_err();
+		assert_eq!(error.detail(), &Detail::DBus(std::string::String::from("org.freedesktop.DBus.Error.NoReply")));
+	}
+}
@@ -0,0 +1,303 @@
+/*!
+ * Minimal D-Bus client, enough to talk with portals, notifications, trays and menus
+ * <https://dbus.freedesktop.org/doc/dbus-specification.html>
+ *
+ * ```rust
+ * let mut bus = dirty::dbus::session()?;
+ * let reply = bus.call(&Message::method_call(
+ * 	"org.freedesktop.portal.Desktop",
+ * 	"/org/freedesktop/portal/desktop",
+ * 	"org.freedesktop.portal.Settings",
+ * 	"ReadOne",
+ * ).arg("org.freedesktop.appearance").arg("color-scheme"))?;
+ * ```
+ */
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use crate::{Error, WResponse};
+#[cfg(target_family = "unix")]
+use crate::socket::{Address, UnixStream};
+
+/// Marshalling of values and messages
+mod wire;
+/// Authentication and message routing
+mod connection;
+
+pub use wire::{Value, Message, MessageType, NO_REPLY_EXPECTED, MAX_MESSAGE_SIZE};
+pub use connection::Connection;
+
+/// Anything that can carry the bytes of a connection
+pub trait Transport {
+	/**
+	 * Sends part of `data`, returns how many bytes were sent
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	fn send(&mut self, data: &[u8]) -> Result<usize, Error>;
+
+	/**
+	 * Receives up to `buf.len()` bytes, `Some(0)` means the peer closed the connection
+	 * if `block` is false and there is nothing to read returns `None`
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	fn recv(&mut self, buf: &mut [u8], block: bool) -> Result<Option<usize>, Error>;
+
+	/**
+	 * Waits up to `timeout` for `recv` to have something, returns what is left of `timeout`
+	 * `None` if it passed first
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	fn wait(&mut self, timeout: Duration) -> Result<Option<Duration>, Error>;
+}
+
+/// Filter for the signals that should be routed to a connection
+/// <https://dbus.freedesktop.org/doc/dbus-specification.html#message-bus-routing-match-rules>
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchRule {
+	/// Bus name of the sender
+	pub sender: Option<String>,
+	/// Object path of the sender
+	pub path: Option<String>,
+	/// Signal interface
+	pub interface: Option<String>,
+	/// Signal name
+	pub member: Option<String>,
+	/// First argument, if it is a string
+	pub arg0: Option<String>,
+}
+
+impl MatchRule {
+	/// Matches the signal `interface.member`
+	#[must_use]
+	pub fn signal(interface: &str, member: &str) -> Self
+	{
+		Self {
+			interface: Some(String::from(interface)),
+			member: Some(String::from(member)),
+			..Self::default()
+		}
+	}
+
+	/// Only match signals sent by `sender`
+	#[must_use]
+	pub fn sender(mut self, sender: &str) -> Self
+	{
+		self.sender = Some(String::from(sender));
+		self
+	}
+
+	/// Only match signals emitted from `path`
+	#[must_use]
+	pub fn path(mut self, path: &str) -> Self
+	{
+		self.path = Some(String::from(path));
+		self
+	}
+
+	/// Only match signals whose first argument is `arg0`
+	#[must_use]
+	pub fn arg0(mut self, arg0: &str) -> Self
+	{
+		self.arg0 = Some(String::from(arg0));
+		self
+	}
+
+	/// Returns true if `message` is a signal matched by the rule
+	/// Well known sender names can't be checked locally, the daemon only reports unique names
+	#[must_use]
+	pub fn matches(&self, message: &Message) -> bool
+	{
+		let check = |rule: &Option<String>, field: Option<&str>|
+			rule.as_deref().is_none_or(|r| Some(r) == field);
+
+		message.kind == MessageType::Signal
+			&& check(&self.path, message.path.as_deref())
+			&& check(&self.interface, message.interface.as_deref())
+			&& check(&self.member, message.member.as_deref())
+			&& check(&self.arg0, message.get(0).and_then(Value::as_str))
+			&& self.sender.as_deref().is_none_or(|s|
+				!s.starts_with(':') || Some(s) == message.sender.as_deref())
+	}
+}
+
+impl fmt::Display for MatchRule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		f.write_str("type='signal'")?;
+		let fields = [
+			("sender", &self.sender),
+			("path", &self.path),
+			("interface", &self.interface),
+			("member", &self.member),
+			("arg0", &self.arg0),
+		];
+		for (key, value) in fields {
+			let Some(v) = value else { continue };
+			// there are no escapes inside quotes, a quote closes them, is escaped and opens them again
+			write!(f, ",{key}='")?;
+			for (i, part) in v.split('\'').enumerate() {
+				if i > 0 { f.write_str("'\\''")? }
+				f.write_str(part)?;
+			}
+			f.write_str("'")?;
+		}
+		Ok(())
+	}
+}
+
+/// Unix socket transport, this is what the bus daemons use
+#[cfg(target_family = "unix")]
+#[derive(Debug)]
+pub struct BusSocket {
+	/// The connected socket
+	stream: UnixStream,
+}
+
+#[cfg(target_family = "unix")]
+/// This will handle with our C imports from `unix/bus.c`
+mod unix {
+	unsafe extern "C" {
+		pub(crate) fn bus_uid() -> u32;
+		pub(crate) fn bus_clock() -> u64;
+		#[cfg(not(target_os = "linux"))]
+		pub(crate) fn bus_env(name: *const core::ffi::c_char) -> *const core::ffi::c_char;
+	}
+}
+
+#[cfg(target_family = "unix")]
+impl BusSocket {
+	/**
+	 * Connects to the first reachable `unix:` entry of a D-Bus address,
+	 * like `unix:path=/run/user/1000/bus`
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if no `unix:` entry was found,
+	 * `AccessDenied` or `ConnectionLost` if the socket couldn't be reached
+	 */
+	pub fn connect(address: &str) -> Result<Self, Error>
+	{
+		let mut response = Error::new(WResponse::InvalidRequest);
+
+		for entry in address.split(';') {
+			let Some(params) = entry.strip_prefix("unix:") else { continue };
+
+			for param in params.split(',') {
+				let (abstract_ns, value) = match param.split_once('=') {
+					Some(("path", v)) => (false, v),
+					Some(("abstract", v)) => (true, v),
+					_ => continue,
+				};
+
+				let raw = unescape(value);
+				let Ok(path) = core::str::from_utf8(&raw) else { continue };
+				let target = if abstract_ns { Address::Abstract(path) } else { Address::Path(path) };
+
+				match UnixStream::connect(target) {
+					Ok(stream) => return Ok(Self { stream }),
+					Err(e) => response = e,
+				}
+			}
+		}
+
+		Err(response)
+	}
+
+	/// The socket file descriptor, to be watched by an event loop
+	#[must_use]
+	pub fn fd(&self) -> i32 { self.stream.fd() }
+}
+
+#[cfg(target_family = "unix")]
+impl Transport for BusSocket {
+	fn send(&mut self, data: &[u8]) -> Result<usize, Error>
+	{
+		// the stream is blocking, a send never gives up without an error
+		self.stream.send(data).map(Option::unwrap_or_default)
+	}
+
+	fn recv(&mut self, buf: &mut [u8], block: bool) -> Result<Option<usize>, Error>
+	{
+		if block { self.stream.recv(buf) } else { self.stream.try_recv(buf) }
+	}
+
+	fn wait(&mut self, timeout: Duration) -> Result<Option<Duration>, Error>
+	{
+		let start = unsafe { unix::bus_clock() };
+		if !self.stream.wait_readable(timeout)? { return Ok(None) }
+		let waited = Duration::from_nanos(unsafe { unix::bus_clock() }.saturating_sub(start));
+		Ok(Some(timeout.saturating_sub(waited)))
+	}
+}
+
+/// Decodes the `%XX` escapes of an address value
+fn unescape(value: &str) -> Vec<u8>
+{
+	let mut out = Vec::with_capacity(value.len());
+	let mut bytes = value.bytes();
+	while let Some(b) = bytes.next() {
+		if b != b'%' { out.push(b); continue }
+		let hex = [bytes.next().unwrap_or(b'0'), bytes.next().unwrap_or(b'0')];
+		let decoded = core::str::from_utf8(&hex).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+		out.push(decoded.unwrap_or(b'%'));
+	}
+	out
+}
+
+/// Reads an environment variable as the process started with it
+#[cfg(target_os = "linux")]
+fn env(name: &core::ffi::CStr) -> Option<String>
+	{ crate::env::var(name.to_str().ok()?) }
+
+/// Reads an environment variable through libc, `dirty::env` is Linux only
+#[cfg(all(target_family = "unix", not(target_os = "linux")))]
+fn env(name: &core::ffi::CStr) -> Option<String>
+{
+	let value = unsafe { unix::bus_env(name.as_ptr()) };
+	if value.is_null() { return None }
+	unsafe { core::ffi::CStr::from_ptr(value) }.to_str().ok().map(String::from)
+}
+
+/**
+ * Connects to the user session bus
+ * the address comes from `DBUS_SESSION_BUS_ADDRESS` or defaults to `$XDG_RUNTIME_DIR/bus`
+ *
+ * # Errors
+ *
+ * Check `BusSocket::connect` and `Connection::new`
+ */
+#[cfg(target_family = "unix")]
+pub fn session() -> Result<Connection<BusSocket>, Error>
+{
+	let address = env(c"DBUS_SESSION_BUS_ADDRESS")
+		.or_else(|| env(c"XDG_RUNTIME_DIR").map(|dir| crate::format!("unix:path={dir}/bus")))
+		.ok_or(WResponse::MissingDependencies)?;
+
+	Connection::new(BusSocket::connect(&address)?, unsafe { unix::bus_uid() })
+}
+
+/**
+ * Connects to the system bus
+ *
+ * # Errors
+ *
+ * Check `BusSocket::connect` and `Connection::new`
+ */
+#[cfg(target_family = "unix")]
+pub fn system() -> Result<Connection<BusSocket>, Error>
+{
+	let address = env(c"DBUS_SYSTEM_BUS_ADDRESS")
+		.unwrap_or_else(|| String::from("unix:path=/var/run/dbus/system_bus_socket"));
+
+	Connection::new(BusSocket::connect(&address)?, unsafe { unix::bus_uid() })
+}
@@ -0,0 +1,957 @@
+/*!
+ * D-Bus wire format, only little endian messages are produced
+ * <https://dbus.freedesktop.org/doc/dbus-specification.html#message-protocol-marshaling>
+ */
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use crate::{Error, WResponse};
+
+/// Messages bigger than this are rejected by the bus daemon anyway
+pub const MAX_MESSAGE_SIZE: usize = 1 << 27;
+
+/// Longest array the spec allows, 64 MiB
+const MAX_ARRAY_SIZE: usize = 1 << 26;
+
+/// Size of the fixed part of the header, the field array length included
+const FIXED_HEADER: usize = 16;
+
+/// A single D-Bus value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	/// `y`
+	Byte(u8),
+	/// `b`
+	Bool(bool),
+	/// `n`
+	Int16(i16),
+	/// `q`
+	UInt16(u16),
+	/// `i`
+	Int32(i32),
+	/// `u`
+	UInt32(u32),
+	/// `x`
+	Int64(i64),
+	/// `t`
+	UInt64(u64),
+	/// `d`
+	Double(f64),
+	/// `s`
+	Str(String),
+	/// `o`
+	ObjectPath(String),
+	/// `g`
+	Signature(String),
+	/// `h`, index on the array of file descriptors sent with the message
+	UnixFd(u32),
+	/// `a`, the element signature is kept so empty arrays can be encoded
+	Array(String, Vec<Value>),
+	/// `(...)`
+	Struct(Vec<Value>),
+	/// `{..}`, only valid as an array element
+	DictEntry(Box<Value>, Box<Value>),
+	/// `v`
+	Variant(Box<Value>),
+}
+
+impl Value {
+	/// Creates a `a{..}` dictionary
+	#[must_use]
+	pub fn dict(key: &str, value: &str, entries: Vec<(Value, Value)>) -> Self
+	{
+		let items = entries
+			.into_iter()
+			.map(|(k, v)| Value::DictEntry(Box::new(k), Box::new(v)))
+			.collect();
+		Value::Array(crate::format!("{{{key}{value}}}"), items)
+	}
+
+	/// Creates a `a{sv}` dictionary, the most common one
+	#[must_use]
+	pub fn properties(entries: Vec<(&str, Value)>) -> Self
+	{
+		let items = entries
+			.into_iter()
+			.map(|(k, v)| (Value::from(k), Value::Variant(Box::new(v))))
+			.collect();
+		Self::dict("s", "v", items)
+	}
+
+	/// Wraps the value inside a variant
+	#[must_use]
+	pub fn variant(self) -> Self { Value::Variant(Box::new(self)) }
+
+	/// Returns the D-Bus signature of the value
+	#[must_use]
+	pub fn signature(&self) -> String
+	{
+		match self {
+			Value::Byte(_) => "y".to_string(),
+			Value::Bool(_) => "b".to_string(),
+			Value::Int16(_) => "n".to_string(),
+			Value::UInt16(_) => "q".to_string(),
+			Value::Int32(_) => "i".to_string(),
+			Value::UInt32(_) => "u".to_string(),
+			Value::Int64(_) => "x".to_string(),
+			Value::UInt64(_) => "t".to_string(),
+			Value::Double(_) => "d".to_string(),
+			Value::Str(_) => "s".to_string(),
+			Value::ObjectPath(_) => "o".to_string(),
+			Value::Signature(_) => "g".to_string(),
+			Value::UnixFd(_) => "h".to_string(),
+			Value::Variant(_) => "v".to_string(),
+			Value::Array(element, _) => crate::format!("a{element}"),
+			Value::DictEntry(k, v) => crate::format!("{{{}{}}}", k.signature(), v.signature()),
+			Value::Struct(fields) => {
+				let inner: String = fields.iter().map(Value::signature).collect();
+				crate::format!("({inner})")
+			},
+		}
+	}
+
+	/// Removes every variant layer around the value
+	#[must_use]
+	pub fn inner(&self) -> &Value
+	{
+		let mut value = self;
+		while let Value::Variant(inner) = value { value = inner }
+		value
+	}
+
+	/// Returns the string of `s`, `o` and `g` values
+	#[must_use]
+	pub fn as_str(&self) -> Option<&str>
+	{
+		match self.inner() {
+			Value::Str(s) | Value::ObjectPath(s) | Value::Signature(s) => Some(s.as_str()),
+			_ => None,
+		}
+	}
+
+	/// Returns any unsigned integer that fits on `u32`
+	#[must_use]
+	pub fn as_u32(&self) -> Option<u32>
+	{
+		match *self.inner() {
+			Value::Byte(v) => Some(u32::from(v)),
+			Value::UInt16(v) => Some(u32::from(v)),
+			Value::UInt32(v) | Value::UnixFd(v) => Some(v),
+			_ => None,
+		}
+	}
+
+	/// Returns any signed integer that fits on `i32`
+	#[must_use]
+	pub fn as_i32(&self) -> Option<i32>
+	{
+		match *self.inner() {
+			Value::Int16(v) => Some(i32::from(v)),
+			Value::Int32(v) => Some(v),
+			_ => None,
+		}
+	}
+
+	/// Returns the value of a `b`
+	#[must_use]
+	pub fn as_bool(&self) -> Option<bool>
+	{
+		match *self.inner() {
+			Value::Bool(v) => Some(v),
+			_ => None,
+		}
+	}
+
+	/// Returns the value of a `d`
+	#[must_use]
+	pub fn as_f64(&self) -> Option<f64>
+	{
+		match *self.inner() {
+			Value::Double(v) => Some(v),
+			_ => None,
+		}
+	}
+
+	/// Returns the elements of an array
+	#[must_use]
+	pub fn as_array(&self) -> Option<&[Value]>
+	{
+		match self.inner() {
+			Value::Array(_, items) => Some(items.as_slice()),
+			_ => None,
+		}
+	}
+
+	/// Returns the fields of a struct
+	#[must_use]
+	pub fn as_struct(&self) -> Option<&[Value]>
+	{
+		match self.inner() {
+			Value::Struct(fields) => Some(fields.as_slice()),
+			_ => None,
+		}
+	}
+
+	/// Looks for `key` on a dictionary with string keys
+	#[must_use]
+	pub fn get(&self, key: &str) -> Option<&Value>
+	{
+		self.as_array()?.iter().find_map(|entry| match entry {
+			Value::DictEntry(k, v) if k.as_str() == Some(key) => Some(v.as_ref()),
+			_ => None,
+		})
+	}
+}
+
+impl From<&str> for Value {
+	fn from(value: &str) -> Self { Value::Str(value.to_string()) }
+}
+
+impl From<String> for Value {
+	fn from(value: String) -> Self { Value::Str(value) }
+}
+
+impl From<bool> for Value {
+	fn from(value: bool) -> Self { Value::Bool(value) }
+}
+
+impl From<u32> for Value {
+	fn from(value: u32) -> Self { Value::UInt32(value) }
+}
+
+impl From<i32> for Value {
+	fn from(value: i32) -> Self { Value::Int32(value) }
+}
+
+impl From<f64> for Value {
+	fn from(value: f64) -> Self { Value::Double(value) }
+}
+
+/// Type of the message, the discriminant is the wire value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+	/// Calls a method on a remote object
+	MethodCall	= 1,
+	/// Successful reply of a method call
+	MethodReturn	= 2,
+	/// Failed reply of a method call
+	Error		= 3,
+	/// Broadcasted event
+	Signal		= 4,
+}
+
+/// The caller doesn't want a reply for this method call
+pub const NO_REPLY_EXPECTED: u8 = 0x1;
+
+/// A D-Bus message, the header fields are flattened
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+	/// Type of the message
+	pub kind: MessageType,
+	/// Header flags, like `NO_REPLY_EXPECTED`
+	pub flags: u8,
+	/// Serial set by the sender, `0` until the message is sent
+	pub serial: u32,
+	/// Object the message is addressed to or emitted from
+	pub path: Option<String>,
+	/// Interface of the method or signal
+	pub interface: Option<String>,
+	/// Method or signal name
+	pub member: Option<String>,
+	/// Name of the error for `MessageType::Error`
+	pub error_name: Option<String>,
+	/// Serial of the message this one replies to
+	pub reply_serial: Option<u32>,
+	/// Bus name of the receiver
+	pub destination: Option<String>,
+	/// Unique bus name of the sender, filled by the daemon
+	pub sender: Option<String>,
+	/// Number of file descriptors sent with the message
+	pub unix_fds: Option<u32>,
+	/// The arguments
+	pub body: Vec<Value>,
+}
+
+impl Message {
+	/// Creates an empty message of the specified type
+	fn empty(kind: MessageType) -> Self
+	{
+		Self {
+			kind,
+			flags: 0,
+			serial: 0,
+			path: None,
+			interface: None,
+			member: None,
+			error_name: None,
+			reply_serial: None,
+			destination: None,
+			sender: None,
+			unix_fds: None,
+			body: Vec::new(),
+		}
+	}
+
+	/// Creates a new method call
+	#[must_use]
+	pub fn method_call(destination: &str, path: &str, interface: &str, member: &str) -> Self
+	{
+		let mut message = Self::empty(MessageType::MethodCall);
+		message.destination = Some(destination.to_string());
+		message.path = Some(path.to_string());
+		message.interface = Some(interface.to_string());
+		message.member = Some(member.to_string());
+		message
+	}
+
+	/// Creates a new signal
+	#[must_use]
+	pub fn signal(path: &str, interface: &str, member: &str) -> Self
+	{
+		let mut message = Self::empty(MessageType::Signal);
+		message.path = Some(path.to_string());
+		message.interface = Some(interface.to_string());
+		message.member = Some(member.to_string());
+		message
+	}
+
+	/// Creates the successful reply of `call`
+	#[must_use]
+	pub fn method_return(call: &Message) -> Self
+	{
+		let mut message = Self::empty(MessageType::MethodReturn);
+		message.reply_serial = Some(call.serial);
+		message.destination.clone_from(&call.sender);
+		message
+	}
+
+	/// Creates the error reply of `call`
+	#[must_use]
+	pub fn error(call: &Message, name: &str, text: &str) -> Self
+	{
+		let mut message = Self::empty(MessageType::Error);
+		message.reply_serial = Some(call.serial);
+		message.destination.clone_from(&call.sender);
+		message.error_name = Some(name.to_string());
+		message.body.push(Value::from(text));
+		message
+	}
+
+	/// Appends an argument to the body
+	#[must_use]
+	pub fn arg(mut self, value: impl Into<Value>) -> Self
+	{
+		self.body.push(value.into());
+		self
+	}
+
+	/// Signature of the body
+	#[must_use]
+	pub fn signature(&self) -> String
+		{ self.body.iter().map(Value::signature).collect() }
+
+	/// Returns true if `self` is the method call `interface.member`
+	#[must_use]
+	pub fn is_call(&self, interface: &str, member: &str) -> bool
+	{
+		self.kind == MessageType::MethodCall
+			&& self.interface.as_deref() == Some(interface)
+			&& self.member.as_deref() == Some(member)
+	}
+
+	/// Returns true if `self` is the signal `interface.member`
+	#[must_use]
+	pub fn is_signal(&self, interface: &str, member: &str) -> bool
+	{
+		self.kind == MessageType::Signal
+			&& self.interface.as_deref() == Some(interface)
+			&& self.member.as_deref() == Some(member)
+	}
+
+	/// Returns the argument at `index`
+	#[must_use]
+	pub fn get(&self, index: usize) -> Option<&Value>
+		{ self.body.get(index) }
+
+	/**
+	 * Serializes the message with the provided serial
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the message is bigger than `MAX_MESSAGE_SIZE`
+	 */
+	pub fn encode(&self, serial: u32) -> Result<Vec<u8>, Error>
+	{
+		let mut body = Writer::default();
+		for value in &self.body { body.value(value)? }
+
+		let mut fields: Vec<Value> = Vec::new();
+		let mut field = |code: u8, value: Value|
+			fields.push(Value::Struct(alloc::vec![Value::Byte(code), value.variant()]));
+
+		if let Some(ref v) = self.path { field(1, Value::ObjectPath(v.clone())) }
+		if let Some(ref v) = self.interface { field(2, Value::from(v.as_str())) }
+		if let Some(ref v) = self.member { field(3, Value::from(v.as_str())) }
+		if let Some(ref v) = self.error_name { field(4, Value::from(v.as_str())) }
+		if let Some(v) = self.reply_serial { field(5, Value::UInt32(v)) }
+		if let Some(ref v) = self.destination { field(6, Value::from(v.as_str())) }
+		if let Some(ref v) = self.sender { field(7, Value::from(v.as_str())) }
+		if !self.body.is_empty() { field(8, Value::Signature(self.signature())) }
+		if let Some(v) = self.unix_fds { field(9, Value::UInt32(v)) }
+
+		let body_len = u32::try_from(body.buf.len()).map_err(|_| WResponse::OutOfBounds)?;
+
+		let mut out = Writer::default();
+		out.buf.extend_from_slice(&[b'l', self.kind as u8, self.flags, 1]);
+		out.buf.extend_from_slice(&body_len.to_le_bytes());
+		out.buf.extend_from_slice(&serial.to_le_bytes());
+		out.value(&Value::Array("(yv)".to_string(), fields))?;
+		out.pad(8);
+		out.buf.extend_from_slice(&body.buf);
+
+		if out.buf.len() > MAX_MESSAGE_SIZE { return Err(WResponse::OutOfBounds.into()) }
+		Ok(out.buf)
+	}
+
+	/**
+	 * Returns the size of the message at the start of `buf`, or `None` if
+	 * not enough bytes are avaliable to know it yet
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the header is not valid
+	 */
+	pub fn size(buf: &[u8]) -> Result<Option<usize>, Error>
+	{
+		let Some(header) = buf.get(..FIXED_HEADER) else { return Ok(None) };
+		let mut reader = Reader::new(header)?;
+		reader.pos = 4;
+
+		let body = reader.u32()? as usize;
+		reader.pos = 12;
+		let fields = reader.u32()? as usize;
+
+		let size = align(FIXED_HEADER + fields, 8) + body;
+		if size > MAX_MESSAGE_SIZE { return Err(WResponse::OutOfBounds.into()) }
+		Ok(Some(size))
+	}
+
+	/**
+	 * Parses a complete message, check `Message::size`
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the message is malformed
+	 */
+	pub fn decode(buf: &[u8]) -> Result<Self, Error>
+	{
+		let mut reader = Reader::new(buf)?;
+		let kind = match buf.get(1) {
+			Some(1) => MessageType::MethodCall,
+			Some(2) => MessageType::MethodReturn,
+			Some(3) => MessageType::Error,
+			Some(4) => MessageType::Signal,
+			_ => return Err(WResponse::InvalidRequest.into()),
+		};
+
+		let mut message = Self::empty(kind);
+		message.flags = buf.get(2).copied().ok_or(WResponse::InvalidRequest)?;
+		reader.pos = 4;
+		let body_len = reader.u32()? as usize;
+		message.serial = reader.u32()?;
+
+		let mut signature = String::new();
+		let Value::Array(_, fields) = reader.value(b"a(yv)")? else {
+			return Err(WResponse::InvalidRequest.into())
+		};
+
+		for field in &fields {
+			let Some([code, value]) = field.as_struct() else { continue };
+			let string = value.as_str().map(ToString::to_string);
+			match code.as_u32() {
+				Some(1) => message.path = string,
+				Some(2) => message.interface = string,
+				Some(3) => message.member = string,
+				Some(4) => message.error_name = string,
+				Some(5) => message.reply_serial = value.as_u32(),
+				Some(6) => message.destination = string,
+				Some(7) => message.sender = string,
+				Some(8) => signature = string.unwrap_or_default(),
+				Some(9) => message.unix_fds = value.as_u32(),
+				_ => {},
+			}
+		}
+
+		reader.align(8)?;
+		let end = reader.pos + body_len;
+		if end > buf.len() { return Err(WResponse::InvalidRequest.into()) }
+
+		let mut sig = signature.as_bytes();
+		while !sig.is_empty() {
+			let (single, rest) = split_type(sig)?;
+			message.body.push(reader.value(single)?);
+			sig = rest;
+		}
+
+		if reader.pos != end { return Err(WResponse::InvalidRequest.into()) }
+		Ok(message)
+	}
+}
+
+/// Rounds `pos` up to the next multiple of `alignment`
+const fn align(pos: usize, alignment: usize) -> usize
+	{ pos.div_ceil(alignment) * alignment }
+
+/// Alignment of a type, by the first character of its signature
+fn alignment(code: u8) -> usize
+{
+	match code {
+		b'n' | b'q' => 2,
+		b'b' | b'i' | b'u' | b's' | b'o' | b'a' | b'h' => 4,
+		b'x' | b't' | b'd' | b'(' | b'{' => 8,
+		_ => 1,
+	}
+}
+
+/**
+ * Splits the first complete type of a signature from the rest
+ *
+ * # Errors
+ *
+ * `InvalidRequest` if the signature is not valid
+ */
+fn split_type(sig: &[u8]) -> Result<(&[u8], &[u8]), Error>
+{
+	let Some(&first) = sig.first() else { return Err(WResponse::InvalidRequest.into()) };
+
+	let len = match first {
+		b'a' => {
+			let rest = sig.get(1..).ok_or(WResponse::InvalidRequest)?;
+			1 + split_type(rest)?.0.len()
+		},
+		b'(' | b'{' => {
+			let close = if first == b'(' { b')' } else { b'}' };
+			let mut depth = 0usize;
+			let mut end = None;
+			for (i, &c) in sig.iter().enumerate() {
+				if c == first { depth += 1 }
+				if c == close { depth -= 1 }
+				if depth == 0 { end = Some(i + 1); break }
+			}
+			// the spec forbids empty structs and dict entries, `()` would take no bytes
+			match end {
+				Some(len) if len > 2 => len,
+				_ => return Err(WResponse::InvalidRequest.into()),
+			}
+		},
+		_ => 1,
+	};
+
+	if len > sig.len() { return Err(WResponse::InvalidRequest.into()) }
+	Ok(sig.split_at(len))
+}
+
+/// Message serializer
+#[derive(Debug, Default)]
+struct Writer {
+	/// The encoded data, alignment is relative to the start of it
+	buf: Vec<u8>,
+}
+
+impl Writer {
+	/// Pads the buffer with zeros until `alignment`
+	fn pad(&mut self, alignment: usize)
+		{ self.buf.resize(align(self.buf.len(), alignment), 0); }
+
+	/// Writes an aligned `u32`
+	fn u32(&mut self, value: u32)
+	{
+		self.pad(4);
+		self.buf.extend_from_slice(&value.to_le_bytes());
+	}
+
+	/// Writes a `s` or `o`
+	fn string(&mut self, value: &str) -> Result<(), Error>
+	{
+		self.u32(u32::try_from(value.len()).map_err(|_| WResponse::OutOfBounds)?);
+		self.buf.extend_from_slice(value.as_bytes());
+		self.buf.push(0);
+		Ok(())
+	}
+
+	/// Writes a `g`
+	fn signature(&mut self, value: &str) -> Result<(), Error>
+	{
+		self.buf.push(u8::try_from(value.len()).map_err(|_| WResponse::OutOfBounds)?);
+		self.buf.extend_from_slice(value.as_bytes());
+		self.buf.push(0);
+		Ok(())
+	}
+
+	/// Writes any value with its alignment
+	fn value(&mut self, value: &Value) -> Result<(), Error>
+	{
+		match value {
+			Value::Byte(v) => self.buf.push(*v),
+			Value::Bool(v) => self.u32(u32::from(*v)),
+			Value::Int16(v) => { self.pad(2); self.buf.extend_from_slice(&v.to_le_bytes()) },
+			Value::UInt16(v) => { self.pad(2); self.buf.extend_from_slice(&v.to_le_bytes()) },
+			Value::Int32(v) => { self.pad(4); self.buf.extend_from_slice(&v.to_le_bytes()) },
+			Value::UInt32(v) | Value::UnixFd(v) => self.u32(*v),
+			Value::Int64(v) => { self.pad(8); self.buf.extend_from_slice(&v.to_le_bytes()) },
+			Value::UInt64(v) => { self.pad(8); self.buf.extend_from_slice(&v.to_le_bytes()) },
+			Value::Double(v) => { self.pad(8); self.buf.extend_from_slice(&v.to_le_bytes()) },
+			Value::Str(v) | Value::ObjectPath(v) => self.string(v)?,
+			Value::Signature(v) => self.signature(v)?,
+			Value::Variant(v) => {
+				self.signature(&v.signature())?;
+				self.value(v)?;
+			},
+			Value::Struct(fields) => {
+				self.pad(8);
+				for field in fields { self.value(field)? }
+			},
+			Value::DictEntry(k, v) => {
+				self.pad(8);
+				self.value(k)?;
+				self.value(v)?;
+			},
+			Value::Array(element, items) => {
+				self.u32(0);
+				let len_pos = self.buf.len() - 4;
+				self.pad(alignment(element.bytes().next().unwrap_or(b'y')));
+
+				let start = self.buf.len();
+				for item in items { self.value(item)? }
+
+				let len = u32::try_from(self.buf.len() - start).map_err(|_| WResponse::OutOfBounds)?;
+				if let Some(slot) = self.buf.get_mut(len_pos..len_pos + 4) {
+					slot.copy_from_slice(&len.to_le_bytes());
+				}
+			},
+		}
+		Ok(())
+	}
+}
+
+/// Message parser
+#[derive(Debug)]
+struct Reader<'a> {
+	/// The whole message, alignment is relative to the start of it
+	buf: &'a [u8],
+	/// Current position
+	pos: usize,
+	/// Nesting level, the spec limits it to 64
+	depth: usize,
+}
+
+impl<'a> Reader<'a> {
+	/// Creates a reader for a little endian message
+	fn new(buf: &'a [u8]) -> Result<Self, Error>
+	{
+		// TODO: big endian messages, the daemon never sends them but peers could
+		if buf.first() != Some(&b'l') { return Err(WResponse::BinarySpecificLimitation.into()) }
+		Ok(Self { buf, pos: 0, depth: 0 })
+	}
+
+	/// Skips the padding until `alignment`
+	fn align(&mut self, alignment: usize) -> Result<(), Error>
+	{
+		self.pos = align(self.pos, alignment);
+		if self.pos > self.buf.len() { return Err(WResponse::InvalidRequest.into()) }
+		Ok(())
+	}
+
+	/// Reads `N` aligned bytes
+	fn bytes<const N: usize>(&mut self) -> Result<[u8; N], Error>
+	{
+		self.align(N)?;
+		let slice = self.buf.get(self.pos..self.pos + N).ok_or(WResponse::InvalidRequest)?;
+		self.pos += N;
+		let mut out = [0u8; N];
+		out.copy_from_slice(slice);
+		Ok(out)
+	}
+
+	/// Reads an aligned `u32`
+	fn u32(&mut self) -> Result<u32, Error>
+		{ Ok(u32::from_le_bytes(self.bytes()?)) }
+
+	/// Reads `len` bytes followed by a nul byte as a string, the text itself can't hold a nul
+	fn text(&mut self, len: usize) -> Result<String, Error>
+	{
+		let slice = self.buf.get(self.pos..=self.pos + len).ok_or(WResponse::InvalidRequest)?;
+		let (nul, bytes) = slice.split_last().ok_or(WResponse::InvalidRequest)?;
+		if *nul != 0 || bytes.contains(&0) { return Err(WResponse::InvalidRequest.into()) }
+
+		let text = core::str::from_utf8(bytes).map_err(|_| WResponse::InvalidRequest)?;
+		self.pos += len + 1;
+		Ok(text.to_string())
+	}
+
+	/// Reads a signature
+	fn signature(&mut self) -> Result<String, Error>
+	{
+		let [len] = self.bytes::<1>()?;
+		self.text(usize::from(len))
+	}
+
+	/// Reads a single complete type
+	fn value(&mut self, sig: &[u8]) -> Result<Value, Error>
+	{
+		let Some(&code) = sig.first() else { return Err(WResponse::InvalidRequest.into()) };
+
+		Ok(match code {
+			b'y' => Value::Byte(u8::from_le_bytes(self.bytes()?)),
+			b'b' => Value::Bool(self.u32()? != 0),
+			b'n' => Value::Int16(i16::from_le_bytes(self.bytes()?)),
+			b'q' => Value::UInt16(u16::from_le_bytes(self.bytes()?)),
+			b'i' => Value::Int32(i32::from_le_bytes(self.bytes()?)),
+			b'u' => Value::UInt32(self.u32()?),
+			b'h' => Value::UnixFd(self.u32()?),
+			b'x' => Value::Int64(i64::from_le_bytes(self.bytes()?)),
+			b't' => Value::UInt64(u64::from_le_bytes(self.bytes()?)),
+			b'd' => Value::Double(f64::from_le_bytes(self.bytes()?)),
+			b's' | b'o' => {
+				let len = self.u32()? as usize;
+				let text = self.text(len)?;
+				if code == b's' { Value::Str(text) } else { Value::ObjectPath(text) }
+			},
+			b'g' => Value::Signature(self.signature()?),
+			b'v' => {
+				let inner = self.signature()?;
+				let (single, rest) = split_type(inner.as_bytes())?;
+				if !rest.is_empty() { return Err(WResponse::InvalidRequest.into()) }
+				Value::Variant(Box::new(self.nested(single)?))
+			},
+			b'a' => {
+				let element = sig.get(1..).ok_or(WResponse::InvalidRequest)?;
+				let len = self.u32()? as usize;
+				if len > MAX_ARRAY_SIZE { return Err(WResponse::OutOfBounds.into()) }
+				self.align(alignment(element.first().copied().unwrap_or(b'y')))?;
+
+				let end = self.pos + len;
+				if end > self.buf.len() { return Err(WResponse::InvalidRequest.into()) }
+
+				let mut items = Vec::new();
+				while self.pos < end {
+					let start = self.pos;
+					items.push(self.nested(element)?);
+					// an element that takes no bytes would repeat forever
+					if self.pos == start { return Err(WResponse::InvalidRequest.into()) }
+				}
+				if self.pos != end { return Err(WResponse::InvalidRequest.into()) }
+
+				let element_sig = core::str::from_utf8(element).map_err(|_| WResponse::InvalidRequest)?;
+				Value::Array(element_sig.to_string(), items)
+			},
+			b'(' | b'{' => {
+				self.align(8)?;
+				let mut inner = sig.get(1..sig.len() - 1).ok_or(WResponse::InvalidRequest)?;
+				if inner.is_empty() { return Err(WResponse::InvalidRequest.into()) }
+				let mut fields = Vec::new();
+				while !inner.is_empty() {
+					let (single, rest) = split_type(inner)?;
+					fields.push(self.nested(single)?);
+					inner = rest;
+				}
+
+				if code == b'(' { Value::Struct(fields) } else {
+					let mut entry = fields.into_iter();
+					let (Some(k), Some(v), None) = (entry.next(), entry.next(), entry.next()) else {
+						return Err(WResponse::InvalidRequest.into())
+					};
+					Value::DictEntry(Box::new(k), Box::new(v))
+				}
+			},
+			_ => return Err(WResponse::InvalidRequest.into()),
+		})
+	}
+
+	/// Reads a value inside of a container
+	fn nested(&mut self, sig: &[u8]) -> Result<Value, Error>
+	{
+		if self.depth >= 64 { return Err(WResponse::OutOfBounds.into()) }
+		self.depth += 1;
+		let value = self.value(sig);
+		self.depth -= 1;
+		value
+	}
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	use alloc::boxed::Box;
+	use alloc::string::ToString;
+	use alloc::vec;
+	use alloc::vec::Vec;
+	use super::{Message, MessageType, Value, Writer, MAX_ARRAY_SIZE};
+
+	/// A signal with `signature` in its header and `body` as is, for inputs `encode` can't make
+	fn raw(signature: &str, body: &[u8]) -> Vec<u8>
+	{
+		let field = Value::Struct(vec![Value::Byte(8), Value::Signature(signature.to_string()).variant()]);
+
+		let mut out = Writer::default();
+		out.buf.extend_from_slice(&[b'l', MessageType::Signal as u8, 0, 1]);
+		out.buf.extend_from_slice(&u32::try_from(body.len()).unwrap().to_le_bytes());
+		out.buf.extend_from_slice(&1u32.to_le_bytes());
+		out.value(&Value::Array("(yv)".to_string(), vec![field])).unwrap();
+		out.pad(8);
+		out.buf.extend_from_slice(body);
+		out.buf
+	}
+
+	/// Encodes and decodes `message`, checking the size announced by the header
+	fn round_trip(message: &Message) -> Message
+	{
+		let data = message.encode(7).unwrap();
+		assert_eq!(Message::size(&data).unwrap(), Some(data.len()));
+		Message::decode(&data).unwrap()
+	}
+
+	#[test]
+	fn method_call_round_trip()
+	{
+		let mut call = Message::method_call("org.freedesktop.portal.Desktop", "/org/freedesktop/portal/desktop", "org.freedesktop.portal.Settings", "ReadAll")
+			.arg(Value::Array("s".to_string(), vec![Value::from("org.freedesktop.appearance")]));
+		call.flags = super::NO_REPLY_EXPECTED;
+
+		let decoded = round_trip(&call);
+		call.serial = 7;
+		assert_eq!(decoded, call);
+	}
+
+	#[test]
+	fn every_type_round_trips()
+	{
+		let body = vec![
+			Value::Byte(0xff),
+			Value::Int16(-2),
+			Value::Bool(true),
+			Value::Int64(i64::MIN),
+			Value::UInt16(3),
+			Value::Double(0.25),
+			Value::Int32(-4),
+			Value::UInt64(u64::MAX),
+			Value::UInt32(5),
+			Value::ObjectPath("/a/b".to_string()),
+			Value::Signature("a{sv}".to_string()),
+			Value::UnixFd(0),
+			Value::from(""),
+			Value::Struct(vec![Value::Byte(1), Value::Double(0.5), Value::from("x")]),
+			Value::properties(vec![("urgency", Value::Byte(2)), ("category", Value::from("im"))]),
+			Value::Array("ay".to_string(), vec![Value::Array("y".to_string(), vec![Value::Byte(1)]), Value::Array("y".to_string(), Vec::new())]),
+			Value::Array("t".to_string(), Vec::new()),
+			Value::UInt32(7).variant().variant(),
+		];
+
+		let mut signal = Message::signal("/", "org.example", "Changed");
+		signal.body = body;
+		signal.sender = Some(":1.42".to_string());
+
+		let decoded = round_trip(&signal);
+		signal.serial = 7;
+		assert_eq!(decoded, signal);
+		assert_eq!(decoded.signature(), "ynbxqdituoghs(yds)a{sv}aayatv");
+	}
+
+	#[test]
+	fn error_reply_round_trip()
+	{
+		let mut call = Message::method_call("a.b", "/", "a.b", "Fail");
+		call.serial = 3;
+		call.sender = Some(":1.1".to_string());
+
+		let decoded = round_trip(&Message::error(&call, "org.freedesktop.DBus.Error.Failed", "nope"));
+		assert_eq!(decoded.kind, MessageType::Error);
+		assert_eq!(decoded.reply_serial, Some(3));
+		assert_eq!(decoded.destination.as_deref(), Some(":1.1"));
+		assert_eq!(decoded.error_name.as_deref(), Some("org.freedesktop.DBus.Error.Failed"));
+		assert_eq!(decoded.get(0).and_then(Value::as_str), Some("nope"));
+	}
+
+	#[test]
+	fn size_needs_the_fixed_header()
+	{
+		let data = Message::signal("/", "a.b", "C").encode(1).unwrap();
+		assert_eq!(Message::size(&data[..15]).unwrap(), None);
+		assert_eq!(Message::size(&data[..16]).unwrap(), Some(data.len()));
+	}
+
+	#[test]
+	fn rejects_empty_structs()
+	{
+		// `a()` holding 8 bytes, each empty struct would take none of them
+		let mut body = Vec::from(8u32.to_le_bytes());
+		body.extend_from_slice(&[0; 12]);
+		assert!(Message::decode(&raw("a()", &body)).is_err());
+		assert!(Message::decode(&raw("()", &[])).is_err());
+		assert!(Message::decode(&raw("a{}", &[0; 8])).is_err());
+	}
+
+	#[test]
+	fn rejects_arrays_over_64_mib()
+	{
+		let len = u32::try_from(MAX_ARRAY_SIZE + 1).unwrap();
+		assert!(Message::decode(&raw("ay", &len.to_le_bytes())).is_err());
+	}
+
+	#[test]
+	fn rejects_elements_past_the_array()
+	{
+		// the array says 2 bytes but its `u32` takes 4
+		let mut body = Vec::from(2u32.to_le_bytes());
+		body.extend_from_slice(&[1, 0, 0, 0]);
+		assert!(Message::decode(&raw("au", &body)).is_err());
+	}
+
+	#[test]
+	fn rejects_strings_without_terminator()
+	{
+		let mut body = Vec::from(2u32.to_le_bytes());
+		body.extend_from_slice(b"hi!\0");
+		assert!(Message::decode(&raw("s", &body)).is_err());
+
+		let mut inner_nul = Vec::from(3u32.to_le_bytes());
+		inner_nul.extend_from_slice(b"h\0i\0");
+		assert!(Message::decode(&raw("s", &inner_nul)).is_err());
+	}
+
+	#[test]
+	fn rejects_truncated_messages()
+	{
+		let data = Message::signal("/", "a.b", "C").arg("hello").encode(1).unwrap();
+		for len in 0..data.len() {
+			assert!(Message::decode(&data[..len]).is_err(), "{len} bytes");
+		}
+	}
+
+	#[test]
+	fn rejects_bad_signatures()
+	{
+		assert!(Message::decode(&raw("(s", &[])).is_err());
+		assert!(Message::decode(&raw("a", &[0; 4])).is_err());
+		assert!(Message::decode(&raw("z", &[0; 4])).is_err());
+		// a variant holds exactly one type
+		assert!(Message::decode(&raw("v", &[2, b'u', b'u', 0, 0, 0, 0, 0, 0, 0, 0, 0])).is_err());
+	}
+
+	#[test]
+	fn rejects_deep_nesting()
+	{
+		let mut value = Value::Byte(1);
+		for _ in 0..70 { value = Value::Variant(Box::new(value)) }
+		let data = Message::signal("/", "a.b", "C").arg(value).encode(1).unwrap();
+		assert!(Message::decode(&data).is_err());
+	}
+
+	#[test]
+	fn rejects_big_endian()
+	{
+		let mut data = Message::signal("/", "a.b", "C").encode(1).unwrap();
+		data[0] = b'B';
+		assert!(Message::decode(&data).is_err());
+	}
+}
@@ -0,0 +1,220 @@
+/*!
+ * Arguments, environment and auxiliary vector of the process, without std
+ *
+ * ```rust
+ * let room = env::args().skip_while(|arg| arg != "--room").nth(1);
+ * let desktop = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+ * let page = env::auxv(env::AT_PAGESZ).unwrap_or(4096);
+ * ```
+ *
+ * They are captured before `main` from the initial stack: glibc passes `argc`, `argv` and `envp`
+ * to the functions of `.init_array`, musl doesn't, so `argv` is found by walking back from the
+ * `environ` set by `__libc_start_main`, when it still points into the initial stack.
+ * The auxiliary vector follows the nul that ends `envp`
+ *
+ * the environment is the one the process started with, later changes through libc aren't seen
+ */
+// `argc` and `argv` are the names everyone knows them by
+#![allow(clippy::similar_names)]
+use core::ffi::{c_char, CStr};
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use crate::{String, Vec};
+
+/// End of the auxiliary vector
+pub const AT_NULL: usize = 0;
+/// Size of a memory page
+pub const AT_PAGESZ: usize = 6;
+/// Real user id
+pub const AT_UID: usize = 11;
+/// Effective user id
+pub const AT_EUID: usize = 12;
+/// CPU features, the bits depend on the architecture
+pub const AT_HWCAP: usize = 16;
+/// Non zero if the program runs setuid or with capabilities, the environment shouldn't be trusted
+pub const AT_SECURE: usize = 23;
+/// Address of 16 random bytes given by the kernel
+pub const AT_RANDOM: usize = 25;
+/// More CPU features
+pub const AT_HWCAP2: usize = 26;
+/// Path used to run the program
+pub const AT_EXECFN: usize = 31;
+
+#[cfg(target_env = "musl")]
+unsafe extern "C" {
+	/// Set by `__libc_start_main` to the `envp` of the initial stack, `setenv` may move it to the heap
+	static environ: *const *const c_char;
+	/// Reads the auxiliary vector musl kept at startup, `unsigned long` is `usize` on our targets
+	fn getauxval(kind: usize) -> usize;
+}
+
+/// Most the `envp` array can be below the `AT_EXECFN` string, the arguments and environment
+/// strings in between are a quarter of the stack limit at most, and `mmap` keeps a bigger gap
+#[cfg(target_env = "musl")]
+const MAX_INITIAL_STACK: usize = 32 * 1024 * 1024;
+
+/// Number of arguments
+static ARGC: AtomicUsize = AtomicUsize::new(0);
+/// `argv`, null until captured
+static ARGV: AtomicPtr<*const c_char> = AtomicPtr::new(core::ptr::null_mut());
+/// `envp`, null until captured
+static ENVP: AtomicPtr<*const c_char> = AtomicPtr::new(core::ptr::null_mut());
+/// Pairs of type and value after `envp`, null until captured
+static AUXV: AtomicPtr<usize> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Runs before `main` with the other constructors
+#[used]
+#[unsafe(link_section = ".init_array")]
+static CAPTURE: extern "C" fn(i32, *const *const c_char, *const *const c_char) = capture;
+
+/// Number of pointers in a nul terminated array
+unsafe fn count(mut list: *const *const c_char) -> usize
+{
+	let mut len = 0usize;
+	while !unsafe { *list }.is_null() {
+		list = unsafe { list.add(1) };
+		len = len.saturating_add(1);
+	}
+	len
+}
+
+/// Stores what was found, `auxv` starts after the nul that ends `envp`
+unsafe fn store(argc: usize, argv: *const *const c_char, envp: *const *const c_char)
+{
+	if envp.is_null() { return }
+
+	let auxv = unsafe { envp.add(count(envp).saturating_add(1)) };
+	ARGC.store(argc, Ordering::Relaxed);
+	ARGV.store(argv.cast_mut(), Ordering::Relaxed);
+	AUXV.store(auxv.cast::<usize>().cast_mut(), Ordering::Relaxed);
+	ENVP.store(envp.cast_mut(), Ordering::Release);
+}
+
+/// glibc gives the initial stack to every constructor
+#[cfg(not(target_env = "musl"))]
+extern "C" fn capture(argc: i32, argv: *const *const c_char, envp: *const *const c_char)
+	{ unsafe { store(usize::try_from(argc).unwrap_or_default(), argv, envp) } }
+
+/**
+ * musl calls the constructors without arguments, the initial stack is
+ * `argc, argv[0..argc], null, envp[..], null, auxv`, so going back from `environ`
+ * the first slot holding its own distance is `argc`, argument pointers are never that small
+ *
+ * the kernel copies the `AT_EXECFN` string at the top of that stack, an `environ` that isn't
+ * shortly below it was replaced by an earlier constructor and nothing is captured
+ */
+#[cfg(target_env = "musl")]
+extern "C" fn capture(_: i32, _: *const *const c_char, _: *const *const c_char)
+{
+	let envp = unsafe { environ };
+	if envp.is_null() || !envp.is_aligned() { return }
+
+	let execfn = unsafe { getauxval(AT_EXECFN) };
+	let on_stack = execfn.checked_sub(envp.addr()).is_some_and(|distance| distance <= MAX_INITIAL_STACK);
+	if !on_stack { return }
+
+	let mut argc = 0usize;
+	// `envp[-1]` is the nul that ends `argv`
+	let mut slot = unsafe { envp.sub(2) };
+	while unsafe { *slot.cast::<usize>() } != argc {
+		slot = unsafe { slot.sub(1) };
+		argc = argc.saturating_add(1);
+	}
+	unsafe { store(argc, slot.add(1), envp) }
+}
+
+/// Copies a C string, invalid UTF-8 is replaced
+fn lossy(raw: *const c_char) -> String
+	{ String::from_utf8_lossy(unsafe { CStr::from_ptr(raw) }.to_bytes()).into_owned() }
+
+/// `envp` as `name=value` entries, empty if it wasn't captured
+fn environment() -> &'static [*const c_char]
+{
+	let envp = ENVP.load(Ordering::Acquire);
+	if envp.is_null() { return &[] }
+	// the initial stack lives as long as the process
+	unsafe { core::slice::from_raw_parts(envp, count(envp)) }
+}
+
+/// Iterator over the arguments, see `args`
+#[derive(Debug, Clone)]
+pub struct Args {
+	/// `argv`
+	argv: &'static [*const c_char],
+	/// Next argument
+	next: usize,
+}
+
+impl Iterator for Args {
+	type Item = String;
+
+	fn next(&mut self) -> Option<String>
+	{
+		let raw = *self.argv.get(self.next)?;
+		self.next = self.next.saturating_add(1);
+		Some(lossy(raw))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>)
+	{
+		let left = self.argv.len().saturating_sub(self.next);
+		(left, Some(left))
+	}
+}
+
+impl ExactSizeIterator for Args {}
+
+/// The arguments of the process, the first is usually the program, invalid UTF-8 is replaced
+#[must_use]
+pub fn args() -> Args
+{
+	let argv = ARGV.load(Ordering::Acquire);
+	let argc = ARGC.load(Ordering::Acquire);
+	if argv.is_null() { return Args { argv: &[], next: 0 } }
+	Args { argv: unsafe { core::slice::from_raw_parts(argv, argc) }, next: 0 }
+}
+
+/// Splits a `name=value` entry
+fn entry(raw: *const c_char) -> (&'static [u8], &'static [u8])
+{
+	let bytes = unsafe { CStr::from_ptr(raw) }.to_bytes();
+	match bytes.iter().position(|&byte| byte == b'=') {
+		Some(split) => (bytes.get(..split).unwrap_or_default(), bytes.get(split.saturating_add(1)..).unwrap_or_default()),
+		None => (bytes, &[]),
+	}
+}
+
+/// The value of the environment variable `name`, `None` if it isn't set or isn't UTF-8
+#[must_use]
+pub fn var(name: &str) -> Option<String>
+{
+	environment().iter()
+		.map(|&raw| entry(raw))
+		.find(|&(key, _)| key == name.as_bytes())
+		.and_then(|(_, value)| core::str::from_utf8(value).ok())
+		.map(String::from)
+}
+
+/// Every environment variable as `(name, value)`, invalid UTF-8 is replaced
+#[must_use]
+pub fn vars() -> Vec<(String, String)>
+{
+	environment().iter()
+		.map(|&raw| entry(raw))
+		.map(|(key, value)| (String::from_utf8_lossy(key).into_owned(), String::from_utf8_lossy(value).into_owned()))
+		.collect()
+}
+
+/// The value of the auxiliary vector entry `kind`, like `AT_PAGESZ`
+#[must_use]
+pub fn auxv(kind: usize) -> Option<usize>
+{
+	if ENVP.load(Ordering::Acquire).is_null() { return None }
+	let mut pair = AUXV.load(Ordering::Relaxed).cast_const();
+
+	loop {
+		let (key, value) = unsafe { (*pair, *pair.add(1)) };
+		if key == AT_NULL { return None }
+		if key == kind { return Some(value) }
+		pair = unsafe { pair.add(2) };
+	}
+}
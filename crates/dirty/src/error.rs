@@ -0,0 +1,254 @@
+/*!
+ * The error type of the workspace
+ *
+ * `WResponse` says what kind of failure it was, `Error` adds what went wrong in words,
+ * the detail given by the backend (errno, Wayland protocol error, `VkResult`, D-Bus error name)
+ * and the error that caused it
+ *
+ * ```rust
+ * let bus = dbus::session().context(WResponse::MissingDependencies, "the session bus is needed for the tray")?;
+ *
+ * if let Err(e) = window.create() {
+ * 	for cause in e.chain() { error!("{cause}") }
+ * }
+ * ```
+ */
+use alloc::borrow::Cow;
+use core::fmt;
+use crate::{Box, String};
+use crate::syscall::Errno;
+
+/** Possible responses
+ *
+ * 6## : Window Request Failed
+ *
+ * 4## : Rendererer Request Failed
+ *
+ * 5## : General Program limitation
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WResponse
+{
+	/// The binary does not support this function
+	BinarySpecificLimitation	= 500,
+	/// Tried to use a wayland protocol that wasn't implemented on the compositor
+	ProtocolNotSuported			= 501,
+	/// tried to access something and the request was denied by the OS
+	AccessDenied				= 502,
+	/// Recived a value that wasn't supposed to be empty or an error
+	UnexpectedError				= 503,
+	/// this will cause a buffer overflow
+	OutOfBounds					= 504,
+	/// user tried to do an impossible action
+	InvalidRequest				= 505,
+	/// Tried to do something with the window, but the compositor denied
+	ForbiddenByCompositor		= 601,
+	/// The connection with the compositor (or display server) was lost
+	ConnectionLost				= 602,
+	/// Something for macos
+	ChannelInUse				= 400,
+	/// A dynamic linked dependency was missing on execution
+	MissingDependencies			= 401,
+}
+
+impl WResponse {
+	/// The category code, like `501`
+	#[must_use]
+	pub fn code(self) -> u16 { self as u16 }
+
+	/// Short description of the category
+	#[must_use]
+	pub fn description(self) -> &'static str
+	{
+		match self {
+			Self::BinarySpecificLimitation => "not supported by this build",
+			Self::ProtocolNotSuported => "protocol not supported by the compositor",
+			Self::AccessDenied => "access denied",
+			Self::UnexpectedError => "unexpected error",
+			Self::OutOfBounds => "out of bounds",
+			Self::InvalidRequest => "invalid request",
+			Self::ForbiddenByCompositor => "forbidden by the compositor",
+			Self::ConnectionLost => "connection lost",
+			Self::ChannelInUse => "channel in use",
+			Self::MissingDependencies => "missing dependencies",
+		}
+	}
+}
+
+/// `invalid request (505)`
+impl fmt::Display for WResponse {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ write!(f, "{} ({})", self.description(), self.code()) }
+}
+
+impl core::error::Error for WResponse {}
+
+/// What the backend reported, kept as it came
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Detail {
+	/// Nothing more than the category
+	#[default]
+	None,
+	/// A failed syscall
+	Errno(Errno),
+	/// A protocol error sent by the compositor, the connection is gone after it
+	Wayland {
+		/// Error code, its meaning depends on `interface`
+		code: u32,
+		/// Object the error was raised on
+		object_id: u32,
+		/// Interface of the object, like `xdg_surface`
+		interface: String,
+	},
+	/// A `VkResult` below zero
+	Vulkan(i32),
+	/// Name of a D-Bus error reply, like `org.freedesktop.DBus.Error.ServiceUnknown`
+	DBus(String),
+}
+
+/// A `WResponse` with context, see the module docs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+	/// Category
+	kind: WResponse,
+	/// What went wrong, in words
+	message: Option<Cow<'static, str>>,
+	/// What the backend reported
+	detail: Detail,
+	/// The error that caused this one
+	source: Option<Box<Error>>,
+}
+
+impl Error {
+	/// An error of `kind` without context
+	#[must_use]
+	pub fn new(kind: WResponse) -> Self
+		{ Self { kind, message: None, detail: Detail::None, source: None } }
+
+	/// Describes what went wrong
+	#[must_use]
+	pub fn with_message(mut self, message: impl Into<Cow<'static, str>>) -> Self
+	{
+		self.message = Some(message.into());
+		self
+	}
+
+	/// Keeps what the backend reported
+	#[must_use]
+	pub fn with_detail(mut self, detail: Detail) -> Self
+	{
+		self.detail = detail;
+		self
+	}
+
+	/// Keeps the error that caused this one
+	#[must_use]
+	pub fn with_source(mut self, source: impl Into<Self>) -> Self
+	{
+		self.source = Some(Box::new(source.into()));
+		self
+	}
+
+	/// Wraps `self` into a new error of `kind`, for callers that know why it was being done
+	#[must_use]
+	pub fn context(self, kind: WResponse, message: impl Into<Cow<'static, str>>) -> Self
+		{ Self::new(kind).with_message(message).with_source(self) }
+
+	/// Category
+	#[must_use]
+	pub fn kind(&self) -> WResponse { self.kind }
+
+	/// Code of the category, like `501`
+	#[must_use]
+	pub fn code(&self) -> u16 { self.kind.code() }
+
+	/// What went wrong, if it was said
+	#[must_use]
+	pub fn message(&self) -> Option<&str> { self.message.as_deref() }
+
+	/// What the backend reported
+	#[must_use]
+	pub fn detail(&self) -> &Detail { &self.detail }
+
+	/// The errno of the failed syscall, searching the whole chain
+	#[must_use]
+	pub fn errno(&self) -> Option<Errno>
+	{
+		self.chain().find_map(|error| match error.detail {
+			Detail::Errno(errno) => Some(errno),
+			_ => None,
+		})
+	}
+
+	/// The error that caused this one
+	#[must_use]
+	pub fn cause(&self) -> Option<&Self> { self.source.as_deref() }
+
+	/// This error followed by its causes, the root cause is the last
+	pub fn chain(&self) -> impl Iterator<Item = &Self>
+		{ core::iter::successors(Some(self), |error| error.cause()) }
+}
+
+/// The message or the category, then the detail: `couldn't bind the socket (EADDRINUSE)`
+/// causes are not included, use `chain` to print them
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self.message {
+			Some(ref message) => f.write_str(message)?,
+			None => write!(f, "{}", self.kind)?,
+		}
+
+		match self.detail {
+			Detail::None => Ok(()),
+			Detail::Errno(errno) => match errno.name() {
+				Some(name) => write!(f, " ({name})"),
+				None => write!(f, " (errno {})", errno.0),
+			},
+			Detail::Wayland { code, object_id, ref interface } =>
+				write!(f, " (protocol error {code} on {interface}@{object_id})"),
+			Detail::Vulkan(result) => write!(f, " (VkResult {result})"),
+			Detail::DBus(ref name) => write!(f, " ({name})"),
+		}
+	}
+}
+
+impl core::error::Error for Error {
+	fn source(&self) -> Option<&(dyn core::error::Error + 'static)>
+	{
+		let source: &(dyn core::error::Error + 'static) = self.source.as_deref()?;
+		Some(source)
+	}
+}
+
+impl From<WResponse> for Error {
+	fn from(kind: WResponse) -> Self { Self::new(kind) }
+}
+
+/// The category is picked by `From<Errno> for WResponse`
+impl From<Errno> for Error {
+	fn from(errno: Errno) -> Self
+		{ Self::new(WResponse::from(errno)).with_detail(Detail::Errno(errno)) }
+}
+
+/// So errors can still be compared with a category, `error == WResponse::ConnectionLost`
+impl PartialEq<WResponse> for Error {
+	fn eq(&self, kind: &WResponse) -> bool { self.kind == *kind }
+}
+
+/// Adds context to the error of a `Result`
+pub trait Context<T> {
+	/**
+	 * Wraps the error into a new one of `kind`, see `Error::context`
+	 *
+	 * # Errors
+	 *
+	 * The wrapped error, if there was one
+	 */
+	fn context(self, kind: WResponse, message: impl Into<Cow<'static, str>>) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+	fn context(self, kind: WResponse, message: impl Into<Cow<'static, str>>) -> Result<T, Error>
+		{ self.map_err(|error| error.into().context(kind, message)) }
+}
@@ -0,0 +1,495 @@
+/*!
+ * Files and directories on top of the raw syscalls, paths are UTF-8
+ *
+ * ```rust
+ * let dir = BaseDir::State.app_dir("walkie-talkie")?;
+ * fs::write_atomic(&format!("{dir}/session.json"), session.as_bytes())?;
+ *
+ * for entry in fs::read_dir(&dir)? {
+ * 	let entry = entry?;
+ * 	if entry.kind() == FileType::File { debug!("{}", entry.name()) }
+ * }
+ * ```
+ *
+ * files are created readable by the user only, they hold sessions and keys
+ */
+use alloc::ffi::CString;
+use core::ffi::CStr;
+use core::sync::atomic::{AtomicU32, Ordering};
+use crate::socket::OwnedFd;
+use crate::syscall::{
+	self,
+	Errno,
+	AT_FDCWD,
+	AT_REMOVEDIR,
+	DT_DIR,
+	DT_LNK,
+	DT_REG,
+	O_APPEND,
+	O_CLOEXEC,
+	O_CREAT,
+	O_DIRECTORY,
+	O_EXCL,
+	GRND_NONBLOCK,
+	LOCK_EX,
+	LOCK_NB,
+	O_RDONLY,
+	O_TRUNC,
+	O_WRONLY,
+	SEEK_CUR,
+	SEEK_END,
+	SEEK_SET,
+};
+use crate::{format, Error, String, Vec, WResponse};
+
+/// Permissions of the files created here
+const FILE_MODE: u32 = 0o600;
+/// Bytes read from a directory at once
+const DIR_BUFFER: usize = 4096;
+/// Offset of `d_reclen` in `struct linux_dirent64`
+const RECLEN_OFFSET: usize = 16;
+/// Offset of `d_type` in `struct linux_dirent64`
+const TYPE_OFFSET: usize = 18;
+/// Offset of `d_name` in `struct linux_dirent64`
+const NAME_OFFSET: usize = 19;
+/// Names tried for the temporary file of `write_atomic`, another one is only needed when a file is left behind
+const TEMPORARY_TRIES: u32 = 16;
+
+/// Tells the temporary files of this process apart when the kernel has no random bytes
+static TEMPORARY_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// The errno with what was being done to which path
+fn failed(errno: Errno, action: &str, path: &str) -> Error
+	{ Error::from(errno).with_message(format!("couldn't {action} {path}")) }
+
+/// `path` with a nul terminator, for the syscalls
+fn c_path(path: &str) -> Result<CString, Error>
+{
+	CString::new(path).map_err(|_| {
+		Error::new(WResponse::InvalidRequest).with_message(format!("{path:?} has a nul byte"))
+	})
+}
+
+/// Repeats `call` while it is interrupted by a signal
+fn retry<T>(mut call: impl FnMut() -> Result<T, Errno>) -> Result<T, Errno>
+{
+	loop {
+		match call() {
+			Err(Errno::EINTR) => {},
+			done => return done,
+		}
+	}
+}
+
+/// Opens `path` with `flags`, new files get `FILE_MODE`
+fn open_path(path: &str, flags: i32, action: &str) -> Result<OwnedFd, Error>
+{
+	let native = c_path(path)?;
+	let fd = retry(|| syscall::openat(AT_FDCWD, &native, flags | O_CLOEXEC, FILE_MODE))
+		.map_err(|errno| failed(errno, action, path))?;
+	Ok(unsafe { OwnedFd::from_raw(fd) })
+}
+
+/// An open file, closed on drop
+#[derive(Debug)]
+pub struct File {
+	/// The descriptor
+	fd: OwnedFd,
+	/// Path given on open, for the error messages
+	path: String,
+}
+
+impl File {
+	/**
+	 * Opens `path` for reading
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if it doesn't exist, `AccessDenied` without permission
+	 */
+	pub fn open(path: &str) -> Result<Self, Error>
+		{ Ok(Self { fd: open_path(path, O_RDONLY, "open")?, path: String::from(path) }) }
+
+	/**
+	 * Opens `path` for writing, it is created or emptied
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the directory doesn't exist, `AccessDenied` without permission
+	 */
+	pub fn create(path: &str) -> Result<Self, Error>
+		{ Ok(Self { fd: open_path(path, O_WRONLY | O_CREAT | O_TRUNC, "create")?, path: String::from(path) }) }
+
+	/**
+	 * Opens `path` to write at its end, it is created if missing
+	 *
+	 * # Errors
+	 *
+	 * Same as `File::create`
+	 */
+	pub fn append(path: &str) -> Result<Self, Error>
+		{ Ok(Self { fd: open_path(path, O_WRONLY | O_CREAT | O_APPEND, "open")?, path: String::from(path) }) }
+
+	/// The descriptor, still owned by the file
+	#[must_use]
+	pub fn fd(&self) -> i32 { self.fd.raw() }
+
+	/**
+	 * Reads up to `buf.len()` bytes, `0` at the end of the file
+	 *
+	 * # Errors
+	 *
+	 * The errno of `read`
+	 */
+	pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>
+		{ retry(|| syscall::read(self.fd.raw(), buf)).map_err(|errno| failed(errno, "read", &self.path)) }
+
+	/**
+	 * Reads until the end of the file, returns how many bytes were added to `out`
+	 *
+	 * # Errors
+	 *
+	 * Same as `File::read`
+	 */
+	pub fn read_to_end(&mut self, out: &mut Vec<u8>) -> Result<usize, Error>
+	{
+		let start = out.len();
+		let mut chunk = [0u8; 4096];
+		loop {
+			let read = self.read(&mut chunk)?;
+			if read == 0 { return Ok(out.len().saturating_sub(start)) }
+			out.extend_from_slice(chunk.get(..read).unwrap_or_default());
+		}
+	}
+
+	/**
+	 * Writes all of `data`
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the disk is full, the errno of `write` otherwise
+	 */
+	pub fn write_all(&mut self, data: &[u8]) -> Result<(), Error>
+		{ syscall::write_all(self.fd.raw(), data).map_err(|errno| failed(errno, "write", &self.path)) }
+
+	/**
+	 * Waits until what was written reached the disk
+	 *
+	 * # Errors
+	 *
+	 * The errno of `fsync`, `EIO` means the data may be lost
+	 */
+	pub fn sync(&self) -> Result<(), Error>
+		{ retry(|| syscall::fsync(self.fd.raw())).map_err(|errno| failed(errno, "sync", &self.path)) }
+
+	/**
+	 * Takes an exclusive lock on the file without waiting, `false` if another open file holds it
+	 * the lock is released when the file is closed, even if the process crashes
+	 *
+	 * # Errors
+	 *
+	 * The errno of `flock`
+	 */
+	pub fn try_lock(&self) -> Result<bool, Error>
+	{
+		match retry(|| syscall::flock(self.fd.raw(), LOCK_EX | LOCK_NB)) {
+			Ok(()) => Ok(true),
+			Err(Errno::EAGAIN) => Ok(false),
+			Err(errno) => Err(failed(errno, "lock", &self.path)),
+		}
+	}
+
+	/**
+	 * Size of the file in bytes, the position doesn't move
+	 *
+	 * # Errors
+	 *
+	 * The errno of `lseek`
+	 */
+	pub fn size(&self) -> Result<u64, Error>
+	{
+		let fd = self.fd.raw();
+		let seek = |offset, whence| syscall::lseek(fd, offset, whence).map_err(|errno| failed(errno, "seek", &self.path));
+		let position = seek(0, SEEK_CUR)?;
+		let size = seek(0, SEEK_END)?;
+		let _ = seek(i64::try_from(position).unwrap_or(i64::MAX), SEEK_SET)?;
+		Ok(size)
+	}
+}
+
+/**
+ * Reads the whole file
+ *
+ * # Errors
+ *
+ * Same as `File::open` and `File::read`
+ */
+pub fn read(path: &str) -> Result<Vec<u8>, Error>
+{
+	let mut data = Vec::new();
+	let _ = File::open(path)?.read_to_end(&mut data)?;
+	Ok(data)
+}
+
+/**
+ * Reads the whole file as text
+ *
+ * # Errors
+ *
+ * Same as `read`, `InvalidRequest` if it isn't UTF-8
+ */
+pub fn read_to_string(path: &str) -> Result<String, Error>
+{
+	String::from_utf8(read(path)?).map_err(|_| {
+		Error::new(WResponse::InvalidRequest).with_message(format!("{path} isn't UTF-8"))
+	})
+}
+
+/**
+ * Replaces the content of `path` with `data`, a crash can leave it half written, see `write_atomic`
+ *
+ * # Errors
+ *
+ * Same as `File::create` and `File::write_all`
+ */
+pub fn write(path: &str, data: &[u8]) -> Result<(), Error>
+	{ File::create(path)?.write_all(data) }
+
+/**
+ * Replaces the content of `path` with `data`, readers see the old or the new content, never a part
+ * the data goes to a temporary file next to `path` that is synced and renamed over it
+ *
+ * # Errors
+ *
+ * Same as `write`, the temporary file is removed on failure
+ */
+pub fn write_atomic(path: &str, data: &[u8]) -> Result<(), Error>
+{
+	let (temporary, fd) = create_temporary(path)?;
+
+	let mut file = File { fd, path: temporary.clone() };
+	let written = file.write_all(data)
+		.and_then(|()| file.sync())
+		.and_then(|()| rename(&temporary, path));
+
+	if let Err(e) = written {
+		let _ = remove_file(&temporary);
+		return Err(e);
+	}
+
+	// the rename itself is only durable once the directory is synced
+	let parent = match path.rsplit_once('/') {
+		Some(("", _)) => "/",
+		Some((parent, _)) => parent,
+		None => ".",
+	};
+	let directory = open_path(parent, O_RDONLY | O_DIRECTORY, "open")?;
+	retry(|| syscall::fsync(directory.raw())).map_err(|errno| failed(errno, "sync", parent))
+}
+
+/**
+ * Creates a new file next to `path` for `write_atomic`, a name already taken, say by a crash, is skipped
+ *
+ * # Errors
+ *
+ * `EEXIST` if every name tried is taken, the errno of `openat` otherwise
+ */
+fn create_temporary(path: &str) -> Result<(String, OwnedFd), Error>
+{
+	let mut tries = 0..TEMPORARY_TRIES;
+
+	loop {
+		// without random bytes the pid and a counter still differ from what other processes use
+		let mut random = [0u8; 8];
+		let suffix = match syscall::getrandom(&mut random, GRND_NONBLOCK) {
+			Ok(8) => u64::from_ne_bytes(random),
+			_ => u64::from(syscall::getpid()) << 32 | u64::from(TEMPORARY_COUNT.fetch_add(1, Ordering::Relaxed)),
+		};
+		let temporary = format!("{path}.{suffix:016x}.tmp");
+
+		let native = c_path(&temporary)?;
+		match retry(|| syscall::openat(AT_FDCWD, &native, O_WRONLY | O_CREAT | O_EXCL | O_CLOEXEC, FILE_MODE)) {
+			Ok(fd) => return Ok((temporary, unsafe { OwnedFd::from_raw(fd) })),
+			Err(Errno::EEXIST) if tries.next().is_some() => {},
+			Err(errno) => return Err(failed(errno, "create", &temporary)),
+		}
+	}
+}
+
+/**
+ * Renames `from` to `to`, replacing `to` if it exists
+ *
+ * # Errors
+ *
+ * `InvalidRequest` if `from` doesn't exist or the paths are on different filesystems
+ */
+pub fn rename(from: &str, to: &str) -> Result<(), Error>
+{
+	let (native_from, native_to) = (c_path(from)?, c_path(to)?);
+	syscall::renameat2(AT_FDCWD, &native_from, AT_FDCWD, &native_to, 0)
+		.map_err(|errno| failed(errno, "rename", from))
+}
+
+/**
+ * Removes the file `path`
+ *
+ * # Errors
+ *
+ * `InvalidRequest` if it doesn't exist or is a directory
+ */
+pub fn remove_file(path: &str) -> Result<(), Error>
+	{ syscall::unlinkat(AT_FDCWD, &c_path(path)?, 0).map_err(|errno| failed(errno, "remove", path)) }
+
+/**
+ * Removes the empty directory `path`
+ *
+ * # Errors
+ *
+ * `InvalidRequest` if it doesn't exist or isn't empty
+ */
+pub fn remove_dir(path: &str) -> Result<(), Error>
+	{ syscall::unlinkat(AT_FDCWD, &c_path(path)?, AT_REMOVEDIR).map_err(|errno| failed(errno, "remove", path)) }
+
+/// Creates one directory, `true` if it was already there
+fn make_dir(path: &CStr, mode: u32) -> Result<bool, Errno>
+{
+	match syscall::mkdirat(AT_FDCWD, path, mode) {
+		Ok(()) => Ok(false),
+		Err(Errno::EEXIST) => Ok(true),
+		Err(errno) => Err(errno),
+	}
+}
+
+/**
+ * Creates `path` and its missing parents with the permissions `mode`, like `mkdir -p`
+ *
+ * # Errors
+ *
+ * `InvalidRequest` if a part of the path is a file, `AccessDenied` without permission
+ */
+pub fn create_dir_all(path: &str, mode: u32) -> Result<(), Error>
+{
+	let ends = path.match_indices('/').map(|(end, _)| end).chain(core::iter::once(path.len()));
+	let mut existed = false;
+	for end in ends {
+		let Some(prefix) = path.get(..end).filter(|prefix| !prefix.is_empty()) else { continue };
+		existed = make_dir(&c_path(prefix)?, mode).map_err(|errno| failed(errno, "create", prefix))?;
+	}
+
+	// something was already there, make sure it is a directory
+	if existed { drop(open_path(path, O_RDONLY | O_DIRECTORY, "open")?) }
+	Ok(())
+}
+
+/// What a directory entry is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+	/// A regular file
+	File,
+	/// A directory
+	Dir,
+	/// A symbolic link, not followed
+	Symlink,
+	/// A socket, a device, a pipe, or a filesystem that doesn't tell
+	Other,
+}
+
+/// An entry of `read_dir`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+	/// File name, without the directory
+	name: String,
+	/// What it is
+	kind: FileType,
+}
+
+impl DirEntry {
+	/// File name, without the directory
+	#[must_use]
+	pub fn name(&self) -> &str { &self.name }
+
+	/// What it is
+	#[must_use]
+	pub fn kind(&self) -> FileType { self.kind }
+}
+
+/// Iterator over a directory, `.` and `..` are skipped
+#[derive(Debug)]
+pub struct ReadDir {
+	/// The directory
+	fd: OwnedFd,
+	/// Path given to `read_dir`, for the error messages
+	path: String,
+	/// Entries returned by the last `getdents64`
+	buf: Vec<u8>,
+	/// Next entry in `buf`
+	pos: usize,
+	/// Bytes filled in `buf`
+	len: usize,
+	/// Set after the end or an error
+	done: bool,
+}
+
+/**
+ * Lists the entries of the directory `path`, in no particular order
+ *
+ * # Errors
+ *
+ * `InvalidRequest` if it doesn't exist or isn't a directory
+ */
+pub fn read_dir(path: &str) -> Result<ReadDir, Error>
+{
+	Ok(ReadDir {
+		fd: open_path(path, O_RDONLY | O_DIRECTORY, "open")?,
+		path: String::from(path),
+		buf: alloc::vec![0; DIR_BUFFER],
+		pos: 0,
+		len: 0,
+		done: false,
+	})
+}
+
+impl ReadDir {
+	/// Parses the entry at `pos`, `None` once `buf` is consumed
+	fn parse(&mut self) -> Option<DirEntry>
+	{
+		let record = self.buf.get(self.pos..self.len)?;
+		let reclen = usize::from(u16::from_ne_bytes([*record.get(RECLEN_OFFSET)?, *record.get(RECLEN_OFFSET + 1)?]));
+		let kind = match *record.get(TYPE_OFFSET)? {
+			DT_REG => FileType::File,
+			DT_DIR => FileType::Dir,
+			DT_LNK => FileType::Symlink,
+			_ => FileType::Other,
+		};
+		let name = record.get(NAME_OFFSET..reclen)?;
+		let end = name.iter().position(|&byte| byte == 0).unwrap_or(name.len());
+		let entry = DirEntry { name: String::from_utf8_lossy(name.get(..end)?).into_owned(), kind };
+
+		self.pos = self.pos.saturating_add(reclen.max(1));
+		Some(entry)
+	}
+}
+
+impl Iterator for ReadDir {
+	type Item = Result<DirEntry, Error>;
+
+	fn next(&mut self) -> Option<Self::Item>
+	{
+		while !self.done {
+			if let Some(entry) = self.parse() {
+				if entry.name == "." || entry.name == ".." { continue }
+				return Some(Ok(entry));
+			}
+
+			match retry(|| syscall::getdents64(self.fd.raw(), &mut self.buf)) {
+				Ok(0) => self.done = true,
+				Ok(filled) => (self.pos, self.len) = (0, filled),
+				Err(errno) => {
+					self.done = true;
+					return Some(Err(failed(errno, "list", &self.path)));
+				},
+			}
+		}
+		None
+	}
+}
@@ -0,0 +1,180 @@
+/*!
+ * Native handles shared between the backends, the windows and the renderers
+ *
+ * A `Handle` is reference counted, every clone points to the same value and the value is
+ * dropped with the last clone. `Handle<dyn Any>` hides the type from crates that don't know it,
+ * `downcast` gives it back
+ *
+ * ```rust
+ * // the renderer keeps its own handle of the window, the window closes with the last handle
+ * let window = app.new_window("walkie talkie", size)?;
+ * let renderer = Renderer::new(window.get_backend(), size)?;
+ * drop(window);
+ * drop(renderer);
+ * ```
+ */
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::any::Any;
+use core::fmt;
+use core::ops::Deref;
+use crate::void;
+
+/// A reference counted value, clones share it
+pub struct Handle<T: ?Sized>(Arc<T>);
+
+impl<T> Handle<T> {
+	/// Moves `value` into a new handle
+	#[must_use]
+	pub fn new(value: T) -> Self { Self(Arc::new(value)) }
+
+	/**
+	 * Gives the reference of `self` to C, the value stays alive until `from_raw` takes it back
+	 *
+	 * ```rust
+	 * let data = handle.clone().into_raw();
+	 * unsafe { register_listener(object, callback, data) };
+	 * ```
+	 */
+	#[must_use]
+	pub fn into_raw(self) -> *const void { Arc::into_raw(self.0).cast::<void>() }
+
+	/**
+	 * Takes back a reference given by `into_raw`
+	 *
+	 * # Safety
+	 *
+	 * `raw` must come from `Handle::<T>::into_raw` and be taken back only once
+	 */
+	#[must_use]
+	pub unsafe fn from_raw(raw: *const void) -> Self
+		{ Self(unsafe { Arc::from_raw(raw.cast::<T>()) }) }
+
+	/**
+	 * Reads the value behind a pointer given by `into_raw` without taking the reference back
+	 *
+	 * # Safety
+	 *
+	 * `raw` must come from `Handle::<T>::into_raw` and not be taken back while the borrow lives
+	 */
+	#[must_use]
+	pub unsafe fn borrow_raw<'a>(raw: *const void) -> &'a T
+		{ unsafe { &*raw.cast::<T>() } }
+}
+
+impl<T: Any> Handle<T> {
+	/// Hides the type, for crates that only pass the handle along
+	#[must_use]
+	pub fn erase(self) -> Handle<dyn Any> { Handle(self.0) }
+}
+
+impl<T: ?Sized> Handle<T> {
+	/// `true` if both handles point to the same value
+	#[must_use]
+	pub fn ptr_eq(&self, other: &Self) -> bool { Arc::ptr_eq(&self.0, &other.0) }
+
+	/// Number of handles sharing the value
+	#[must_use]
+	pub fn count(&self) -> usize { Arc::strong_count(&self.0) }
+}
+
+impl Handle<dyn Any> {
+	/**
+	 * Gives the type back
+	 *
+	 * # Errors
+	 *
+	 * The handle itself if the value isn't a `T`
+	 */
+	pub fn downcast<T: Any>(self) -> Result<Handle<T>, Self>
+	{
+		if !self.0.is::<T>() { return Err(self) }
+		Ok(Handle(unsafe { Arc::from_raw(Arc::into_raw(self.0).cast::<T>()) }))
+	}
+
+	/// The value, if it is a `T`
+	#[must_use]
+	pub fn downcast_ref<T: Any>(&self) -> Option<&T> { self.0.downcast_ref() }
+}
+
+/// Shares an unsized value, like a `Box<dyn Fn()>`
+impl<T: ?Sized> From<Box<T>> for Handle<T> {
+	fn from(value: Box<T>) -> Self { Self(Arc::from(value)) }
+}
+
+impl<T: ?Sized> Clone for Handle<T> {
+	fn clone(&self) -> Self { Self(Arc::clone(&self.0)) }
+}
+
+impl<T: ?Sized> Deref for Handle<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T { &self.0 }
+}
+
+/// Handles are equal when they share the value, like two pointers to the same object
+impl<T: ?Sized> PartialEq for Handle<T> {
+	fn eq(&self, other: &Self) -> bool { self.ptr_eq(other) }
+}
+
+impl<T: ?Sized> Eq for Handle<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for Handle<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ f.debug_tuple("Handle").field(&*self.0).finish() }
+}
+
+impl fmt::Debug for Handle<dyn Any> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ f.debug_tuple("Handle").field(&Arc::as_ptr(&self.0)).finish() }
+}
+
+/// Native objects of a window, what a renderer needs to create its surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RawWindow {
+	/// A wayland toplevel
+	Wayland {
+		/// `wl_display` of the connection
+		display: *mut void,
+		/// `wl_surface` of the window
+		surface: *mut void,
+	},
+	/// An `AppKit` window
+	AppKit {
+		/// `NSView` holding the content of the window
+		ns_view: *mut void,
+	},
+}
+
+/// Owns the native objects of a window, the window is closed when this is dropped
+/// shared as `Handle<NativeWindow>`, so it stays open while a renderer keeps a handle of it
+pub struct NativeWindow {
+	/// The objects, valid as long as `self` lives
+	raw: RawWindow,
+	/// Closes the window, taken by `drop`
+	close: Option<Box<dyn FnOnce()>>,
+}
+
+impl NativeWindow {
+	/// Takes ownership of the objects in `raw`, `close` is called once to close them on drop
+	#[must_use]
+	pub fn new(raw: RawWindow, close: impl FnOnce() + 'static) -> Self
+		{ Self { raw, close: Some(Box::new(close)) } }
+
+	/// The native objects, don't keep them past the handle they came from
+	#[must_use]
+	pub fn raw(&self) -> RawWindow { self.raw }
+}
+
+impl Drop for NativeWindow {
+	fn drop(&mut self)
+	{
+		if let Some(close) = self.close.take() { close() }
+	}
+}
+
+impl fmt::Debug for NativeWindow {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ f.debug_tuple("NativeWindow").field(&self.raw).finish() }
+}
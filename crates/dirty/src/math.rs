@@ -0,0 +1,277 @@
+/*!
+ * The float functions of `std` that `core` doesn't have, precise enough for colors
+ * every function expects finite inputs
+ */
+// the casts only see values already reduced to a small range
+#![allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_sign_loss)]
+use core::f64::consts::{FRAC_PI_2, FRAC_PI_6, LN_2, PI, SQRT_2};
+
+/// `1 / sqrt(3)`, the tangent of 30°
+const TAN_PI_6: f64 = 0.577_350_269_189_625_8;
+/// `tan(15°)`, below it the `atan` series converges quickly
+const TAN_PI_12: f64 = 0.267_949_192_431_122_7;
+
+/// Largest integer below or equal to `x`
+pub(crate) fn floor(x: f64) -> f64
+{
+	// past 2^52 every float is an integer
+	if x.abs() >= 4_503_599_627_370_496.0 { return x }
+	let truncated = x as i64 as f64;
+	if truncated > x { truncated - 1.0 } else { truncated }
+}
+
+/// Nearest integer, halfway cases away from zero
+pub(crate) fn round(x: f64) -> f64
+{
+	// `x + 0.5` would round up the float just below one half, the fraction is exact
+	let magnitude = x.abs();
+	let floored = floor(magnitude);
+	let rounded = if magnitude - floored >= 0.5 { floored + 1.0 } else { floored };
+	rounded.copysign(x)
+}
+
+/// Remainder of `x / modulo` in `0.0..modulo`, `modulo` must be positive
+pub(crate) fn rem_euclid(x: f64, modulo: f64) -> f64
+{
+	let remainder = x - modulo * floor(x / modulo);
+	if remainder >= modulo { remainder - modulo } else { remainder.max(0.0) }
+}
+
+/// `2^exponent` for exponents in `-1022..=1023`
+fn pow2(exponent: i64) -> f64
+	{ f64::from_bits(((exponent + 1023) as u64) << 52) }
+
+/// Natural logarithm, `x` must be positive
+fn ln(x: f64) -> f64
+{
+	let (mut value, mut exponent) = (x, 0i64);
+	// subnormals have no implicit bit, scale them into the normal range first
+	if value < f64::MIN_POSITIVE {
+		value *= pow2(54);
+		exponent -= 54;
+	}
+
+	let bits = value.to_bits();
+	exponent += ((bits >> 52) & 0x7ff).cast_signed() - 1023;
+	let mut mantissa = f64::from_bits((bits & 0x000f_ffff_ffff_ffff) | (1023 << 52));
+	if mantissa > SQRT_2 {
+		mantissa /= 2.0;
+		exponent += 1;
+	}
+
+	// ln(m) = 2 atanh(s), |s| < 0.18 here
+	let s = (mantissa - 1.0) / (mantissa + 1.0);
+	let (square, mut power, mut sum) = (s * s, s, 0.0);
+	for n in 0..13 {
+		sum += power / f64::from(2 * n + 1);
+		power *= square;
+	}
+	2.0 * sum + exponent as f64 * LN_2
+}
+
+/// `e^x`
+fn exp(x: f64) -> f64
+{
+	if x > 709.78 { return f64::INFINITY }
+	if x < -745.2 { return 0.0 }
+
+	// e^x = 2^k e^r, |r| <= ln(2) / 2
+	let k = round(x / LN_2);
+	let r = x - k * LN_2;
+	let (mut term, mut sum) = (1.0, 1.0);
+	for n in 1..22 {
+		term *= r / f64::from(n);
+		sum += term;
+	}
+
+	// split in two so both halves stay in the range of `pow2`
+	let scale = k as i64;
+	let half = scale / 2;
+	sum * pow2(half) * pow2(scale - half)
+}
+
+/// `x^y` for positive `x`, `0.0` otherwise
+pub(crate) fn powf(x: f64, y: f64) -> f64
+{
+	if x <= 0.0 { return 0.0 }
+	exp(y * ln(x))
+}
+
+/// Cube root, negative values included
+pub(crate) fn cbrt(x: f64) -> f64
+{
+	if x.abs() < f64::MIN_POSITIVE { return 0.0 }
+	let root = powf(x.abs(), 1.0 / 3.0);
+	// a Newton step fixes the last bits lost by `exp` and `ln`
+	let refined = root - (root * root * root - x.abs()) / (3.0 * root * root);
+	refined.copysign(x)
+}
+
+/// Square root, `0.0` for values below zero
+pub(crate) fn sqrt(x: f64) -> f64
+{
+	if x < f64::MIN_POSITIVE { return 0.0 }
+	let root = powf(x, 0.5);
+	0.5 * (root + x / root)
+}
+
+/// Length of the vector `(x, y)`
+pub(crate) fn hypot(x: f64, y: f64) -> f64 { sqrt(x * x + y * y) }
+
+/// Sine and cosine of `x` radians
+pub(crate) fn sin_cos(x: f64) -> (f64, f64)
+{
+	// x = k π/2 + r, |r| <= π/4
+	let k = round(x / FRAC_PI_2);
+	let r = x - k * FRAC_PI_2;
+	let square = r * r;
+
+	let (mut sin_term, mut cos_term) = (r, 1.0);
+	let (mut sin, mut cos) = (r, 1.0);
+	for n in 1..10 {
+		sin_term *= -square / f64::from((2 * n) * (2 * n + 1));
+		cos_term *= -square / f64::from((2 * n - 1) * (2 * n));
+		sin += sin_term;
+		cos += cos_term;
+	}
+
+	match (k as i64).rem_euclid(4) {
+		0 => (sin, cos),
+		1 => (cos, -sin),
+		2 => (-sin, -cos),
+		_ => (-cos, sin),
+	}
+}
+
+/// Arctangent in radians
+fn atan(x: f64) -> f64
+{
+	// atan(x) = 90° - atan(1 / x), so only `0.0..=1.0` is left
+	let magnitude = x.abs();
+	let (base, sign, reduced) = if magnitude > 1.0 { (FRAC_PI_2, -1.0, 1.0 / magnitude) } else { (0.0, 1.0, magnitude) };
+
+	// atan(x) = 30° + atan((x - tan 30°) / (1 + x tan 30°)), done once it lands within ±15°
+	let (shift, small) = if reduced > TAN_PI_12 {
+		(FRAC_PI_6, (reduced - TAN_PI_6) / (1.0 + reduced * TAN_PI_6))
+	} else {
+		(0.0, reduced)
+	};
+
+	let square = small * small;
+	let (mut power, mut sum) = (small, 0.0);
+	for n in 0..14 {
+		let term = power / f64::from(2 * n + 1);
+		sum += if n % 2 == 0 { term } else { -term };
+		power *= square;
+	}
+	(base + sign * (shift + sum)).copysign(x)
+}
+
+/// Angle of the point `(x, y)` in radians, in `-π..=π`
+pub(crate) fn atan2(y: f64, x: f64) -> f64
+{
+	if x > 0.0 { return atan(y / x) }
+	if x < 0.0 { return if y.is_sign_negative() { atan(y / x) - PI } else { atan(y / x) + PI } }
+	if y > 0.0 { FRAC_PI_2 } else if y < 0.0 { -FRAC_PI_2 } else { 0.0 }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+	extern crate std;
+	use super::{atan, atan2, cbrt, exp, floor, hypot, ln, powf, rem_euclid, round, sin_cos, sqrt};
+
+	/// `count` values evenly spread over `from..=to`
+	fn samples(from: f64, to: f64, count: u32) -> impl Iterator<Item = f64>
+		{ (0..=count).map(move |n| from + (to - from) * f64::from(n) / f64::from(count)) }
+
+	/// `actual` is `expected` within `tolerance`, relative for big values
+	fn close(actual: f64, expected: f64, tolerance: f64) -> bool
+		{ (actual - expected).abs() <= tolerance * expected.abs().max(1.0) }
+
+	#[test]
+	fn floor_and_round()
+	{
+		assert_eq!(floor(2.7), 2.0);
+		assert_eq!(floor(-2.3), -3.0);
+		assert_eq!(floor(-3.0), -3.0);
+		assert_eq!(floor(1e300), 1e300);
+
+		assert_eq!(round(0.499_999_999_999_999_94), 0.0);
+		assert_eq!(round(-0.499_999_999_999_999_94), 0.0);
+		assert_eq!(round(0.5), 1.0);
+		assert_eq!(round(-0.5), -1.0);
+		assert_eq!(round(2.5), 3.0);
+		assert_eq!(round(-2.4), -2.0);
+		assert_eq!(round(4_503_599_627_370_497.0), 4_503_599_627_370_497.0);
+	}
+
+	#[test]
+	fn rem_euclid_stays_in_range()
+	{
+		assert_eq!(rem_euclid(370.0, 360.0), 10.0);
+		assert_eq!(rem_euclid(-30.0, 360.0), 330.0);
+		assert_eq!(rem_euclid(360.0, 360.0), 0.0);
+		let tiny = rem_euclid(-1e-20, 360.0);
+		assert!((0.0..360.0).contains(&tiny));
+	}
+
+	#[test]
+	fn ln_and_exp()
+	{
+		for x in samples(-700.0, 700.0, 1000) {
+			assert!(close(exp(x), x.exp(), 1e-13), "exp({x})");
+		}
+		for x in samples(1e-300, 1e300, 1000).chain([f64::MIN_POSITIVE / 4.0, 0.5, 1.0, 2.0]) {
+			assert!((ln(x) - x.ln()).abs() < 1e-12, "ln({x})");
+		}
+	}
+
+	#[test]
+	fn powers_and_roots()
+	{
+		assert_eq!(powf(-2.0, 2.0), 0.0);
+		assert_eq!(cbrt(-27.0), -3.0);
+		assert_eq!(sqrt(-1.0), 0.0);
+		assert_eq!(hypot(3.0, 4.0), 5.0);
+
+		for x in samples(0.0, 1000.0, 997) {
+			assert!(close(powf(x, 2.4), x.powf(2.4), 1e-13), "powf({x})");
+			assert!(close(cbrt(x), x.cbrt(), 1e-15), "cbrt({x})");
+			assert!(close(sqrt(x), x.sqrt(), 1e-15), "sqrt({x})");
+		}
+	}
+
+	#[test]
+	fn sin_and_cos()
+	{
+		for x in samples(-20.0, 20.0, 1001) {
+			let (sin, cos) = sin_cos(x);
+			assert!((sin - x.sin()).abs() < 1e-14, "sin({x})");
+			assert!((cos - x.cos()).abs() < 1e-14, "cos({x})");
+		}
+	}
+
+	#[test]
+	fn atan_near_the_reductions()
+	{
+		// tan 15° and tan 75°, where a reduction used to hand the same value to the next one
+		assert!((atan2(0.267_949_192_431_122_75, 1.0) - 15f64.to_radians()).abs() < 1e-15);
+		assert!((atan2(3.732_050_807_568_876_7, 1.0) - 75f64.to_radians()).abs() < 1e-15);
+
+		for x in samples(-50.0, 50.0, 10_001).chain([1e300, -1e300, 1e-300]) {
+			assert!((atan(x) - x.atan()).abs() < 1e-15, "atan({x})");
+		}
+	}
+
+	#[test]
+	fn atan2_every_quadrant()
+	{
+		for degrees in samples(-179.0, 180.0, 359) {
+			let (y, x) = sin_cos(degrees.to_radians());
+			assert!((atan2(y * 3.0, x * 3.0) - y.atan2(x)).abs() < 1e-14, "atan2 at {degrees}°");
+		}
+		assert_eq!(atan2(0.0, 0.0), 0.0);
+		assert_eq!(atan2(-1.0, 0.0), -core::f64::consts::FRAC_PI_2);
+	}
+}
@@ -0,0 +1,374 @@
+/*!
+ * Readiness based event loop over `epoll`, cross thread wakeups over `eventfd` and timers over `timerfd`
+ *
+ * ```rust
+ * let mut reactor = Reactor::new()?;
+ * reactor.register(bus.fd(), BUS, Interest::READABLE)?;
+ * let waker = reactor.waker(WAKE)?;
+ * reactor.set_timer(BLINK, Duration::from_millis(500), Some(Duration::from_millis(500)))?;
+ *
+ * let mut events = Vec::new();
+ * loop {
+ * 	reactor.poll(&mut events, None)?;
+ * 	for event in &events { handle(event.token()) }
+ * }
+ * ```
+ */
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::BitOr;
+use core::time::Duration;
+use crate::socket::OwnedFd;
+use crate::{Detail, Error, WResponse};
+use crate::syscall::Errno;
+
+/// This will handle with our C imports from `linux/reactor.c`
+mod linux {
+	use super::Event;
+
+	unsafe extern "C" {
+		pub(crate) fn reactor_create() -> i32;
+		pub(crate) fn reactor_control(epfd: i32, op: i32, fd: i32, interest: u32, token: u64) -> i32;
+		pub(crate) fn reactor_wait(epfd: i32, out: *mut Event, max: i32, timeout_ms: i32) -> i32;
+		pub(crate) fn reactor_eventfd() -> i32;
+		pub(crate) fn reactor_wake(fd: i32) -> i32;
+		pub(crate) fn reactor_drain(fd: i32) -> isize;
+		pub(crate) fn reactor_timer() -> i32;
+		pub(crate) fn reactor_timer_set(fd: i32, after_ns: u64, interval_ns: u64) -> i32;
+	}
+}
+
+/// Most events taken from the kernel by a single `poll`
+const MAX_EVENTS: usize = 64;
+
+/// `reactor_control` operations
+const ADD: i32 = 0;
+/// `reactor_control` operations
+const MODIFY: i32 = 1;
+/// `reactor_control` operations
+const REMOVE: i32 = 2;
+
+/// `EPERM`, the descriptor can't be watched, like a regular file
+const EPERM: i32 = 1;
+/// `ENOENT`, the descriptor wasn't registered
+const ENOENT: i32 = 2;
+/// `EINTR`, the wait was interrupted by a signal
+const EINTR: i32 = 4;
+/// `EBADF`, the descriptor is not open
+const EBADF: i32 = 9;
+/// `ENOMEM`, the kernel is out of memory
+const ENOMEM: i32 = 12;
+/// `EEXIST`, the descriptor is already registered
+const EEXIST: i32 = 17;
+/// `EINVAL`, bad argument
+const EINVAL: i32 = 22;
+/// `ENFILE`, the system ran out of descriptors
+const ENFILE: i32 = 23;
+/// `EMFILE`, the process ran out of descriptors
+const EMFILE: i32 = 24;
+/// `ENOSPC`, the user limit of watched descriptors was reached
+const ENOSPC: i32 = 28;
+
+/// Maps a negative errno from `reactor.c` into the closest `WResponse`, the errno is kept as detail
+fn response(errno: i32) -> Error
+{
+	let kind = match errno.saturating_neg() {
+		EPERM | ENOENT | EBADF | EEXIST | EINVAL => WResponse::InvalidRequest,
+		ENOMEM | ENFILE | EMFILE | ENOSPC => WResponse::OutOfBounds,
+		_ => WResponse::UnexpectedError,
+	};
+	Error::new(kind).with_detail(Detail::Errno(Errno(errno.saturating_neg())))
+}
+
+/// Turns a status from `reactor.c` into a result
+fn check(status: i32) -> Result<(), Error>
+{
+	if status < 0 { return Err(response(status)) }
+	Ok(())
+}
+
+/// Turns the result of a call that creates a descriptor into an `OwnedFd`
+fn created(fd: i32) -> Result<OwnedFd, Error>
+{
+	if fd < 0 { return Err(response(fd)) }
+	Ok(unsafe { OwnedFd::from_raw(fd) })
+}
+
+/// Saturating nanoseconds of `duration`
+fn nanos(duration: Duration) -> u64
+	{ u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX) }
+
+/// Identifies what became ready, chosen when registering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Token(pub u64);
+
+/// What to be notified about, combine them with `|`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interest(u32);
+
+impl Interest {
+	/// There is something to read, or a client to accept
+	pub const READABLE: Self = Self(1);
+	/// There is room to write
+	pub const WRITABLE: Self = Self(2);
+}
+
+impl BitOr for Interest {
+	type Output = Self;
+
+	fn bitor(self, other: Self) -> Self { Self(self.0 | other.0) }
+}
+
+/// A registered descriptor that became ready, filled by `Reactor::poll`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+	/// Token given on `register`
+	token: u64,
+	/// `READABLE`, `WRITABLE` and `CLOSED` bits, keep in sync with `reactor.c`
+	flags: u32,
+}
+
+impl Event {
+	/// `reactor.c` flag, the peer hung up or the descriptor has an error
+	const CLOSED: u32 = 4;
+
+	/// Token given when the descriptor, waker or timer was registered
+	#[must_use]
+	pub fn token(&self) -> Token { Token(self.token) }
+
+	/// There is something to read, wakers and timers are always readable
+	#[must_use]
+	pub fn is_readable(&self) -> bool { self.flags & Interest::READABLE.0 != 0 }
+
+	/// There is room to write
+	#[must_use]
+	pub fn is_writable(&self) -> bool { self.flags & Interest::WRITABLE.0 != 0 }
+
+	/// The peer hung up or the descriptor has an error, reading tells which
+	#[must_use]
+	pub fn is_closed(&self) -> bool { self.flags & Self::CLOSED != 0 }
+}
+
+/// Wakes a `Reactor` from any thread, its token shows up in the next `poll`
+#[derive(Debug, Clone)]
+pub struct Waker {
+	/// The `eventfd` watched by the reactor
+	fd: Arc<OwnedFd>,
+}
+
+impl Waker {
+	/**
+	 * Makes the reactor return from `poll`, wakes before the next `poll` are merged
+	 *
+	 * # Errors
+	 *
+	 * `UnexpectedError` if the counter couldn't be written, the descriptor lives as long as any waker
+	 */
+	pub fn wake(&self) -> Result<(), Error>
+		{ check(unsafe { linux::reactor_wake(self.fd.raw()) }) }
+}
+
+/// A `timerfd` owned by the reactor
+#[derive(Debug)]
+struct Timer {
+	/// Reported when it fires
+	token: Token,
+	/// The timer descriptor
+	fd: OwnedFd,
+	/// `false` for single shots, they are closed after firing
+	repeat: bool,
+}
+
+/// Waits on many descriptors at once, see the module docs
+#[derive(Debug)]
+pub struct Reactor {
+	/// The `epoll` instance
+	epoll: OwnedFd,
+	/// Wakers handed out, their counter is reset when they fire
+	wakers: Vec<(Token, Arc<OwnedFd>)>,
+	/// Armed timers
+	timers: Vec<Timer>,
+}
+
+impl Reactor {
+	/**
+	 * Creates an empty reactor
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the process ran out of descriptors
+	 */
+	pub fn new() -> Result<Self, Error>
+	{
+		let epoll = created(unsafe { linux::reactor_create() })?;
+		Ok(Self { epoll, wakers: Vec::new(), timers: Vec::new() })
+	}
+
+	/**
+	 * Watches `fd`, `poll` reports `token` while it is ready
+	 * it is level triggered: an event repeats until the descriptor is read or written
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if `fd` is already registered or can't be watched, like a regular file
+	 */
+	pub fn register(&mut self, fd: i32, token: Token, interest: Interest) -> Result<(), Error>
+		{ check(unsafe { linux::reactor_control(self.epoll.raw(), ADD, fd, interest.0, token.0) }) }
+
+	/**
+	 * Changes the token or interest of a registered `fd`
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if `fd` isn't registered
+	 */
+	pub fn modify(&mut self, fd: i32, token: Token, interest: Interest) -> Result<(), Error>
+		{ check(unsafe { linux::reactor_control(self.epoll.raw(), MODIFY, fd, interest.0, token.0) }) }
+
+	/**
+	 * Stops watching `fd`, closing it does the same
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if `fd` isn't registered
+	 */
+	pub fn deregister(&mut self, fd: i32) -> Result<(), Error>
+		{ check(unsafe { linux::reactor_control(self.epoll.raw(), REMOVE, fd, 0, 0) }) }
+
+	/**
+	 * Creates a `Waker` for other threads, `poll` reports `token` when it is used
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the process ran out of descriptors
+	 */
+	pub fn waker(&mut self, token: Token) -> Result<Waker, Error>
+	{
+		let fd = Arc::new(created(unsafe { linux::reactor_eventfd() })?);
+		self.register(fd.raw(), token, Interest::READABLE)?;
+		self.wakers.push((token, Arc::clone(&fd)));
+		Ok(Waker { fd })
+	}
+
+	/**
+	 * Reports `token` after `after`, then every `interval` if there is one
+	 * setting a token that is already armed restarts it
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if `interval` is zero, `OutOfBounds` if the process ran out of descriptors
+	 */
+	pub fn set_timer(&mut self, token: Token, after: Duration, interval: Option<Duration>) -> Result<(), Error>
+	{
+		// a zero interval makes a timer that fires once, but it would stay registered as repeating
+		if interval == Some(Duration::ZERO) {
+			return Err(Error::new(WResponse::InvalidRequest).with_message("a timer can't repeat every 0 ns"));
+		}
+
+		let index = if let Some(index) = self.timers.iter().position(|timer| timer.token == token) { index } else {
+			let fd = created(unsafe { linux::reactor_timer() })?;
+			self.register(fd.raw(), token, Interest::READABLE)?;
+			self.timers.push(Timer { token, fd, repeat: false });
+			self.timers.len().saturating_sub(1)
+		};
+
+		let Some(timer) = self.timers.get_mut(index) else { return Err(WResponse::UnexpectedError.into()) };
+		timer.repeat = interval.is_some();
+
+		// a zero `after` would disarm the timer
+		let first = nanos(after).max(1);
+		check(unsafe { linux::reactor_timer_set(timer.fd.raw(), first, interval.map_or(0, nanos)) })
+	}
+
+	/// Disarms the timer of `token`, returns `false` if it wasn't armed
+	pub fn cancel_timer(&mut self, token: Token) -> bool
+	{
+		let Some(index) = self.timers.iter().position(|timer| timer.token == token) else { return false };
+		let _ = self.timers.swap_remove(index);
+		true
+	}
+
+	/**
+	 * Waits until something is ready or `timeout` passes, `None` waits forever
+	 * `events` is cleared and filled, it stays empty on timeout or if a signal interrupted the wait
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the reactor is no longer valid
+	 */
+	pub fn poll(&mut self, events: &mut Vec<Event>, timeout: Option<Duration>) -> Result<(), Error>
+	{
+		events.clear();
+
+		// rounded up, so timeouts never end early
+		let timeout_ms = timeout.map_or(-1, |t| {
+			let ms = t.as_nanos().div_ceil(1_000_000);
+			i32::try_from(ms).unwrap_or(i32::MAX)
+		});
+
+		let mut ready = [Event { token: 0, flags: 0 }; MAX_EVENTS];
+		let max = i32::try_from(ready.len()).unwrap_or(0);
+		let status = unsafe { linux::reactor_wait(self.epoll.raw(), ready.as_mut_ptr(), max, timeout_ms) };
+		if status == -EINTR { return Ok(()) }
+		let count = usize::try_from(status).map_err(|_| response(status))?;
+
+		for event in ready.iter().take(count) {
+			self.reset(event.token());
+			events.push(*event);
+		}
+		Ok(())
+	}
+
+	/// Clears the counter of the waker or timer behind `token`, single shot timers are closed
+	fn reset(&mut self, token: Token)
+	{
+		if let Some((_, fd)) = self.wakers.iter().find(|(waker, _)| *waker == token) {
+			let _ = unsafe { linux::reactor_drain(fd.raw()) };
+		}
+
+		let Some(index) = self.timers.iter().position(|timer| timer.token == token) else { return };
+		let Some(timer) = self.timers.get(index) else { return };
+		let _ = unsafe { linux::reactor_drain(timer.fd.raw()) };
+		if !timer.repeat { let _ = self.timers.swap_remove(index); }
+	}
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	use alloc::vec::Vec;
+	use core::time::Duration;
+	use crate::WResponse;
+	use super::{Reactor, Token};
+
+	#[test]
+	fn zero_interval_is_rejected()
+	{
+		let mut reactor = Reactor::new().unwrap();
+		let error = reactor.set_timer(Token(1), Duration::ZERO, Some(Duration::ZERO)).unwrap_err();
+		assert_eq!(error.kind(), WResponse::InvalidRequest);
+		assert!(!reactor.cancel_timer(Token(1)));
+	}
+
+	#[test]
+	fn timers_fire_once_or_repeat()
+	{
+		let mut reactor = Reactor::new().unwrap();
+		let mut events = Vec::new();
+		reactor.set_timer(Token(1), Duration::ZERO, None).unwrap();
+		reactor.set_timer(Token(2), Duration::from_millis(1), Some(Duration::from_millis(1))).unwrap();
+
+		let mut fired = [0u32; 2];
+		while fired[1] < 3 {
+			reactor.poll(&mut events, Some(Duration::from_secs(1))).unwrap();
+			assert!(!events.is_empty());
+			for event in &events { fired[usize::try_from(event.token().0 - 1).unwrap()] += 1 }
+		}
+
+		// the single shot timer is gone once it fired, the interval keeps going until cancelled
+		assert_eq!(fired[0], 1);
+		assert!(!reactor.cancel_timer(Token(1)));
+		assert!(reactor.cancel_timer(Token(2)));
+	}
+}
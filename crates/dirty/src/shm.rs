@@ -0,0 +1,136 @@
+/*!
+ * Memory shared with another process through a sealed `memfd`, the storage behind `wl_shm` pools
+ *
+ * ```rust
+ * let mut pool = ShmPool::new(c"cursor", 32 * 32 * 4)?;
+ * pool.as_mut_slice().fill(0xff);
+ * send_to_compositor(pool.fd(), pool.len());
+ * ```
+ *
+ * the file is sealed against shrinking, so the other side can map it without fearing `SIGBUS`
+ * and it can only grow, like `wl_shm_pool.resize`
+ */
+use core::ffi::CStr;
+use crate::socket::OwnedFd;
+use crate::syscall::{
+	self,
+	F_ADD_SEALS,
+	F_SEAL_GROW,
+	F_SEAL_SEAL,
+	F_SEAL_SHRINK,
+	F_SEAL_WRITE,
+	MAP_SHARED,
+	MFD_ALLOW_SEALING,
+	MFD_CLOEXEC,
+	PROT_READ,
+	PROT_WRITE,
+};
+use crate::{Error, WResponse};
+
+/// A shared memory file mapped into this process, see the module docs
+#[derive(Debug)]
+pub struct ShmPool {
+	/// The `memfd`, closed on drop
+	fd: OwnedFd,
+	/// Start of the mapping
+	ptr: *mut u8,
+	/// Size of the file and of the mapping
+	len: usize,
+}
+
+// the mapping is owned by the pool, like a `Box<[u8]>`
+unsafe impl Send for ShmPool {}
+
+/// Maps `len` bytes of `fd` for reading and writing
+fn map(fd: i32, len: usize) -> Result<*mut u8, Error>
+{
+	let ptr = unsafe { syscall::mmap(core::ptr::null_mut(), len, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0) }?;
+	Ok(ptr)
+}
+
+impl ShmPool {
+	/**
+	 * Creates a zeroed pool of `len` bytes, `name` only shows up in `/proc/<pid>/fd`
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if `len` is `0`, `OutOfBounds` without memory or descriptors left,
+	 * `BinarySpecificLimitation` on kernels without `memfd_create`
+	 */
+	pub fn new(name: &CStr, len: usize) -> Result<Self, Error>
+	{
+		if len == 0 { return Err(WResponse::InvalidRequest.into()) }
+
+		let fd = unsafe { OwnedFd::from_raw(syscall::memfd_create(name, MFD_CLOEXEC | MFD_ALLOW_SEALING)?) };
+		syscall::ftruncate(fd.raw(), len)?;
+
+		// sealing the seals too, the receiver could otherwise forbid growing it
+		let _ = syscall::fcntl(fd.raw(), F_ADD_SEALS, F_SEAL_SHRINK | F_SEAL_SEAL)?;
+
+		let ptr = map(fd.raw(), len)?;
+		Ok(Self { fd, ptr, len })
+	}
+
+	/// The `memfd`, send it to share the pool, it stays owned by `self`
+	#[must_use]
+	pub fn fd(&self) -> i32 { self.fd.raw() }
+
+	/// Size in bytes
+	#[must_use]
+	pub fn len(&self) -> usize { self.len }
+
+	/// Never true, pools have at least one byte
+	#[must_use]
+	pub fn is_empty(&self) -> bool { self.len == 0 }
+
+	/// The whole pool
+	#[must_use]
+	pub fn as_slice(&self) -> &[u8]
+		{ unsafe { core::slice::from_raw_parts(self.ptr, self.len) } }
+
+	/// The whole pool, writes are seen by the other process
+	#[must_use]
+	pub fn as_mut_slice(&mut self) -> &mut [u8]
+		{ unsafe { core::slice::from_raw_parts_mut(self.ptr, self.len) } }
+
+	/**
+	 * Grows the pool to `len` bytes, keeping the contents, smaller sizes are ignored
+	 * the mapping moves, slices taken before can't outlive this call anyway
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` without memory left, the pool keeps its old size then
+	 */
+	pub fn grow(&mut self, len: usize) -> Result<(), Error>
+	{
+		if len <= self.len { return Ok(()) }
+
+		syscall::ftruncate(self.fd.raw(), len)?;
+		let ptr = map(self.fd.raw(), len)?;
+		let _ = unsafe { syscall::munmap(self.ptr, self.len) };
+		self.ptr = ptr;
+		self.len = len;
+		Ok(())
+	}
+}
+
+impl Drop for ShmPool {
+	fn drop(&mut self)
+		{ let _ = unsafe { syscall::munmap(self.ptr, self.len) }; }
+}
+
+/**
+ * A `memfd` holding `data` that nobody can change anymore, for receivers that want a sealed file
+ * like journald with entries too big for a datagram
+ *
+ * # Errors
+ *
+ * `OutOfBounds` without memory or descriptors left, `BinarySpecificLimitation` on kernels without `memfd_create`
+ */
+pub fn sealed(name: &CStr, data: &[u8]) -> Result<OwnedFd, Error>
+{
+	let fd = unsafe { OwnedFd::from_raw(syscall::memfd_create(name, MFD_CLOEXEC | MFD_ALLOW_SEALING)?) };
+	syscall::write_all(fd.raw(), data)?;
+	let _ = syscall::fcntl(fd.raw(), F_ADD_SEALS, F_SEAL_SHRINK | F_SEAL_GROW | F_SEAL_WRITE | F_SEAL_SEAL)?;
+	Ok(fd)
+}
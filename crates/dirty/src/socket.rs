@@ -0,0 +1,645 @@
+/*!
+ * Unix domain sockets, bound to a path on the file system or to an abstract name on linux
+ *
+ * ```rust
+ * let listener = UnixListener::bind("/run/user/1000/app.sock")?;
+ * let mut client = UnixStream::connect("/run/user/1000/app.sock")?;
+ * client.send_all(b"hello")?;
+ *
+ * // descriptors can travel with the data, wayland sends keymaps like this
+ * let (mut left, mut right) = UnixStream::pair()?;
+ * left.send_fds(b"k", &[keymap.raw()])?;
+ * let mut fds = Vec::new();
+ * right.recv_fds(&mut [0; 1], &mut fds)?;
+ * ```
+ */
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use crate::{Detail, Error, WResponse};
+use crate::syscall::Errno;
+
+/// This will handle with our C imports from `unix/socket.c`
+mod unix {
+	unsafe extern "C" {
+		pub(crate) fn socket_connect(path: *const u8, len: usize, abstract_ns: i32, datagram: i32) -> i32;
+		pub(crate) fn socket_bind(path: *const u8, len: usize, abstract_ns: i32, datagram: i32, backlog: i32) -> i32;
+		pub(crate) fn socket_unbound() -> i32;
+		pub(crate) fn socket_pair(datagram: i32, fds: *mut i32) -> i32;
+		pub(crate) fn socket_accept(fd: i32) -> i32;
+		pub(crate) fn socket_nonblocking(fd: i32, enable: i32) -> i32;
+		pub(crate) fn socket_wait(fd: i32, timeout_ms: i32) -> i32;
+		pub(crate) fn socket_send(fd: i32, buf: *const u8, len: usize) -> isize;
+		pub(crate) fn socket_send_to(
+			fd: i32,
+			buf: *const u8,
+			len: usize,
+			path: *const u8,
+			path_len: usize,
+			abstract_ns: i32,
+		) -> isize;
+		pub(crate) fn socket_recv(fd: i32, buf: *mut u8, len: usize, nonblock: i32) -> isize;
+		pub(crate) fn socket_send_fds(fd: i32, buf: *const u8, len: usize, fds: *const i32, count: usize) -> isize;
+		pub(crate) fn socket_recv_fds(
+			fd: i32,
+			buf: *mut u8,
+			len: usize,
+			fds: *mut i32,
+			count: *mut usize,
+			nonblock: i32,
+		) -> isize;
+		pub(crate) fn socket_unlink(path: *const u8, len: usize) -> i32;
+		pub(crate) fn socket_close(fd: i32);
+		pub(crate) fn socket_failure(errno: i32) -> i32;
+	}
+}
+
+/// The most descriptors `send_fds` and `recv_fds` handle in a single message
+pub const MAX_FDS: usize = 28;
+
+/// Connections waiting to be accepted before new clients are refused
+const BACKLOG: i32 = 16;
+
+/// `SOCKET_INTERRUPTED` of `socket.c`, `EINTR`: a signal arrived and the call should be repeated
+const INTERRUPTED: i32 = 1;
+/// `SOCKET_WOULD_BLOCK` of `socket.c`, `EAGAIN`: nothing to do on a non blocking socket
+const WOULD_BLOCK: i32 = 2;
+/// `SOCKET_DENIED` of `socket.c`, `EACCES` or `EPERM`: no permission on the socket file
+const DENIED: i32 = 3;
+/// `SOCKET_IN_USE` of `socket.c`, `EADDRINUSE`: the address is already bound
+const IN_USE: i32 = 4;
+/// `SOCKET_EXHAUSTED` of `socket.c`: a path or message too long, or out of memory or descriptors
+const EXHAUSTED: i32 = 5;
+/// `SOCKET_LOST` of `socket.c`: nobody is listening or the peer went away
+const LOST: i32 = 6;
+/// `SOCKET_INVALID` of `socket.c`: a closed descriptor, a bad argument or the wrong kind of socket
+const INVALID: i32 = 7;
+
+/// Sorts a negative errno from `socket.c` into one of the constants above, the numbers depend on the OS
+fn failure(result: isize) -> i32
+{
+	let errno = i32::try_from(result.saturating_neg()).unwrap_or_default();
+	unsafe { unix::socket_failure(errno) }
+}
+
+/// Maps a negative errno from `socket.c` into the closest `WResponse`, the errno is kept as detail
+fn response(result: isize) -> Error
+{
+	let kind = match failure(result) {
+		DENIED => WResponse::AccessDenied,
+		IN_USE => WResponse::ChannelInUse,
+		EXHAUSTED => WResponse::OutOfBounds,
+		LOST => WResponse::ConnectionLost,
+		INVALID => WResponse::InvalidRequest,
+		_ => WResponse::UnexpectedError,
+	};
+	let code = i32::try_from(result.saturating_neg()).unwrap_or_default();
+	Error::new(kind).with_detail(Detail::Errno(Errno(code)))
+}
+
+/// Retries `call` while it is interrupted by a signal
+fn retry(mut call: impl FnMut() -> isize) -> isize
+{
+	loop {
+		let result = call();
+		if result >= 0 || failure(result) != INTERRUPTED { return result }
+	}
+}
+
+/// Turns the result of a send or recv into a byte count, `None` if a non blocking call would wait
+fn transferred(result: isize) -> Result<Option<usize>, Error>
+{
+	if result < 0 && failure(result) == WOULD_BLOCK { return Ok(None) }
+	usize::try_from(result).map(Some).map_err(|_| response(result))
+}
+
+/// Turns the result of a call that creates a descriptor into an `OwnedFd`
+fn created(fd: i32) -> Result<OwnedFd, Error>
+{
+	if fd < 0 { return Err(response(fd as isize)) }
+	Ok(OwnedFd(fd))
+}
+
+/// Where a socket lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address<'a> {
+	/// A socket file, plain strings convert into this
+	Path(&'a str),
+	/// A name in the linux abstract namespace, it has no file and goes away with the last socket
+	Abstract(&'a str),
+}
+
+impl<'a> Address<'a> {
+	/// The address bytes and the abstract flag for `socket.c`
+	fn raw(self) -> (&'a [u8], i32)
+	{
+		match self {
+			Address::Path(path) => (path.as_bytes(), 0),
+			Address::Abstract(name) => (name.as_bytes(), 1),
+		}
+	}
+}
+
+impl<'a> From<&'a str> for Address<'a> {
+	fn from(path: &'a str) -> Self { Address::Path(path) }
+}
+
+impl<'a> From<&'a String> for Address<'a> {
+	fn from(path: &'a String) -> Self { Address::Path(path) }
+}
+
+/// A file descriptor closed on drop, like the ones received with `recv_fds`
+#[derive(Debug, PartialEq, Eq)]
+pub struct OwnedFd(i32);
+
+impl OwnedFd {
+	/**
+	 * Takes ownership of `fd`
+	 *
+	 * # Safety
+	 *
+	 * `fd` must be open and nothing else may close it
+	 */
+	#[must_use]
+	pub unsafe fn from_raw(fd: i32) -> Self { Self(fd) }
+
+	/// The descriptor, still owned by `self`
+	#[must_use]
+	pub fn raw(&self) -> i32 { self.0 }
+
+	/// Gives the descriptor back without closing it
+	#[must_use]
+	pub fn into_raw(self) -> i32
+	{
+		let fd = self.0;
+		core::mem::forget(self);
+		fd
+	}
+}
+
+impl Drop for OwnedFd {
+	fn drop(&mut self)
+		{ unsafe { unix::socket_close(self.0) } }
+}
+
+/// Shared by every socket type
+fn set_nonblocking(fd: &OwnedFd, enable: bool) -> Result<(), Error>
+{
+	let status = unsafe { unix::socket_nonblocking(fd.raw(), i32::from(enable)) };
+	if status < 0 { return Err(response(status as isize)) }
+	Ok(())
+}
+
+/// Shared by every socket type
+fn send(fd: &OwnedFd, data: &[u8]) -> Result<Option<usize>, Error>
+	{ transferred(retry(|| unsafe { unix::socket_send(fd.raw(), data.as_ptr(), data.len()) })) }
+
+/// Shared by every socket type, `nonblock` overrides a blocking socket for this call
+fn recv(fd: &OwnedFd, buf: &mut [u8], nonblock: bool) -> Result<Option<usize>, Error>
+{
+	let flag = i32::from(nonblock);
+	transferred(retry(|| unsafe { unix::socket_recv(fd.raw(), buf.as_mut_ptr(), buf.len(), flag) }))
+}
+
+/// Shared by every socket type
+fn send_fds(fd: &OwnedFd, data: &[u8], fds: &[i32]) -> Result<Option<usize>, Error>
+{
+	if fds.len() > MAX_FDS { return Err(WResponse::OutOfBounds.into()) }
+	transferred(retry(|| unsafe { unix::socket_send_fds(fd.raw(), data.as_ptr(), data.len(), fds.as_ptr(), fds.len()) }))
+}
+
+/// Shared by every socket type
+fn recv_fds(fd: &OwnedFd, buf: &mut [u8], fds: &mut Vec<OwnedFd>) -> Result<Option<usize>, Error>
+{
+	let mut received = [0i32; MAX_FDS];
+	let mut count = 0usize;
+	let read = retry(|| unsafe {
+		unix::socket_recv_fds(fd.raw(), buf.as_mut_ptr(), buf.len(), received.as_mut_ptr(), &raw mut count, 0)
+	});
+
+	fds.extend(received.iter().take(count).map(|&raw| OwnedFd(raw)));
+	transferred(read)
+}
+
+/// Creates two connected sockets
+fn pair(datagram: bool) -> Result<(OwnedFd, OwnedFd), Error>
+{
+	let mut fds = [0i32; 2];
+	let status = unsafe { unix::socket_pair(i32::from(datagram), fds.as_mut_ptr()) };
+	if status < 0 { return Err(response(status as isize)) }
+	let [first, second] = fds;
+	Ok((OwnedFd(first), OwnedFd(second)))
+}
+
+/// A connected stream socket, closed on drop
+#[derive(Debug)]
+pub struct UnixStream {
+	/// The socket file descriptor
+	fd: OwnedFd,
+}
+
+impl UnixStream {
+	/**
+	 * Connects to the socket bound on `address`
+	 *
+	 * # Errors
+	 *
+	 * `AccessDenied` without permission on the socket, `OutOfBounds` if the path is too long,
+	 * `ConnectionLost` if nobody is listening and `InvalidRequest` for abstract names outside of linux
+	 */
+	pub fn connect<'a>(address: impl Into<Address<'a>>) -> Result<Self, Error>
+	{
+		let (path, abstract_ns) = address.into().raw();
+		created(unsafe { unix::socket_connect(path.as_ptr(), path.len(), abstract_ns, 0) })
+			.map(|fd| Self { fd })
+	}
+
+	/**
+	 * Creates two streams connected to each other
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the process ran out of descriptors
+	 */
+	pub fn pair() -> Result<(Self, Self), Error>
+	{
+		let (first, second) = pair(false)?;
+		Ok((Self { fd: first }, Self { fd: second }))
+	}
+
+	/// The socket file descriptor, to be watched by an event loop
+	#[must_use]
+	pub fn fd(&self) -> i32 { self.fd.raw() }
+
+	/**
+	 * Makes `send` and `recv` return instead of waiting
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the socket is no longer valid
+	 */
+	pub fn set_nonblocking(&self, enable: bool) -> Result<(), Error>
+		{ set_nonblocking(&self.fd, enable) }
+
+	/**
+	 * Waits up to `timeout` for something to read, returns `false` if it passed first
+	 * a closed peer counts as readable, `recv` then returns `Some(0)`
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the socket is no longer valid
+	 */
+	pub fn wait_readable(&self, timeout: Duration) -> Result<bool, Error>
+	{
+		// rounded up, so a wait shorter than a millisecond doesn't turn into a poll
+		let ms = i32::try_from(timeout.as_micros().div_ceil(1000)).unwrap_or(i32::MAX);
+		let ready = retry(|| unsafe { unix::socket_wait(self.fd.raw(), ms) } as isize);
+		if ready < 0 { return Err(response(ready)) }
+		Ok(ready > 0)
+	}
+
+	/**
+	 * Sends part of `data`, returns how many bytes were sent
+	 * on a non blocking socket `None` means the peer isn't reading
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	pub fn send(&mut self, data: &[u8]) -> Result<Option<usize>, Error>
+		{ send(&self.fd, data) }
+
+	/**
+	 * Sends all of `data`, waiting for the peer if needed
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	pub fn send_all(&mut self, mut data: &[u8]) -> Result<(), Error>
+	{
+		while !data.is_empty() {
+			let Some(sent) = self.send(data)? else { continue };
+			data = data.get(sent..).unwrap_or_default();
+		}
+		Ok(())
+	}
+
+	/**
+	 * Receives up to `buf.len()` bytes, `Some(0)` means the peer closed the connection
+	 * on a non blocking socket `None` means there is nothing to read yet
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	pub fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error>
+		{ recv(&self.fd, buf, false) }
+
+	/**
+	 * Same as `recv`, but returns `None` instead of waiting even on a blocking socket
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	pub fn try_recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error>
+		{ recv(&self.fd, buf, true) }
+
+	/**
+	 * Sends part of `data` with up to `MAX_FDS` descriptors attached, the peer gets its own copies
+	 * `data` must not be empty, the descriptors travel with its first byte
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` with too many descriptors, `InvalidRequest` if one of them isn't open
+	 * and `ConnectionLost` if the peer is gone
+	 */
+	pub fn send_fds(&mut self, data: &[u8], fds: &[i32]) -> Result<Option<usize>, Error>
+	{
+		if data.is_empty() { return Err(WResponse::InvalidRequest.into()) }
+		send_fds(&self.fd, data, fds)
+	}
+
+	/**
+	 * Same as `recv`, the descriptors that arrived with the data are pushed to `fds`
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the peer sent more than `MAX_FDS` descriptors, they are all closed
+	 * and `ConnectionLost` if the peer is gone
+	 */
+	pub fn recv_fds(&mut self, buf: &mut [u8], fds: &mut Vec<OwnedFd>) -> Result<Option<usize>, Error>
+		{ recv_fds(&self.fd, buf, fds) }
+}
+
+/// A socket listening on an address, the socket file is removed on drop
+#[derive(Debug)]
+pub struct UnixListener {
+	/// The socket file descriptor
+	fd: OwnedFd,
+	/// Path or abstract name the socket is bound to
+	name: String,
+	/// `false` if there is a socket file to remove
+	abstract_ns: bool,
+}
+
+impl UnixListener {
+	/**
+	 * Binds a socket on `address` and starts listening
+	 *
+	 * # Errors
+	 *
+	 * `ChannelInUse` if the address is taken, a socket file counts even if nobody is listening anymore,
+	 * the rest like `UnixStream::connect`
+	 */
+	pub fn bind<'a>(address: impl Into<Address<'a>>) -> Result<Self, Error>
+	{
+		let bound = address.into();
+		let (path, abstract_ns) = bound.raw();
+		let fd = created(unsafe { unix::socket_bind(path.as_ptr(), path.len(), abstract_ns, 0, BACKLOG) })?;
+		let (Address::Path(name) | Address::Abstract(name)) = bound;
+		Ok(Self { fd, name: String::from(name), abstract_ns: abstract_ns != 0 })
+	}
+
+	/// The socket file descriptor, to be watched by an event loop
+	#[must_use]
+	pub fn fd(&self) -> i32 { self.fd.raw() }
+
+	/// The address the socket is bound to
+	#[must_use]
+	pub fn address(&self) -> Address<'_>
+	{
+		if self.abstract_ns { Address::Abstract(&self.name) } else { Address::Path(&self.name) }
+	}
+
+	/**
+	 * Makes `accept` return instead of waiting for a client
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the socket is no longer valid
+	 */
+	pub fn set_nonblocking(&self, enable: bool) -> Result<(), Error>
+		{ set_nonblocking(&self.fd, enable) }
+
+	/**
+	 * Takes the next client, the stream is blocking even if the listener isn't
+	 * on a non blocking listener `None` means no client is waiting
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the process ran out of descriptors, `InvalidRequest` if the socket is no longer valid
+	 */
+	pub fn accept(&self) -> Result<Option<UnixStream>, Error>
+	{
+		let client = retry(|| unsafe { unix::socket_accept(self.fd.raw()) } as isize);
+		if client < 0 && failure(client) == WOULD_BLOCK { return Ok(None) }
+		match i32::try_from(client) {
+			Ok(fd) if fd >= 0 => Ok(Some(UnixStream { fd: OwnedFd(fd) })),
+			_ => Err(response(client)),
+		}
+	}
+}
+
+impl Drop for UnixListener {
+	fn drop(&mut self)
+	{
+		if !self.abstract_ns { let _ = remove(&self.name); }
+	}
+}
+
+/// A datagram socket, every `send` is received whole by a single `recv`
+#[derive(Debug)]
+pub struct UnixDatagram {
+	/// The socket file descriptor
+	fd: OwnedFd,
+	/// Socket file to remove on drop
+	path: Option<String>,
+}
+
+impl UnixDatagram {
+	/**
+	 * Binds a socket on `address` to receive datagrams, a path is removed on drop
+	 *
+	 * # Errors
+	 *
+	 * Like `UnixListener::bind`
+	 */
+	pub fn bind<'a>(address: impl Into<Address<'a>>) -> Result<Self, Error>
+	{
+		let bound = address.into();
+		let (raw, abstract_ns) = bound.raw();
+		let fd = created(unsafe { unix::socket_bind(raw.as_ptr(), raw.len(), abstract_ns, 1, 0) })?;
+		let path = match bound {
+			Address::Path(path) => Some(String::from(path)),
+			Address::Abstract(_) => None,
+		};
+		Ok(Self { fd, path })
+	}
+
+	/**
+	 * A socket without address, it can only `send_to` or `connect`
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the process ran out of descriptors
+	 */
+	pub fn unbound() -> Result<Self, Error>
+		{ created(unsafe { unix::socket_unbound() }).map(|fd| Self { fd, path: None }) }
+
+	/**
+	 * A socket that sends every datagram to `address`, it is unbound itself
+	 *
+	 * # Errors
+	 *
+	 * Like `UnixStream::connect`
+	 */
+	pub fn connect<'a>(address: impl Into<Address<'a>>) -> Result<Self, Error>
+	{
+		let (path, abstract_ns) = address.into().raw();
+		created(unsafe { unix::socket_connect(path.as_ptr(), path.len(), abstract_ns, 1) })
+			.map(|fd| Self { fd, path: None })
+	}
+
+	/**
+	 * Creates two datagram sockets connected to each other
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the process ran out of descriptors
+	 */
+	pub fn pair() -> Result<(Self, Self), Error>
+	{
+		let (first, second) = pair(true)?;
+		Ok((Self { fd: first, path: None }, Self { fd: second, path: None }))
+	}
+
+	/// The socket file descriptor, to be watched by an event loop
+	#[must_use]
+	pub fn fd(&self) -> i32 { self.fd.raw() }
+
+	/**
+	 * Makes sending and receiving return instead of waiting
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the socket is no longer valid
+	 */
+	pub fn set_nonblocking(&self, enable: bool) -> Result<(), Error>
+		{ set_nonblocking(&self.fd, enable) }
+
+	/**
+	 * Sends `data` as one datagram to the connected peer
+	 * on a non blocking socket `None` means the peer queue is full
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the socket isn't connected, `OutOfBounds` if `data` is too big
+	 * and `ConnectionLost` if the peer is gone
+	 */
+	pub fn send(&self, data: &[u8]) -> Result<Option<usize>, Error>
+		{ send(&self.fd, data) }
+
+	/**
+	 * Sends `data` as one datagram to `address`
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if nothing is bound on `address`, the rest like `send`
+	 */
+	pub fn send_to<'a>(&self, data: &[u8], address: impl Into<Address<'a>>) -> Result<Option<usize>, Error>
+	{
+		let (path, abstract_ns) = address.into().raw();
+		transferred(retry(|| unsafe {
+			unix::socket_send_to(self.fd.raw(), data.as_ptr(), data.len(), path.as_ptr(), path.len(), abstract_ns)
+		}))
+	}
+
+	/**
+	 * Receives one datagram, the part that doesn't fit in `buf` is lost
+	 * on a non blocking socket `None` means nothing was sent yet
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	pub fn recv(&self, buf: &mut [u8]) -> Result<Option<usize>, Error>
+		{ recv(&self.fd, buf, false) }
+
+	/**
+	 * Same as `recv`, but returns `None` instead of waiting even on a blocking socket
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the peer is gone
+	 */
+	pub fn try_recv(&self, buf: &mut [u8]) -> Result<Option<usize>, Error>
+		{ recv(&self.fd, buf, true) }
+
+	/**
+	 * Sends `data` as one datagram with up to `MAX_FDS` descriptors to the connected peer
+	 *
+	 * # Errors
+	 *
+	 * Like `UnixStream::send_fds`
+	 */
+	pub fn send_fds(&self, data: &[u8], fds: &[i32]) -> Result<Option<usize>, Error>
+		{ send_fds(&self.fd, data, fds) }
+
+	/**
+	 * Same as `recv`, the descriptors that arrived with the datagram are pushed to `fds`
+	 *
+	 * # Errors
+	 *
+	 * Like `UnixStream::recv_fds`
+	 */
+	pub fn recv_fds(&self, buf: &mut [u8], fds: &mut Vec<OwnedFd>) -> Result<Option<usize>, Error>
+		{ recv_fds(&self.fd, buf, fds) }
+}
+
+impl Drop for UnixDatagram {
+	fn drop(&mut self)
+	{
+		if let Some(path) = &self.path { let _ = remove(path); }
+	}
+}
+
+/**
+ * Removes the socket file on `path`, left behind when a listener didn't exit cleanly
+ *
+ * # Errors
+ *
+ * `AccessDenied` without permission on the folder, `ConnectionLost` if there was nothing to remove
+ */
+pub fn remove(path: &str) -> Result<(), Error>
+{
+	let status = unsafe { unix::socket_unlink(path.as_ptr(), path.len()) };
+	if status < 0 { return Err(response(status as isize)) }
+	Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	extern crate std;
+
+	use crate::WResponse;
+	use super::{Address, UnixStream, UnixListener};
+
+	#[test]
+	fn errors_are_sorted_on_every_os()
+	{
+		let name = crate::format!("{}/dirty-test-{}", std::env::temp_dir().display(), std::process::id());
+		let address = if cfg!(target_os = "linux") { Address::Abstract(&name) } else { Address::Path(&name) };
+
+		assert_eq!(UnixStream::connect(address).unwrap_err().kind(), WResponse::ConnectionLost);
+		let _listener = UnixListener::bind(address).unwrap();
+		assert_eq!(UnixListener::bind(address).unwrap_err().kind(), WResponse::ChannelInUse);
+
+		let (mut left, mut right) = UnixStream::pair().unwrap();
+		assert_eq!(right.try_recv(&mut [0; 4]).unwrap(), None);
+		left.send_all(b"ping").unwrap();
+		assert_eq!(right.try_recv(&mut [0; 4]).unwrap(), Some(4));
+	}
+}
@@ -0,0 +1,136 @@
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+use super::{futex, MutexGuard};
+
+/// Lets threads sleep until another thread changes the value behind a `Mutex`
+///
+/// ```rust
+/// let mut ready = pair.0.lock();
+/// while !*ready { ready = pair.1.wait(ready) }
+/// ```
+#[derive(Default)]
+pub struct Condvar {
+	/// Bumped by every notification, sleepers wait for it to change
+	sequence: AtomicU32,
+}
+
+impl Condvar {
+	/// Creates a condition variable nobody waits on
+	#[must_use]
+	pub const fn new() -> Self
+		{ Self { sequence: AtomicU32::new(0) } }
+
+	/// Releases the lock of `guard`, sleeps until notified and takes the lock again
+	/// it can also return on spurious wakes, the condition must be checked again
+	pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T>
+		{ self.wait_timeout(guard, None).0 }
+
+	/// Sleeps while `condition` returns `true`
+	pub fn wait_while<'a, T: ?Sized>(
+		&self,
+		mut guard: MutexGuard<'a, T>,
+		mut condition: impl FnMut(&mut T) -> bool,
+	) -> MutexGuard<'a, T>
+	{
+		while condition(&mut *guard) { guard = self.wait(guard) }
+		guard
+	}
+
+	/// Same as `wait`, but gives up after `timeout`, the `bool` is `false` if the timeout passed
+	pub fn wait_timeout<'a, T: ?Sized>(
+		&self,
+		guard: MutexGuard<'a, T>,
+		timeout: Option<Duration>,
+	) -> (MutexGuard<'a, T>, bool)
+	{
+		// read before unlocking, a notification sent after the unlock changes it and the wait returns
+		let sequence = self.sequence.load(Ordering::Relaxed);
+		let mutex = guard.mutex;
+		drop(guard);
+
+		let notified = futex::wait(&self.sequence, sequence, timeout);
+		(mutex.lock(), notified)
+	}
+
+	/// Wakes one sleeping thread
+	pub fn notify_one(&self)
+	{
+		let _ = self.sequence.fetch_add(1, Ordering::Relaxed);
+		futex::wake_one(&self.sequence);
+	}
+
+	/// Wakes every sleeping thread
+	pub fn notify_all(&self)
+	{
+		let _ = self.sequence.fetch_add(1, Ordering::Relaxed);
+		futex::wake_all(&self.sequence);
+	}
+}
+
+impl fmt::Debug for Condvar {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ f.debug_struct("Condvar").finish_non_exhaustive() }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	extern crate std;
+
+	use alloc::sync::Arc;
+	use core::time::Duration;
+	use super::super::Mutex;
+	use super::Condvar;
+
+	#[test]
+	fn notify_wakes_the_waiter()
+	{
+		let pair = Arc::new((Mutex::new(false), Condvar::new()));
+		let shared = Arc::clone(&pair);
+		let waiter = crate::thread::spawn(move |_| {
+			let (ready, condvar) = &*shared;
+			*condvar.wait_while(ready.lock(), |done| !*done)
+		}).unwrap();
+
+		std::thread::sleep(Duration::from_millis(20));
+		*pair.0.lock() = true;
+		pair.1.notify_one();
+		assert!(waiter.join().unwrap());
+	}
+
+	#[test]
+	fn notify_all_wakes_every_waiter()
+	{
+		let pair = Arc::new((Mutex::new(0u32), Condvar::new()));
+		let waiters: alloc::vec::Vec<_> = (0..4)
+			.map(|_| {
+				let shared = Arc::clone(&pair);
+				crate::thread::spawn(move |_| {
+					let (generation, condvar) = &*shared;
+					let _ = condvar.wait_while(generation.lock(), |current| *current == 0);
+				}).unwrap()
+			})
+			.collect();
+
+		std::thread::sleep(Duration::from_millis(20));
+		*pair.0.lock() = 1;
+		pair.1.notify_all();
+		for waiter in waiters { waiter.join().unwrap() }
+	}
+
+	#[test]
+	fn wait_timeout_gives_up()
+	{
+		let mutex = Mutex::new(());
+		let condvar = Condvar::new();
+		let start = std::time::Instant::now();
+
+		let (guard, notified) = condvar.wait_timeout(mutex.lock(), Some(Duration::from_millis(30)));
+		assert!(!notified);
+		assert!(start.elapsed() >= Duration::from_millis(30));
+		// the lock is held again when the wait returns
+		assert!(mutex.try_lock().is_none());
+		drop(guard);
+	}
+}
@@ -0,0 +1,25 @@
+/*!
+ * `futex(2)` wait and wake, the building block of every lock in here
+ * <https://man7.org/linux/man-pages/man2/futex.2.html>
+ */
+use core::sync::atomic::AtomicU32;
+use core::time::Duration;
+use crate::syscall::{self, Errno, Timespec, FUTEX_PRIVATE_FLAG, FUTEX_WAIT, FUTEX_WAKE};
+
+/// Sleeps while `atomic` is `expected`, returns `false` if `timeout` passed first
+/// it can also return on spurious wakes, callers must check their condition again
+pub(crate) fn wait(atomic: &AtomicU32, expected: u32, timeout: Option<Duration>) -> bool
+{
+	// the futexes are never shared with other processes
+	let timespec = timeout.map(Timespec::from);
+	let ret = unsafe { syscall::futex(atomic.as_ptr(), FUTEX_WAIT | FUTEX_PRIVATE_FLAG, expected, timespec.as_ref()) };
+	ret != Err(Errno::ETIMEDOUT)
+}
+
+/// Wakes one thread waiting on `atomic`
+pub(crate) fn wake_one(atomic: &AtomicU32)
+	{ let _ = unsafe { syscall::futex(atomic.as_ptr(), FUTEX_WAKE | FUTEX_PRIVATE_FLAG, 1, None) }; }
+
+/// Wakes every thread waiting on `atomic`
+pub(crate) fn wake_all(atomic: &AtomicU32)
+	{ let _ = unsafe { syscall::futex(atomic.as_ptr(), FUTEX_WAKE | FUTEX_PRIVATE_FLAG, i32::MAX.cast_unsigned(), None) }; }
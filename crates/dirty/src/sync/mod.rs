@@ -0,0 +1,35 @@
+/*!
+ * Synchronization primitives on top of linux futexes, for threads spawned with `dirty::thread`
+ * <https://man7.org/linux/man-pages/man7/futex.7.html>
+ *
+ * ```rust
+ * let unread = Arc::new(Mutex::new(0));
+ * let (events, receiver) = mpsc::channel();
+ *
+ * let counter = Arc::clone(&unread);
+ * dirty::thread::spawn(move |_| {
+ * 	*counter.lock() += 1;
+ * 	let _ = events.send("new message");
+ * })?;
+ *
+ * debug!("{}: {}", receiver.recv()?, unread.lock());
+ * ```
+ */
+
+/// Raw wait and wake
+mod futex;
+/// Mutual exclusion
+mod mutex;
+/// Readers and writer lock
+mod rwlock;
+/// Condition variables
+mod condvar;
+/// One time initialization
+mod once;
+/// Channels between threads
+pub mod mpsc;
+
+pub use mutex::{Mutex, MutexGuard};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use condvar::Condvar;
+pub use once::Once;
@@ -0,0 +1,360 @@
+/*!
+ * Multi producer, single consumer channels, workers use them to post events to the main loop
+ *
+ * ```rust
+ * let (sender, receiver) = mpsc::channel();
+ * let worker = dirty::thread::spawn(move |_| sender.send(sync_rooms()))?;
+ * let rooms = receiver.recv()?;
+ * ```
+ */
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::fmt;
+use core::time::Duration;
+use crate::syscall::{self, CLOCK_MONOTONIC};
+use crate::{Error, WResponse};
+use super::{Condvar, Mutex};
+
+/// Time since boot, deadlines don't move when the wall clock does
+fn monotonic() -> Duration
+	{ syscall::clock_gettime(CLOCK_MONOTONIC).unwrap_or_default() }
+
+/// The value could not be sent because the `Receiver` is gone, it is given back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// Why `SyncSender::try_send` failed, the value is given back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+	/// The channel is at its bound
+	Full(T),
+	/// The `Receiver` is gone
+	Disconnected(T),
+}
+
+/// State behind the lock
+struct Queue<T> {
+	/// Values waiting for the receiver
+	items: VecDeque<T>,
+	/// Living `Sender`s and `SyncSender`s
+	senders: usize,
+	/// `false` once the `Receiver` was dropped
+	receiver: bool,
+}
+
+/// Shared by both ends of a channel
+struct Channel<T> {
+	/// The queued values
+	queue: Mutex<Queue<T>>,
+	/// Maximum number of queued values, `None` for unbounded channels
+	bound: Option<usize>,
+	/// Notified when a value arrives or the last sender leaves
+	readable: Condvar,
+	/// Notified when a value is taken or the receiver leaves
+	writable: Condvar,
+}
+
+impl<T> Channel<T> {
+	/// A channel with one sender and one receiver
+	fn new(bound: Option<usize>) -> Arc<Self>
+	{
+		Arc::new(Self {
+			queue: Mutex::new(Queue { items: VecDeque::new(), senders: 1, receiver: true }),
+			bound,
+			readable: Condvar::new(),
+			writable: Condvar::new(),
+		})
+	}
+
+	/// Queues `value`, waiting for room if `block` is set
+	fn send(&self, value: T, block: bool) -> Result<(), TrySendError<T>>
+	{
+		let mut queue = self.queue.lock();
+
+		loop {
+			if !queue.receiver { return Err(TrySendError::Disconnected(value)) }
+			if self.bound.is_none_or(|bound| queue.items.len() < bound) { break }
+			if !block { return Err(TrySendError::Full(value)) }
+			queue = self.writable.wait(queue);
+		}
+
+		queue.items.push_back(value);
+		drop(queue);
+		self.readable.notify_one();
+		Ok(())
+	}
+
+	/// Registers one more sender
+	fn add_sender(&self)
+	{
+		let mut queue = self.queue.lock();
+		queue.senders = queue.senders.saturating_add(1);
+	}
+
+	/// Unregisters a sender, the receiver is woken when the last one leaves
+	fn drop_sender(&self)
+	{
+		let mut queue = self.queue.lock();
+		queue.senders = queue.senders.saturating_sub(1);
+		let last = queue.senders == 0;
+		drop(queue);
+		if last { self.readable.notify_all() }
+	}
+}
+
+/// Creates a channel without bound, `send` never blocks
+#[must_use]
+pub fn channel<T>() -> (Sender<T>, Receiver<T>)
+{
+	let channel = Channel::new(None);
+	(Sender { channel: Arc::clone(&channel) }, Receiver { channel })
+}
+
+/// Creates a channel that holds at most `bound` values, `send` blocks while it is full
+#[must_use]
+pub fn sync_channel<T>(bound: usize) -> (SyncSender<T>, Receiver<T>)
+{
+	let channel = Channel::new(Some(bound.max(1)));
+	(SyncSender { channel: Arc::clone(&channel) }, Receiver { channel })
+}
+
+/// Sending end of a `channel`, it can be cloned for each worker
+pub struct Sender<T> {
+	/// Shared with the receiver
+	channel: Arc<Channel<T>>,
+}
+
+impl<T> Sender<T> {
+	/**
+	 * Queues `value` for the receiver
+	 *
+	 * # Errors
+	 *
+	 * Gives `value` back if the `Receiver` was dropped
+	 */
+	pub fn send(&self, value: T) -> Result<(), SendError<T>>
+	{
+		self.channel.send(value, false).map_err(|e| match e {
+			TrySendError::Full(v) | TrySendError::Disconnected(v) => SendError(v),
+		})
+	}
+}
+
+impl<T> Clone for Sender<T> {
+	fn clone(&self) -> Self
+	{
+		self.channel.add_sender();
+		Self { channel: Arc::clone(&self.channel) }
+	}
+}
+
+impl<T> Drop for Sender<T> {
+	fn drop(&mut self)
+		{ self.channel.drop_sender() }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ f.debug_struct("Sender").finish_non_exhaustive() }
+}
+
+/// Sending end of a `sync_channel`, it can be cloned for each worker
+pub struct SyncSender<T> {
+	/// Shared with the receiver
+	channel: Arc<Channel<T>>,
+}
+
+impl<T> SyncSender<T> {
+	/**
+	 * Queues `value`, waiting while the channel is full
+	 *
+	 * # Errors
+	 *
+	 * Gives `value` back if the `Receiver` was dropped
+	 */
+	pub fn send(&self, value: T) -> Result<(), SendError<T>>
+	{
+		self.channel.send(value, true).map_err(|e| match e {
+			TrySendError::Full(v) | TrySendError::Disconnected(v) => SendError(v),
+		})
+	}
+
+	/**
+	 * Queues `value` if there is room
+	 *
+	 * # Errors
+	 *
+	 * Gives `value` back if the channel is full or the `Receiver` was dropped
+	 */
+	pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>>
+		{ self.channel.send(value, false) }
+}
+
+impl<T> Clone for SyncSender<T> {
+	fn clone(&self) -> Self
+	{
+		self.channel.add_sender();
+		Self { channel: Arc::clone(&self.channel) }
+	}
+}
+
+impl<T> Drop for SyncSender<T> {
+	fn drop(&mut self)
+		{ self.channel.drop_sender() }
+}
+
+impl<T> fmt::Debug for SyncSender<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ f.debug_struct("SyncSender").finish_non_exhaustive() }
+}
+
+/// Receiving end of a channel
+pub struct Receiver<T> {
+	/// Shared with the senders
+	channel: Arc<Channel<T>>,
+}
+
+impl<T> Receiver<T> {
+	/**
+	 * Waits for a value
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the channel is empty and every sender was dropped
+	 */
+	pub fn recv(&self) -> Result<T, Error>
+	{
+		self.recv_timeout(None)?.ok_or(WResponse::ConnectionLost.into())
+	}
+
+	/**
+	 * Takes a value if there is one queued, never blocks
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the channel is empty and every sender was dropped
+	 */
+	pub fn try_recv(&self) -> Result<Option<T>, Error>
+		{ self.recv_timeout(Some(Duration::ZERO)) }
+
+	/**
+	 * Waits for a value up to `timeout`, `None` waits forever
+	 * returns `Ok(None)` if the timeout passed
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the channel is empty and every sender was dropped
+	 */
+	pub fn recv_timeout(&self, timeout: Option<Duration>) -> Result<Option<T>, Error>
+	{
+		// `None` waits forever, also when the deadline doesn't fit
+		let deadline = timeout.and_then(|t| monotonic().checked_add(t));
+		let mut queue = self.channel.queue.lock();
+
+		loop {
+			if let Some(value) = queue.items.pop_front() {
+				drop(queue);
+				self.channel.writable.notify_one();
+				return Ok(Some(value));
+			}
+
+			if queue.senders == 0 { return Err(WResponse::ConnectionLost.into()) }
+			if timeout == Some(Duration::ZERO) { return Ok(None) }
+
+			// a spurious wake only waits for what is left until the deadline
+			let remaining = deadline.map(|end| end.saturating_sub(monotonic()));
+			if remaining == Some(Duration::ZERO) { return Ok(None) }
+			queue = self.channel.readable.wait_timeout(queue, remaining).0;
+		}
+	}
+
+	/// Iterates over the values until every sender was dropped
+	pub fn iter(&self) -> impl Iterator<Item = T> + '_
+		{ core::iter::from_fn(|| self.recv().ok()) }
+}
+
+impl<T> Drop for Receiver<T> {
+	fn drop(&mut self)
+	{
+		self.channel.queue.lock().receiver = false;
+		self.channel.writable.notify_all();
+	}
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ f.debug_struct("Receiver").finish_non_exhaustive() }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	extern crate std;
+
+	use alloc::vec::Vec;
+	use core::time::Duration;
+	use crate::WResponse;
+	use super::{channel, monotonic, sync_channel, SendError, TrySendError};
+
+	#[test]
+	fn values_arrive_in_order_from_another_thread()
+	{
+		let (sender, receiver) = channel();
+		let worker = crate::thread::spawn(move |_| for i in 0..100u32 { sender.send(i).unwrap() }).unwrap();
+
+		assert_eq!(receiver.iter().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+		worker.join().unwrap();
+	}
+
+	#[test]
+	fn recv_timeout_waits_until_the_deadline()
+	{
+		let (sender, receiver) = channel::<u32>();
+		let start = monotonic();
+		assert_eq!(receiver.recv_timeout(Some(Duration::from_millis(30))).unwrap(), None);
+		assert!(monotonic().saturating_sub(start) >= Duration::from_millis(30));
+
+		let worker = crate::thread::spawn(move |_| {
+			std::thread::sleep(Duration::from_millis(20));
+			sender.send(5).unwrap();
+		}).unwrap();
+		assert_eq!(receiver.recv_timeout(Some(Duration::from_secs(5))).unwrap(), Some(5));
+		worker.join().unwrap();
+	}
+
+	#[test]
+	fn receiver_sees_the_last_sender_leave()
+	{
+		let (sender, receiver) = channel();
+		let second = sender.clone();
+		sender.send(1).unwrap();
+		drop(sender);
+
+		let worker = crate::thread::spawn(move |_| {
+			std::thread::sleep(Duration::from_millis(20));
+			drop(second);
+		}).unwrap();
+
+		// queued values are still delivered, then the blocked receiver wakes up with the error
+		assert_eq!(receiver.recv().unwrap(), 1);
+		assert_eq!(receiver.recv().unwrap_err().kind(), WResponse::ConnectionLost);
+		worker.join().unwrap();
+	}
+
+	#[test]
+	fn senders_see_the_receiver_leave()
+	{
+		let (sender, receiver) = sync_channel(1);
+		sender.send(1).unwrap();
+		assert_eq!(sender.try_send(2), Err(TrySendError::Full(2)));
+
+		// a sender blocked on the full channel is woken when the receiver goes away
+		let blocked = sender.clone();
+		let worker = crate::thread::spawn(move |_| blocked.send(3)).unwrap();
+		std::thread::sleep(Duration::from_millis(20));
+		drop(receiver);
+
+		assert_eq!(worker.join().unwrap(), Err(SendError(3)));
+		assert_eq!(sender.try_send(4), Err(TrySendError::Disconnected(4)));
+	}
+}
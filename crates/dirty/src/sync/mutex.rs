@@ -0,0 +1,167 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+use super::futex;
+
+/// Nobody holds the lock
+const UNLOCKED: u32 = 0;
+/// Locked, nobody is waiting
+const LOCKED: u32 = 1;
+/// Locked, and someone may be sleeping on the futex
+const CONTENDED: u32 = 2;
+
+/// Mutual exclusion lock, a locked mutex costs no syscall unless there is contention
+///
+/// ```rust
+/// let counter = Arc::new(Mutex::new(0));
+/// *counter.lock() += 1;
+/// ```
+pub struct Mutex<T: ?Sized> {
+	/// `UNLOCKED`, `LOCKED` or `CONTENDED`
+	pub(super) state: AtomicU32,
+	/// The protected value
+	value: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+	/// Creates an unlocked mutex
+	pub const fn new(value: T) -> Self
+		{ Self { state: AtomicU32::new(UNLOCKED), value: UnsafeCell::new(value) } }
+
+	/// Takes the value out of the mutex
+	pub fn into_inner(self) -> T
+		{ self.value.into_inner() }
+}
+
+impl<T: ?Sized> Mutex<T> {
+	/// Waits until the lock is free and takes it, it is released when the guard is dropped
+	pub fn lock(&self) -> MutexGuard<'_, T>
+	{
+		if self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed).is_err() {
+			self.lock_contended();
+		}
+		MutexGuard { mutex: self }
+	}
+
+	/// Takes the lock if it is free, returns `None` otherwise
+	pub fn try_lock(&self) -> Option<MutexGuard<'_, T>>
+	{
+		self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+			.ok()
+			.map(|_| MutexGuard { mutex: self })
+	}
+
+	/// Access without locking, the borrow checker already proves nobody else has the mutex
+	pub fn get_mut(&mut self) -> &mut T
+		{ self.value.get_mut() }
+
+	/// Slow path of `lock`, marks the lock as contended and sleeps until it is released
+	fn lock_contended(&self)
+	{
+		// a short spin avoids the syscall when the lock is held for a few instructions
+		for _ in 0..100 {
+			if self.state.load(Ordering::Relaxed) == UNLOCKED
+				&& self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed).is_ok()
+			{
+				return;
+			}
+			core::hint::spin_loop();
+		}
+
+		// whoever takes the lock from here can't know if someone else is still sleeping
+		while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+			let _ = futex::wait(&self.state, CONTENDED, None);
+		}
+	}
+
+	/// Releases the lock, waking one sleeper if there may be any
+	pub(super) fn unlock(&self)
+	{
+		if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+			futex::wake_one(&self.state);
+		}
+	}
+}
+
+impl<T: Default> Default for Mutex<T> {
+	fn default() -> Self { Self::new(T::default()) }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		let mut debug = f.debug_struct("Mutex");
+		let _ = match self.try_lock() {
+			Some(guard) => debug.field("value", &&*guard),
+			None => debug.field("value", &format_args!("<locked>")),
+		};
+		debug.finish()
+	}
+}
+
+/// Access to the value of a locked `Mutex`, the lock is released on drop
+pub struct MutexGuard<'a, T: ?Sized> {
+	/// The locked mutex
+	pub(super) mutex: &'a Mutex<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for MutexGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T
+		{ unsafe { &*self.mutex.value.get() } }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+	fn deref_mut(&mut self) -> &mut T
+		{ unsafe { &mut *self.mutex.value.get() } }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+	fn drop(&mut self)
+		{ self.mutex.unlock() }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ fmt::Debug::fmt(&**self, f) }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	use alloc::sync::Arc;
+	use alloc::vec::Vec;
+	use super::Mutex;
+
+	#[test]
+	fn contended_increments_are_not_lost()
+	{
+		let counter = Arc::new(Mutex::new(0u32));
+		let workers: Vec<_> = (0..8)
+			.map(|_| {
+				let shared = Arc::clone(&counter);
+				crate::thread::spawn(move |_| for _ in 0..10_000 { *shared.lock() += 1 }).unwrap()
+			})
+			.collect();
+
+		for worker in workers { worker.join().unwrap() }
+		assert_eq!(*counter.lock(), 80_000);
+	}
+
+	#[test]
+	fn try_lock_fails_while_held()
+	{
+		let mutex = Mutex::new(());
+		let guard = mutex.lock();
+		assert!(mutex.try_lock().is_none());
+		drop(guard);
+		assert!(mutex.try_lock().is_some());
+	}
+}
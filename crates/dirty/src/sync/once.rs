@@ -0,0 +1,106 @@
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+use super::futex;
+
+/// `call_once` was never called
+const INCOMPLETE: u32 = 0;
+/// A thread is running the closure
+const RUNNING: u32 = 1;
+/// A thread is running the closure and others are sleeping until it finishes
+const QUEUED: u32 = 2;
+/// The closure returned
+const COMPLETE: u32 = 3;
+
+/// Runs a closure only once, even if many threads ask at the same time
+///
+/// ```rust
+/// static INIT: Once = Once::new();
+/// INIT.call_once(|| load_config());
+/// ```
+pub struct Once {
+	/// `INCOMPLETE`, `RUNNING`, `QUEUED` or `COMPLETE`
+	state: AtomicU32,
+}
+
+impl Once {
+	/// A `Once` that wasn't called yet
+	#[must_use]
+	pub const fn new() -> Self
+		{ Self { state: AtomicU32::new(INCOMPLETE) } }
+
+	/// `true` once a closure given to `call_once` returned
+	pub fn is_completed(&self) -> bool
+		{ self.state.load(Ordering::Acquire) == COMPLETE }
+
+	/// Runs `function` if no other call did, otherwise waits for that call to finish
+	pub fn call_once(&self, function: impl FnOnce())
+	{
+		let mut state = self.state.load(Ordering::Acquire);
+
+		loop {
+			match state {
+				COMPLETE => return,
+				INCOMPLETE => {
+					if let Err(current) = self.state.compare_exchange(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Acquire) {
+						state = current;
+						continue;
+					}
+
+					function();
+					if self.state.swap(COMPLETE, Ordering::Release) == QUEUED { futex::wake_all(&self.state) }
+					return;
+				},
+				_ => {
+					// tells the running thread that someone needs a wake
+					if state == RUNNING
+						&& let Err(current) = self.state.compare_exchange(RUNNING, QUEUED, Ordering::Relaxed, Ordering::Acquire)
+					{
+						state = current;
+						continue;
+					}
+
+					let _ = futex::wait(&self.state, QUEUED, None);
+					state = self.state.load(Ordering::Acquire);
+				},
+			}
+		}
+	}
+}
+
+impl Default for Once {
+	fn default() -> Self { Self::new() }
+}
+
+impl fmt::Debug for Once {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ f.debug_struct("Once").field("completed", &self.is_completed()).finish() }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	use alloc::sync::Arc;
+	use alloc::vec::Vec;
+	use core::sync::atomic::{AtomicU32, Ordering};
+	use super::Once;
+
+	#[test]
+	fn runs_once_across_threads()
+	{
+		let once = Arc::new(Once::new());
+		let calls = Arc::new(AtomicU32::new(0));
+		let workers: Vec<_> = (0..8)
+			.map(|_| {
+				let (shared, counted) = (Arc::clone(&once), Arc::clone(&calls));
+				// everyone returns after the closure ran, not just the thread that ran it
+				crate::thread::spawn(move |_| {
+					shared.call_once(|| { let _ = counted.fetch_add(1, Ordering::Relaxed); });
+					counted.load(Ordering::Relaxed)
+				}).unwrap()
+			})
+			.collect();
+
+		for worker in workers { assert_eq!(worker.join().unwrap(), 1) }
+		assert!(once.is_completed());
+	}
+}
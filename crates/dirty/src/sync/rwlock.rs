@@ -0,0 +1,233 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+use super::futex;
+
+/// `state` while a writer holds the lock, any other value is the number of readers
+const WRITE_LOCKED: u32 = u32::MAX;
+
+/// Lock with many readers or a single writer
+///
+/// ```rust
+/// let rooms = RwLock::new(Vec::new());
+/// rooms.write().push(room);
+/// let count = rooms.read().len();
+/// ```
+pub struct RwLock<T: ?Sized> {
+	/// Number of readers, or `WRITE_LOCKED`
+	state: AtomicU32,
+	/// Threads sleeping on `state`, unlocking only makes a syscall when there are any
+	sleepers: AtomicU32,
+	/// The protected value
+	value: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+	/// Creates an unlocked lock
+	pub const fn new(value: T) -> Self
+	{
+		Self {
+			state: AtomicU32::new(0),
+			sleepers: AtomicU32::new(0),
+			value: UnsafeCell::new(value),
+		}
+	}
+
+	/// Takes the value out of the lock
+	pub fn into_inner(self) -> T
+		{ self.value.into_inner() }
+}
+
+impl<T: ?Sized> RwLock<T> {
+	/// Waits until no writer holds the lock, other readers may hold it at the same time
+	pub fn read(&self) -> RwLockReadGuard<'_, T>
+	{
+		loop {
+			if let Some(guard) = self.try_read() { return guard }
+			self.sleep();
+		}
+	}
+
+	/// Takes a read lock if no writer holds it
+	pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>>
+	{
+		let mut state = self.state.load(Ordering::Relaxed);
+		// `WRITE_LOCKED - 1` readers would look like a writer
+		while state < WRITE_LOCKED.wrapping_sub(1) {
+			match self.state.compare_exchange_weak(state, state.wrapping_add(1), Ordering::Acquire, Ordering::Relaxed) {
+				Ok(_) => return Some(RwLockReadGuard { lock: self }),
+				Err(current) => state = current,
+			}
+		}
+		None
+	}
+
+	/// Waits until nobody holds the lock and takes it
+	pub fn write(&self) -> RwLockWriteGuard<'_, T>
+	{
+		loop {
+			if let Some(guard) = self.try_write() { return guard }
+			self.sleep();
+		}
+	}
+
+	/// Takes the write lock if nobody holds it
+	pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>>
+	{
+		self.state.compare_exchange(0, WRITE_LOCKED, Ordering::Acquire, Ordering::Relaxed)
+			.ok()
+			.map(|_| RwLockWriteGuard { lock: self })
+	}
+
+	/// Access without locking, the borrow checker already proves nobody else has the lock
+	pub fn get_mut(&mut self) -> &mut T
+		{ self.value.get_mut() }
+
+	/// Sleeps until `state` changes
+	fn sleep(&self)
+	{
+		let _ = self.sleepers.fetch_add(1, Ordering::SeqCst);
+		let state = self.state.load(Ordering::SeqCst);
+
+		// the lock may have been released between the failed try and now
+		if state != 0 { let _ = futex::wait(&self.state, state, None); }
+		let _ = self.sleepers.fetch_sub(1, Ordering::SeqCst);
+	}
+
+	/// Wakes everyone, readers can share the lock and a writer retries
+	fn wake(&self)
+	{
+		if self.sleepers.load(Ordering::SeqCst) != 0 { futex::wake_all(&self.state) }
+	}
+}
+
+impl<T: Default> Default for RwLock<T> {
+	fn default() -> Self { Self::new(T::default()) }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		let mut debug = f.debug_struct("RwLock");
+		let _ = match self.try_read() {
+			Some(guard) => debug.field("value", &&*guard),
+			None => debug.field("value", &format_args!("<locked>")),
+		};
+		debug.finish()
+	}
+}
+
+/// Shared access to the value of a `RwLock`
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+	/// The locked lock
+	lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for RwLockReadGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T
+		{ unsafe { &*self.lock.value.get() } }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+	fn drop(&mut self)
+	{
+		// only the last reader can let a writer in
+		if self.lock.state.fetch_sub(1, Ordering::SeqCst) == 1 { self.lock.wake() }
+	}
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ fmt::Debug::fmt(&**self, f) }
+}
+
+/// Exclusive access to the value of a `RwLock`
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+	/// The locked lock
+	lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for RwLockWriteGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T
+		{ unsafe { &*self.lock.value.get() } }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+	fn deref_mut(&mut self) -> &mut T
+		{ unsafe { &mut *self.lock.value.get() } }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+	fn drop(&mut self)
+	{
+		self.lock.state.store(0, Ordering::SeqCst);
+		self.lock.wake();
+	}
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+		{ fmt::Debug::fmt(&**self, f) }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+	extern crate std;
+
+	use alloc::sync::Arc;
+	use core::time::Duration;
+	use super::RwLock;
+
+	#[test]
+	fn writer_waits_for_readers()
+	{
+		let lock = Arc::new(RwLock::new(0u32));
+		let first = lock.read();
+		let second = lock.try_read().unwrap();
+		assert!(lock.try_write().is_none());
+
+		let shared = Arc::clone(&lock);
+		let writer = crate::thread::spawn(move |_| *shared.write() = 1).unwrap();
+
+		// the writer has to sleep as long as a reader is left
+		std::thread::sleep(Duration::from_millis(50));
+		assert!(!writer.is_finished());
+		drop(first);
+		std::thread::sleep(Duration::from_millis(50));
+		assert!(!writer.is_finished());
+		assert_eq!(*second, 0);
+		drop(second);
+
+		writer.join().unwrap();
+		assert_eq!(*lock.read(), 1);
+	}
+
+	#[test]
+	fn readers_wait_for_the_writer()
+	{
+		let lock = Arc::new(RwLock::new(0u32));
+		let mut guard = lock.write();
+		assert!(lock.try_read().is_none());
+
+		let shared = Arc::clone(&lock);
+		let reader = crate::thread::spawn(move |_| *shared.read()).unwrap();
+		std::thread::sleep(Duration::from_millis(50));
+		*guard = 7;
+		drop(guard);
+
+		assert_eq!(reader.join().unwrap(), 7);
+	}
+}
@@ -0,0 +1,451 @@
+/*!
+ * Typed wrappers over the linux syscalls the crate needs, without going through libc
+ * every call returns the `Errno` set by the kernel on failure and is never retried on `EINTR`
+ */
+use core::ffi::CStr;
+use core::time::Duration;
+use super::Errno;
+use super::raw::{nr, syscall3, syscall6};
+
+/// `openat` relative to the current directory
+pub const AT_FDCWD: i32 = -100;
+/// Open for reading only
+pub const O_RDONLY: i32 = 0;
+/// Open for writing only
+pub const O_WRONLY: i32 = 1;
+/// Open for reading and writing
+pub const O_RDWR: i32 = 2;
+/// Create the file if it doesn't exist, `mode` is used then
+pub const O_CREAT: i32 = 0o100;
+/// Fail if `O_CREAT` finds an existing file
+pub const O_EXCL: i32 = 0o200;
+/// Empty the file
+pub const O_TRUNC: i32 = 0o1000;
+/// Every write goes to the end of the file
+pub const O_APPEND: i32 = 0o2000;
+/// Reads and writes return `EAGAIN` instead of waiting
+pub const O_NONBLOCK: i32 = 0o4000;
+/// Closed when the process runs `exec`
+pub const O_CLOEXEC: i32 = 0o2_000_000;
+/// Fails unless the path is a directory, the value differs between architectures
+#[cfg(target_arch = "x86_64")]
+pub const O_DIRECTORY: i32 = 0o200_000;
+/// Fails unless the path is a directory, the value differs between architectures
+#[cfg(target_arch = "aarch64")]
+pub const O_DIRECTORY: i32 = 0o40_000;
+
+/// `lseek` from the start of the file
+pub const SEEK_SET: i32 = 0;
+/// `lseek` from the current position
+pub const SEEK_CUR: i32 = 1;
+/// `lseek` from the end of the file
+pub const SEEK_END: i32 = 2;
+
+/// `ioctl` that reads the terminal settings, only terminals know it
+pub const TCGETS: usize = 0x5401;
+
+/// `unlinkat` removes an empty directory instead of a file
+pub const AT_REMOVEDIR: i32 = 0x200;
+/// `renameat2` fails with `EEXIST` instead of replacing the target
+pub const RENAME_NOREPLACE: u32 = 1;
+
+/// `d_type` of a directory
+pub const DT_DIR: u8 = 4;
+/// `d_type` of a regular file
+pub const DT_REG: u8 = 8;
+/// `d_type` of a symbolic link
+pub const DT_LNK: u8 = 10;
+
+/// Pages can be read
+pub const PROT_READ: i32 = 1;
+/// Pages can be written
+pub const PROT_WRITE: i32 = 2;
+/// Changes are seen by every process mapping the same file
+pub const MAP_SHARED: i32 = 1;
+/// Changes stay in this process
+pub const MAP_PRIVATE: i32 = 2;
+/// Memory not backed by a file, `fd` must be `-1`
+pub const MAP_ANONYMOUS: i32 = 0x20;
+
+/// Closed when the process runs `exec`
+pub const MFD_CLOEXEC: u32 = 1;
+/// Allows `F_ADD_SEALS`, a sealed file can be trusted by the compositor
+pub const MFD_ALLOW_SEALING: u32 = 2;
+
+/// `fcntl` command adding seals to a `memfd`
+pub const F_ADD_SEALS: i32 = 1033;
+/// `fcntl` command returning the seals of a `memfd`
+pub const F_GET_SEALS: i32 = 1034;
+/// No more seals can be added
+pub const F_SEAL_SEAL: i32 = 1;
+/// The file can't get smaller, so mappings of it never fault
+pub const F_SEAL_SHRINK: i32 = 2;
+/// The file can't get bigger
+pub const F_SEAL_GROW: i32 = 4;
+/// The contents can't change
+pub const F_SEAL_WRITE: i32 = 8;
+
+/// Wall clock time, it can jump
+pub const CLOCK_REALTIME: i32 = 0;
+/// Time since boot without suspend, it never goes back
+pub const CLOCK_MONOTONIC: i32 = 1;
+
+/// Return `EAGAIN` instead of waiting for the entropy pool
+pub const GRND_NONBLOCK: u32 = 1;
+
+/// Sleep while the futex holds the expected value
+pub const FUTEX_WAIT: i32 = 0;
+/// Wake up to `val` sleepers
+pub const FUTEX_WAKE: i32 = 1;
+/// The futex is not shared with other processes, the kernel skips the page lookup
+pub const FUTEX_PRIVATE_FLAG: i32 = 128;
+
+/// Exclusive lock for `flock`
+pub const LOCK_EX: i32 = 2;
+/// Fails with `EAGAIN` instead of waiting for the lock
+pub const LOCK_NB: i32 = 4;
+/// Releases the lock
+pub const LOCK_UN: i32 = 8;
+
+/// `struct timespec` of the kernel
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Timespec {
+	/// Whole seconds
+	pub tv_sec: i64,
+	/// Nanoseconds, below one second
+	pub tv_nsec: i64,
+}
+
+impl From<Duration> for Timespec {
+	fn from(duration: Duration) -> Self
+	{
+		Self {
+			tv_sec: i64::try_from(duration.as_secs()).unwrap_or(i64::MAX),
+			tv_nsec: i64::from(duration.subsec_nanos()),
+		}
+	}
+}
+
+impl From<Timespec> for Duration {
+	fn from(time: Timespec) -> Self
+	{
+		let secs = u64::try_from(time.tv_sec).unwrap_or_default();
+		let nanos = u32::try_from(time.tv_nsec).unwrap_or_default();
+		Duration::new(secs, nanos)
+	}
+}
+
+/// Turns a raw result into the returned value or the errno
+fn result(ret: isize) -> Result<usize, Errno>
+{
+	if (-4095..0).contains(&ret) {
+		return Err(Errno(i32::try_from(ret.unsigned_abs()).unwrap_or_default()));
+	}
+	Ok(ret.cast_unsigned())
+}
+
+/// Turns a raw result into a file descriptor
+fn descriptor(ret: isize) -> Result<i32, Errno>
+	{ result(ret).and_then(|fd| i32::try_from(fd).map_err(|_| Errno::EBADF)) }
+
+/// Passes an `int` argument, the kernel only reads the low 32 bits
+fn int(value: i32) -> usize
+	{ usize::try_from(value.cast_unsigned()).unwrap_or_default() }
+
+/**
+ * Reads up to `buf.len()` bytes from `fd`, `0` means end of file
+ *
+ * # Errors
+ *
+ * `EAGAIN` on a non blocking descriptor with nothing to read, `EBADF`, `EINTR`...
+ */
+pub fn read(fd: i32, buf: &mut [u8]) -> Result<usize, Errno>
+	{ result(unsafe { syscall3(nr::READ, int(fd), buf.as_mut_ptr().expose_provenance(), buf.len()) }) }
+
+/**
+ * Writes part of `buf` to `fd`, returns how many bytes were written
+ *
+ * # Errors
+ *
+ * `EAGAIN` on a full non blocking descriptor, `EPIPE`, `EBADF`, `EINTR`...
+ */
+pub fn write(fd: i32, buf: &[u8]) -> Result<usize, Errno>
+	{ result(unsafe { syscall3(nr::WRITE, int(fd), buf.as_ptr().expose_provenance(), buf.len()) }) }
+
+/**
+ * Writes all of `buf`, repeating interrupted and partial writes
+ *
+ * # Errors
+ *
+ * Like `write`, except for `EINTR`
+ */
+pub fn write_all(fd: i32, mut buf: &[u8]) -> Result<(), Errno>
+{
+	while !buf.is_empty() {
+		match write(fd, buf) {
+			Ok(0) => return Err(Errno::EIO),
+			Ok(written) => buf = buf.get(written..).unwrap_or_default(),
+			Err(Errno::EINTR) => {},
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(())
+}
+
+/**
+ * Opens `path`, relative to the directory `dirfd` or to the working directory with `AT_FDCWD`
+ *
+ * # Errors
+ *
+ * `ENOENT` if it doesn't exist, `EACCES` without permission, `EMFILE`...
+ */
+pub fn openat(dirfd: i32, path: &CStr, flags: i32, mode: u32) -> Result<i32, Errno>
+{
+	let permissions = usize::try_from(mode).unwrap_or_default();
+	let path_ptr = path.as_ptr().expose_provenance();
+	descriptor(unsafe { syscall6(nr::OPENAT, int(dirfd), path_ptr, int(flags), permissions, 0, 0) })
+}
+
+/**
+ * Closes `fd`, it must not be retried even on `EINTR`
+ *
+ * # Errors
+ *
+ * `EBADF` if it wasn't open
+ */
+pub fn close(fd: i32) -> Result<(), Errno>
+	{ result(unsafe { syscall3(nr::CLOSE, int(fd), 0, 0) }).map(|_| ()) }
+
+/**
+ * Flushes the data and metadata of `fd` to the disk
+ *
+ * # Errors
+ *
+ * `EIO` if the disk failed, `EROFS` or `EINVAL` if `fd` can't be synced
+ */
+pub fn fsync(fd: i32) -> Result<(), Errno>
+	{ result(unsafe { syscall3(nr::FSYNC, int(fd), 0, 0) }).map(|_| ()) }
+
+/**
+ * Moves the position of `fd` to `offset` from `whence`, like `SEEK_END`, returns the new position
+ *
+ * # Errors
+ *
+ * `ESPIPE` on pipes and sockets, `EINVAL` for a negative position
+ */
+pub fn lseek(fd: i32, offset: i64, whence: i32) -> Result<u64, Errno>
+{
+	let ret = unsafe { syscall3(nr::LSEEK, int(fd), isize::try_from(offset).unwrap_or(isize::MIN).cast_unsigned(), int(whence)) };
+	result(ret).map(|position| position as u64)
+}
+
+/// `true` if `fd` is a terminal
+#[must_use]
+pub fn isatty(fd: i32) -> bool
+{
+	// `struct termios` is 60 bytes on linux, the buffer is big enough for every architecture
+	let mut termios = [0u8; 64];
+	unsafe { ioctl(fd, TCGETS, termios.as_mut_ptr().expose_provenance()) }.is_ok()
+}
+
+/**
+ * Creates the directory `path` with the permissions `mode`, relative to `dirfd` like `openat`
+ *
+ * # Errors
+ *
+ * `EEXIST` if it is already there, `ENOENT` if the parent is missing, `EACCES`...
+ */
+pub fn mkdirat(dirfd: i32, path: &CStr, mode: u32) -> Result<(), Errno>
+{
+	let permissions = usize::try_from(mode).unwrap_or_default();
+	result(unsafe { syscall3(nr::MKDIRAT, int(dirfd), path.as_ptr().expose_provenance(), permissions) }).map(|_| ())
+}
+
+/**
+ * Removes the file `path`, or the empty directory with `AT_REMOVEDIR`
+ *
+ * # Errors
+ *
+ * `ENOENT`, `EISDIR` for a directory without `AT_REMOVEDIR`, `ENOTEMPTY`...
+ */
+pub fn unlinkat(dirfd: i32, path: &CStr, flags: i32) -> Result<(), Errno>
+	{ result(unsafe { syscall3(nr::UNLINKAT, int(dirfd), path.as_ptr().expose_provenance(), int(flags)) }).map(|_| ()) }
+
+/**
+ * Renames `from` to `to` in one step, the target is replaced unless `RENAME_NOREPLACE` is given
+ *
+ * # Errors
+ *
+ * `EXDEV` across filesystems, `EEXIST` with `RENAME_NOREPLACE`, `ENOENT`...
+ */
+pub fn renameat2(from_dirfd: i32, from: &CStr, to_dirfd: i32, to: &CStr, flags: u32) -> Result<(), Errno>
+{
+	let (from_ptr, to_ptr) = (from.as_ptr().expose_provenance(), to.as_ptr().expose_provenance());
+	let raw_flags = usize::try_from(flags).unwrap_or_default();
+	result(unsafe { syscall6(nr::RENAMEAT2, int(from_dirfd), from_ptr, int(to_dirfd), to_ptr, raw_flags, 0) }).map(|_| ())
+}
+
+/**
+ * Reads the next entries of the directory `fd` into `buf` as `struct linux_dirent64`,
+ * returns how many bytes were filled, `0` at the end
+ *
+ * # Errors
+ *
+ * `ENOTDIR` if `fd` isn't a directory, `EINVAL` if `buf` can't hold one entry
+ */
+pub fn getdents64(fd: i32, buf: &mut [u8]) -> Result<usize, Errno>
+	{ result(unsafe { syscall3(nr::GETDENTS64, int(fd), buf.as_mut_ptr().expose_provenance(), buf.len()) }) }
+
+/**
+ * Maps `len` bytes of `fd` from `offset`, or anonymous memory with `MAP_ANONYMOUS`
+ *
+ * # Errors
+ *
+ * `ENOMEM`, `EACCES` if `prot` doesn't match how `fd` was opened, `EINVAL`...
+ *
+ * # Safety
+ *
+ * `addr` is only a hint unless `MAP_FIXED` is used, which can replace existing mappings
+ */
+pub unsafe fn mmap(addr: *mut u8, len: usize, prot: i32, flags: i32, fd: i32, offset: usize) -> Result<*mut u8, Errno>
+{
+	let ret = unsafe { syscall6(nr::MMAP, addr.expose_provenance(), len, int(prot), int(flags), int(fd), offset) };
+	result(ret).map(core::ptr::with_exposed_provenance_mut)
+}
+
+/**
+ * Unmaps memory returned by `mmap`
+ *
+ * # Errors
+ *
+ * `EINVAL` if `addr` isn't page aligned
+ *
+ * # Safety
+ *
+ * Nothing may use the memory afterwards
+ */
+pub unsafe fn munmap(addr: *mut u8, len: usize) -> Result<(), Errno>
+	{ result(unsafe { syscall3(nr::MUNMAP, addr.expose_provenance(), len, 0) }).map(|_| ()) }
+
+/**
+ * Creates an anonymous file living in memory, `name` only shows up in `/proc/self/fd`
+ *
+ * # Errors
+ *
+ * `EMFILE`, `ENOSYS` on kernels older than 3.17
+ */
+pub fn memfd_create(name: &CStr, flags: u32) -> Result<i32, Errno>
+{
+	let raw_flags = usize::try_from(flags).unwrap_or_default();
+	descriptor(unsafe { syscall3(nr::MEMFD_CREATE, name.as_ptr().expose_provenance(), raw_flags, 0) })
+}
+
+/**
+ * Sets the size of `fd` to `len`, new bytes are zeroed
+ *
+ * # Errors
+ *
+ * `EPERM` if a seal forbids it, `EINVAL` if `fd` isn't a writable file
+ */
+pub fn ftruncate(fd: i32, len: usize) -> Result<(), Errno>
+	{ result(unsafe { syscall3(nr::FTRUNCATE, int(fd), len, 0) }).map(|_| ()) }
+
+/**
+ * Runs `cmd` on `fd` with an integer argument, like `F_ADD_SEALS`
+ *
+ * # Errors
+ *
+ * `EINVAL` for an unknown command, `EPERM` when adding seals to a sealed file...
+ */
+pub fn fcntl(fd: i32, cmd: i32, arg: i32) -> Result<usize, Errno>
+	{ result(unsafe { syscall3(nr::FCNTL, int(fd), int(cmd), int(arg)) }) }
+
+/**
+ * Takes or releases an advisory lock on the whole file, see `LOCK_EX`
+ * the lock goes away with the last descriptor of the open file
+ *
+ * # Errors
+ *
+ * `EAGAIN` with `LOCK_NB` if another open file holds it, `EINTR` while waiting
+ */
+pub fn flock(fd: i32, operation: i32) -> Result<(), Errno>
+	{ result(unsafe { syscall3(nr::FLOCK, int(fd), int(operation), 0) }).map(|_| ()) }
+
+/**
+ * Reads `clock`, like `CLOCK_MONOTONIC`
+ * this is a real syscall, libc would use the vDSO instead
+ *
+ * # Errors
+ *
+ * `EINVAL` for an unknown clock
+ */
+pub fn clock_gettime(clock: i32) -> Result<Duration, Errno>
+{
+	let mut time = Timespec::default();
+	let ret = unsafe { syscall3(nr::CLOCK_GETTIME, int(clock), (&raw mut time).expose_provenance(), 0) };
+	result(ret).map(|_| Duration::from(time))
+}
+
+/**
+ * Sleeps for `duration`
+ *
+ * # Errors
+ *
+ * `EINTR` if a signal handler ran first, the sleep is then shorter
+ */
+pub fn nanosleep(duration: Duration) -> Result<(), Errno>
+{
+	let time = Timespec::from(duration);
+	result(unsafe { syscall3(nr::NANOSLEEP, core::ptr::from_ref(&time).expose_provenance(), 0, 0) }).map(|_| ())
+}
+
+/// The id of this process, it can't fail
+#[must_use]
+pub fn getpid() -> u32
+	{ result(unsafe { syscall3(nr::GETPID, 0, 0, 0) }).ok().and_then(|pid| u32::try_from(pid).ok()).unwrap_or_default() }
+
+/**
+ * Fills `buf` with random bytes from the kernel, returns how many were written
+ *
+ * # Errors
+ *
+ * `EAGAIN` with `GRND_NONBLOCK` before the entropy pool is ready, `EINTR`
+ */
+pub fn getrandom(buf: &mut [u8], flags: u32) -> Result<usize, Errno>
+{
+	let raw_flags = usize::try_from(flags).unwrap_or_default();
+	result(unsafe { syscall3(nr::GETRANDOM, buf.as_mut_ptr().expose_provenance(), buf.len(), raw_flags) })
+}
+
+/**
+ * Waits on or wakes the futex at `uaddr`, see `FUTEX_WAIT` and `FUTEX_WAKE`
+ *
+ * # Errors
+ *
+ * `EAGAIN` if the futex didn't hold `val`, `ETIMEDOUT`, `EINTR`
+ *
+ * # Safety
+ *
+ * `uaddr` must point to an aligned `u32` that lives during the call
+ */
+pub unsafe fn futex(uaddr: *const u32, op: i32, val: u32, timeout: Option<&Timespec>) -> Result<usize, Errno>
+{
+	let timeout_ptr = timeout.map_or(0, |t| core::ptr::from_ref(t).expose_provenance());
+	let expected = usize::try_from(val).unwrap_or_default();
+	result(unsafe { syscall6(nr::FUTEX, uaddr.expose_provenance(), int(op), expected, timeout_ptr, 0, 0) })
+}
+
+/**
+ * Sends `request` to the driver behind `fd`
+ *
+ * # Errors
+ *
+ * `ENOTTY` if the driver doesn't know `request`, the rest depends on it
+ *
+ * # Safety
+ *
+ * `arg` must be what `request` expects, usually a pointer to a struct of the right size
+ */
+pub unsafe fn ioctl(fd: i32, request: usize, arg: usize) -> Result<usize, Errno>
+	{ result(unsafe { syscall3(nr::IOCTL, int(fd), request, arg) }) }
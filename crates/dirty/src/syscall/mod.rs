@@ -0,0 +1,221 @@
+// https://github.com/SCLorentz/UwU/blob/main/ARM64/src/main.s
+use core::fmt;
+use crate::WResponse;
+
+/// Raw `syscall` instruction and numbers
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod raw;
+/// Typed syscalls with errno results
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod linux;
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[allow(clippy::wildcard_imports)]
+pub use linux::*;
+
+/// This will get our ASM bindings
+mod bindings {
+	unsafe extern "C" {
+		pub(crate) fn exit(code: crate::f8) -> !;
+	}
+}
+
+/// Exits the program with a specified exit code
+#[allow(unused)]
+#[inline]
+pub fn exit(code: crate::f8) -> ! { unsafe { bindings::exit(code) } }
+
+/// Standard input
+pub const STDIN: i32 = 0;
+/// Standard output
+pub const STDOUT: i32 = 1;
+/// Standard error, where logs and diagnostics go
+pub const STDERR: i32 = 2;
+
+/// Error number of a failed syscall, the linux values are used on every platform
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Errno(pub i32);
+
+impl Errno {
+	/// Operation not permitted
+	pub const EPERM: Self = Self(1);
+	/// No such file or directory
+	pub const ENOENT: Self = Self(2);
+	/// Interrupted by a signal, the call can be repeated
+	pub const EINTR: Self = Self(4);
+	/// Input/output error
+	pub const EIO: Self = Self(5);
+	/// Bad file descriptor
+	pub const EBADF: Self = Self(9);
+	/// Try again, a non blocking call would wait
+	pub const EAGAIN: Self = Self(11);
+	/// Out of memory
+	pub const ENOMEM: Self = Self(12);
+	/// Permission denied
+	pub const EACCES: Self = Self(13);
+	/// Bad address
+	pub const EFAULT: Self = Self(14);
+	/// Device or resource busy
+	pub const EBUSY: Self = Self(16);
+	/// File exists
+	pub const EEXIST: Self = Self(17);
+	/// The rename crosses two filesystems
+	pub const EXDEV: Self = Self(18);
+	/// Not a directory
+	pub const ENOTDIR: Self = Self(20);
+	/// Is a directory
+	pub const EISDIR: Self = Self(21);
+	/// Invalid argument
+	pub const EINVAL: Self = Self(22);
+	/// The system ran out of file descriptors
+	pub const ENFILE: Self = Self(23);
+	/// The process ran out of file descriptors
+	pub const EMFILE: Self = Self(24);
+	/// Inappropriate ioctl for the device
+	pub const ENOTTY: Self = Self(25);
+	/// No space left on device
+	pub const ENOSPC: Self = Self(28);
+	/// Read only filesystem
+	pub const EROFS: Self = Self(30);
+	/// Broken pipe
+	pub const EPIPE: Self = Self(32);
+	/// Result out of range
+	pub const ERANGE: Self = Self(34);
+	/// File name too long
+	pub const ENAMETOOLONG: Self = Self(36);
+	/// The kernel doesn't have this syscall
+	pub const ENOSYS: Self = Self(38);
+	/// The directory isn't empty
+	pub const ENOTEMPTY: Self = Self(39);
+	/// Too many symbolic links, or one where it was refused
+	pub const ELOOP: Self = Self(40);
+	/// Timed out
+	pub const ETIMEDOUT: Self = Self(110);
+
+	/// Name of the constant, `None` for errors without one here
+	#[must_use]
+	pub fn name(self) -> Option<&'static str>
+	{
+		Some(match self {
+			Self::EPERM => "EPERM",
+			Self::ENOENT => "ENOENT",
+			Self::EINTR => "EINTR",
+			Self::EIO => "EIO",
+			Self::EBADF => "EBADF",
+			Self::EAGAIN => "EAGAIN",
+			Self::ENOMEM => "ENOMEM",
+			Self::EACCES => "EACCES",
+			Self::EFAULT => "EFAULT",
+			Self::EBUSY => "EBUSY",
+			Self::EEXIST => "EEXIST",
+			Self::EXDEV => "EXDEV",
+			Self::ENOTDIR => "ENOTDIR",
+			Self::EISDIR => "EISDIR",
+			Self::EINVAL => "EINVAL",
+			Self::ENFILE => "ENFILE",
+			Self::EMFILE => "EMFILE",
+			Self::ENOTTY => "ENOTTY",
+			Self::ENOSPC => "ENOSPC",
+			Self::EROFS => "EROFS",
+			Self::EPIPE => "EPIPE",
+			Self::ERANGE => "ERANGE",
+			Self::ENAMETOOLONG => "ENAMETOOLONG",
+			Self::ENOSYS => "ENOSYS",
+			Self::ENOTEMPTY => "ENOTEMPTY",
+			Self::ELOOP => "ELOOP",
+			Self::ETIMEDOUT => "ETIMEDOUT",
+			_ => return None,
+		})
+	}
+}
+
+impl fmt::Debug for Errno {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		match self.name() {
+			Some(name) => write!(f, "Errno({name})"),
+			None => write!(f, "Errno({})", self.0),
+		}
+	}
+}
+
+impl From<Errno> for WResponse {
+	fn from(errno: Errno) -> Self
+	{
+		match errno {
+			Errno::EPERM | Errno::EACCES | Errno::EROFS => WResponse::AccessDenied,
+			Errno::ENOMEM | Errno::ENFILE | Errno::EMFILE | Errno::ENOSPC | Errno::ERANGE | Errno::ENAMETOOLONG =>
+				WResponse::OutOfBounds,
+			Errno::ENOENT | Errno::EBADF | Errno::EFAULT | Errno::EEXIST | Errno::ENOTDIR | Errno::EISDIR
+				| Errno::EINVAL | Errno::ENOTTY | Errno::EXDEV | Errno::ENOTEMPTY | Errno::ELOOP => WResponse::InvalidRequest,
+			Errno::EPIPE => WResponse::ConnectionLost,
+			Errno::EBUSY => WResponse::ChannelInUse,
+			Errno::ENOSYS => WResponse::BinarySpecificLimitation,
+			_ => WResponse::UnexpectedError,
+		}
+	}
+}
+
+/// Formats `args` and writes them to `fd`, used by the print macros
+/// errors are ignored, there is nowhere left to report them
+#[doc(hidden)]
+pub fn print_to(fd: i32, args: fmt::Arguments<'_>)
+{
+	let text = alloc::fmt::format(args);
+
+	#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+	let _ = write_all(fd, text.as_bytes());
+
+	// the BSD ABI returns in x0 and x1 and reports errors in the carry flag,
+	// flags are clobbered as `preserves_flags` isn't given
+	#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+	unsafe {
+		core::arch::asm!(
+			"mov x16, #4",	// SYS_write
+			"svc #0x80",
+			inlateout("x0") i64::from(fd) => _,
+			inlateout("x1") text.as_ptr() => _,
+			in("x2") text.len(),
+			out("x16") _,
+			options(nostack)
+		)
+	}
+
+	#[cfg(not(any(
+		all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")),
+		all(target_os = "macos", target_arch = "aarch64"),
+	)))]
+	let _ = (fd, text);
+}
+
+/// write attribute to print strings into the terminal, same as `print!`
+#[macro_export]
+macro_rules! write {
+	($($x:tt)+) => { $crate::syscall::print_to($crate::syscall::STDOUT, format_args!($($x)+)) };
+}
+
+/// Prints to the standard output
+#[macro_export]
+macro_rules! print {
+	($($x:tt)+) => { $crate::syscall::print_to($crate::syscall::STDOUT, format_args!($($x)+)) };
+}
+
+/// Prints a line to the standard output
+#[macro_export]
+macro_rules! println {
+	() => { $crate::syscall::print_to($crate::syscall::STDOUT, format_args!("\n")) };
+	($($x:tt)+) => { $crate::syscall::print_to($crate::syscall::STDOUT, format_args!("{}\n", format_args!($($x)+))) };
+}
+
+/// Prints to the standard error
+#[macro_export]
+macro_rules! eprint {
+	($($x:tt)+) => { $crate::syscall::print_to($crate::syscall::STDERR, format_args!($($x)+)) };
+}
+
+/// Prints a line to the standard error
+#[macro_export]
+macro_rules! eprintln {
+	() => { $crate::syscall::print_to($crate::syscall::STDERR, format_args!("\n")) };
+	($($x:tt)+) => { $crate::syscall::print_to($crate::syscall::STDERR, format_args!("{}\n", format_args!($($x)+))) };
+}
@@ -0,0 +1,124 @@
+/*!
+ * The `syscall` instruction of each architecture, arguments and results are raw registers
+ * <https://man7.org/linux/man-pages/man2/syscall.2.html>
+ */
+#![allow(clippy::missing_docs_in_private_items)]
+
+/// Syscall numbers, they differ between architectures
+#[cfg(target_arch = "x86_64")]
+pub(super) mod nr {
+	pub(crate) const READ: usize = 0;
+	pub(crate) const WRITE: usize = 1;
+	pub(crate) const CLOSE: usize = 3;
+	pub(crate) const LSEEK: usize = 8;
+	pub(crate) const MMAP: usize = 9;
+	pub(crate) const MUNMAP: usize = 11;
+	pub(crate) const IOCTL: usize = 16;
+	pub(crate) const NANOSLEEP: usize = 35;
+	pub(crate) const GETPID: usize = 39;
+	pub(crate) const FCNTL: usize = 72;
+	pub(crate) const FLOCK: usize = 73;
+	pub(crate) const FSYNC: usize = 74;
+	pub(crate) const FTRUNCATE: usize = 77;
+	pub(crate) const FUTEX: usize = 202;
+	pub(crate) const GETDENTS64: usize = 217;
+	pub(crate) const CLOCK_GETTIME: usize = 228;
+	pub(crate) const OPENAT: usize = 257;
+	pub(crate) const MKDIRAT: usize = 258;
+	pub(crate) const UNLINKAT: usize = 263;
+	pub(crate) const RENAMEAT2: usize = 316;
+	pub(crate) const GETRANDOM: usize = 318;
+	pub(crate) const MEMFD_CREATE: usize = 319;
+}
+
+/// Syscall numbers, they differ between architectures
+#[cfg(target_arch = "aarch64")]
+pub(super) mod nr {
+	pub(crate) const FCNTL: usize = 25;
+	pub(crate) const FLOCK: usize = 32;
+	pub(crate) const IOCTL: usize = 29;
+	pub(crate) const MKDIRAT: usize = 34;
+	pub(crate) const UNLINKAT: usize = 35;
+	pub(crate) const FTRUNCATE: usize = 46;
+	pub(crate) const OPENAT: usize = 56;
+	pub(crate) const CLOSE: usize = 57;
+	pub(crate) const GETDENTS64: usize = 61;
+	pub(crate) const LSEEK: usize = 62;
+	pub(crate) const READ: usize = 63;
+	pub(crate) const WRITE: usize = 64;
+	pub(crate) const FSYNC: usize = 82;
+	pub(crate) const FUTEX: usize = 98;
+	pub(crate) const NANOSLEEP: usize = 101;
+	pub(crate) const CLOCK_GETTIME: usize = 113;
+	pub(crate) const GETPID: usize = 172;
+	pub(crate) const MUNMAP: usize = 215;
+	pub(crate) const MMAP: usize = 222;
+	pub(crate) const RENAMEAT2: usize = 276;
+	pub(crate) const GETRANDOM: usize = 278;
+	pub(crate) const MEMFD_CREATE: usize = 279;
+}
+
+/**
+ * Runs syscall `nr` with up to six arguments, unused ones should be 0
+ * returns the raw result, `-4095..=-1` is a negated errno
+ *
+ * # Safety
+ *
+ * The arguments must be valid for the syscall, pointers included
+ */
+#[cfg(target_arch = "x86_64")]
+#[inline]
+pub(super) unsafe fn syscall6(nr: usize, arg1: usize, arg2: usize, arg3: usize, arg4: usize, arg5: usize, arg6: usize) -> isize
+{
+	let ret: isize;
+	unsafe {
+		core::arch::asm!(
+			"syscall",
+			inlateout("rax") nr => ret,
+			in("rdi") arg1,
+			in("rsi") arg2,
+			in("rdx") arg3,
+			in("r10") arg4,
+			in("r8") arg5,
+			in("r9") arg6,
+			lateout("rcx") _,
+			lateout("r11") _,
+			options(nostack)
+		);
+	}
+	ret
+}
+
+/**
+ * Runs syscall `nr` with up to six arguments, unused ones should be 0
+ * returns the raw result, `-4095..=-1` is a negated errno
+ *
+ * # Safety
+ *
+ * The arguments must be valid for the syscall, pointers included
+ */
+#[cfg(target_arch = "aarch64")]
+#[inline]
+pub(super) unsafe fn syscall6(nr: usize, arg1: usize, arg2: usize, arg3: usize, arg4: usize, arg5: usize, arg6: usize) -> isize
+{
+	let ret: isize;
+	unsafe {
+		core::arch::asm!(
+			"svc #0",
+			in("x8") nr,
+			inlateout("x0") arg1 => ret,
+			in("x1") arg2,
+			in("x2") arg3,
+			in("x3") arg4,
+			in("x4") arg5,
+			in("x5") arg6,
+			options(nostack)
+		);
+	}
+	ret
+}
+
+/// `syscall6` with three arguments
+#[inline]
+pub(super) unsafe fn syscall3(nr: usize, arg1: usize, arg2: usize, arg3: usize) -> isize
+	{ unsafe { syscall6(nr, arg1, arg2, arg3, 0, 0, 0) } }
@@ -0,0 +1,267 @@
+/*!
+ * Threads running Rust closures, on top of pthreads
+ *
+ * ```rust
+ * let worker = Thread::new().name("sync").spawn(|cancel| {
+ * 	let mut rounds = 0;
+ * 	while !cancel.is_cancelled() { rounds += sync_once() }
+ * 	rounds
+ * })?;
+ *
+ * worker.cancel();
+ * let rounds = worker.join()?;
+ * ```
+ */
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::{void, Detail, Error, WResponse};
+use crate::syscall::Errno;
+
+/// This will handle with our C imports from `unix/thread.c`
+mod unix {
+	use core::ffi::c_char;
+	use crate::void;
+
+	unsafe extern "C" {
+		pub(crate) fn thread_spawn(
+			start: extern "C" fn(*mut void) -> *mut void,
+			arg: *mut void,
+			stack_size: usize,
+			native: *mut u64,
+		) -> i32;
+		pub(crate) fn thread_join(native: u64) -> i32;
+		pub(crate) fn thread_detach(native: u64) -> i32;
+		pub(crate) fn thread_set_name(name: *const c_char) -> i32;
+		pub(crate) fn thread_failure(status: i32) -> i32;
+	}
+}
+
+/// `THREAD_DENIED` of `thread.c`, `EPERM`: not allowed to use the requested attributes
+const DENIED: i32 = 1;
+/// `THREAD_EXHAUSTED` of `thread.c`, `EAGAIN`: out of resources or over the thread limit
+const EXHAUSTED: i32 = 2;
+/// `THREAD_INVALID` of `thread.c`, `EINVAL`, `ESRCH` or `EDEADLK`: bad attributes, a missing thread or joining itself
+const INVALID: i32 = 3;
+
+/// Longest name accepted by linux, without the nul terminator
+const MAX_NAME: usize = 15;
+
+/// Maps the error number of pthread into the closest `WResponse`, the number is kept as detail
+fn response(status: i32) -> Error
+{
+	// the numbers depend on the OS, `thread.c` sorts them with its own `errno.h`
+	let kind = match unsafe { unix::thread_failure(status) } {
+		DENIED => WResponse::AccessDenied,
+		EXHAUSTED => WResponse::OutOfBounds,
+		INVALID => WResponse::InvalidRequest,
+		_ => WResponse::UnexpectedError,
+	};
+	Error::new(kind).with_detail(Detail::Errno(Errno(status)))
+}
+
+/// Checked by a running thread to know if it was asked to stop, see `JoinHandle::cancel`
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+	/// `true` once the thread was asked to stop, it should return as soon as possible
+	#[must_use]
+	pub fn is_cancelled(&self) -> bool
+		{ self.0.load(Ordering::Acquire) }
+}
+
+/// Where the thread leaves its result
+struct Packet<T> {
+	/// Written once by the thread, read only after it was joined
+	result: UnsafeCell<Option<T>>,
+	/// Set when the closure returned
+	finished: AtomicBool,
+}
+
+// the result is only touched by the thread before it exits and by `join` after it
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+/// Everything the new thread takes ownership of
+struct Start<F, T> {
+	/// The closure to run
+	function: F,
+	/// Nul terminated name, truncated to `MAX_NAME`
+	name: Option<String>,
+	/// Shared with the `JoinHandle`
+	cancel: CancelToken,
+	/// Shared with the `JoinHandle`
+	packet: Arc<Packet<T>>,
+}
+
+/// Entry point of every thread, `data` is a boxed `Start<F, T>`
+extern "C" fn start<F, T>(data: *mut void) -> *mut void
+where
+	F: FnOnce(&CancelToken) -> T,
+{
+	let Start { function, name, cancel, packet } = *unsafe { Box::from_raw(data.cast::<Start<F, T>>()) };
+	if let Some(native) = name { let _ = unsafe { unix::thread_set_name(native.as_ptr().cast()) }; }
+
+	let result = function(&cancel);
+	unsafe { *packet.result.get() = Some(result) };
+	packet.finished.store(true, Ordering::Release);
+	core::ptr::null_mut()
+}
+
+/// Cuts `name` to what the OS accepts, without splitting a character
+fn native_name(name: &str) -> String
+{
+	let visible = name.split('\0').next().unwrap_or_default();
+	let mut end = visible.len().min(MAX_NAME);
+	while !visible.is_char_boundary(end) { end = end.saturating_sub(1) }
+
+	let mut native = String::from(visible.get(..end).unwrap_or_default());
+	native.push('\0');
+	native
+}
+
+/// Thread options, `spawn` starts it
+#[derive(Debug, Clone, Default)]
+pub struct Thread {
+	/// Shown by debuggers and `top`, linux only keeps the first 15 bytes
+	name: Option<String>,
+	/// Stack size in bytes, `0` for the system default
+	stack_size: usize,
+}
+
+impl Thread {
+	/// A thread without name and with the default stack size
+	#[must_use]
+	pub fn new() -> Self { Self::default() }
+
+	/// Names the thread
+	#[must_use]
+	pub fn name(mut self, name: &str) -> Self
+	{
+		self.name = Some(String::from(name));
+		self
+	}
+
+	/// Changes the stack size, in bytes
+	#[must_use]
+	pub fn stack_size(mut self, size: usize) -> Self
+	{
+		self.stack_size = size;
+		self
+	}
+
+	/**
+	 * Runs `function` on a new thread, the result is returned by `JoinHandle::join`
+	 * `function` gets a `CancelToken` to know when it should stop
+	 *
+	 * # Errors
+	 *
+	 * `OutOfBounds` if the system is out of threads, `InvalidRequest` if the stack size is invalid
+	 */
+	pub fn spawn<F, T>(self, function: F) -> Result<JoinHandle<T>, Error>
+	where
+		F: FnOnce(&CancelToken) -> T + Send + 'static,
+		T: Send + 'static,
+	{
+		let cancel = CancelToken::default();
+		let packet = Arc::new(Packet { result: UnsafeCell::new(None), finished: AtomicBool::new(false) });
+
+		let data = Box::into_raw(Box::new(Start {
+			function,
+			name: self.name.as_deref().map(native_name),
+			cancel: cancel.clone(),
+			packet: Arc::clone(&packet),
+		})).cast::<void>();
+
+		let mut native = 0;
+		let status = unsafe { unix::thread_spawn(start::<F, T>, data, self.stack_size, &raw mut native) };
+
+		if status != 0 {
+			// the thread never started, the closure is still ours
+			drop(unsafe { Box::from_raw(data.cast::<Start<F, T>>()) });
+			return Err(response(status));
+		}
+
+		Ok(JoinHandle { native: Some(native), name: self.name, cancel, packet })
+	}
+}
+
+/**
+ * Runs `function` on a new unnamed thread, same as `Thread::new().spawn(function)`
+ *
+ * # Errors
+ *
+ * Same as `Thread::spawn`
+ */
+pub fn spawn<F, T>(function: F) -> Result<JoinHandle<T>, Error>
+where
+	F: FnOnce(&CancelToken) -> T + Send + 'static,
+	T: Send + 'static,
+{
+	Thread::new().spawn(function)
+}
+
+/// A running thread, it is detached if dropped without `join`
+pub struct JoinHandle<T> {
+	/// `pthread_t` of the thread, `None` once joined
+	native: Option<u64>,
+	/// Name given on `Thread::name`
+	name: Option<String>,
+	/// Shared with the thread
+	cancel: CancelToken,
+	/// Result of the thread
+	packet: Arc<Packet<T>>,
+}
+
+impl<T> JoinHandle<T> {
+	/// Name given on `Thread::name`
+	#[must_use]
+	pub fn name(&self) -> Option<&str> { self.name.as_deref() }
+
+	/// Asks the thread to stop, it is up to the closure to check its `CancelToken`
+	pub fn cancel(&self)
+		{ self.cancel.0.store(true, Ordering::Release) }
+
+	/// `true` once the closure returned, `join` won't block
+	#[must_use]
+	pub fn is_finished(&self) -> bool
+		{ self.packet.finished.load(Ordering::Acquire) }
+
+	/**
+	 * Waits for the thread to finish, returns what the closure returned
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if a thread tries to join itself,
+	 * `UnexpectedError` if the thread exited without a result
+	 */
+	pub fn join(mut self) -> Result<T, Error>
+	{
+		let native = self.native.take().ok_or(WResponse::InvalidRequest)?;
+		let status = unsafe { unix::thread_join(native) };
+		if status != 0 { return Err(response(status)) }
+
+		unsafe { (*self.packet.result.get()).take() }.ok_or(WResponse::UnexpectedError.into())
+	}
+}
+
+impl<T> Drop for JoinHandle<T> {
+	fn drop(&mut self)
+	{
+		let Some(native) = self.native else { return };
+		let _ = unsafe { unix::thread_detach(native) };
+	}
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		f.debug_struct("JoinHandle")
+			.field("name", &self.name)
+			.field("finished", &self.is_finished())
+			.finish_non_exhaustive()
+	}
+}
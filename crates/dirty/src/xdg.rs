@@ -0,0 +1,121 @@
+/*!
+ * XDG base directories, where config, data, caches, state and sockets go
+ * <https://specifications.freedesktop.org/basedir-spec/latest/>
+ *
+ * ```rust
+ * let sessions = BaseDir::Data.app_dir("walkie-talkie")?;
+ * let config = BaseDir::Config.path()?;
+ * ```
+ *
+ * relative paths in the variables are ignored, like the spec asks
+ */
+use crate::{env, format, fs, Error, String, Vec, WResponse};
+
+/// Permissions of the directories created here, the spec asks for `0700`
+const DIR_MODE: u32 = 0o700;
+
+/// Reads an environment variable, empty values count as unset
+fn var(name: &str) -> Option<String>
+	{ env::var(name).filter(|value| !value.is_empty()) }
+
+/// Reads a variable holding an absolute path
+fn absolute(name: &str) -> Option<String>
+	{ var(name).filter(|path| path.starts_with('/')) }
+
+/// The user directories of the spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseDir {
+	/// `$XDG_CONFIG_HOME`, `~/.config` by default
+	Config,
+	/// `$XDG_DATA_HOME`, `~/.local/share` by default
+	Data,
+	/// `$XDG_CACHE_HOME`, `~/.cache` by default
+	Cache,
+	/// `$XDG_STATE_HOME`, `~/.local/state` by default, for logs and history
+	State,
+	/// `$XDG_RUNTIME_DIR`, sockets and other files that live with the session, it has no default
+	Runtime,
+}
+
+impl BaseDir {
+	/// The variable that overrides the directory
+	fn variable(self) -> &'static str
+	{
+		match self {
+			Self::Config => "XDG_CONFIG_HOME",
+			Self::Data => "XDG_DATA_HOME",
+			Self::Cache => "XDG_CACHE_HOME",
+			Self::State => "XDG_STATE_HOME",
+			Self::Runtime => "XDG_RUNTIME_DIR",
+		}
+	}
+
+	/// Where the directory is when the variable isn't set, relative to `$HOME`
+	fn fallback(self) -> Option<&'static str>
+	{
+		match self {
+			Self::Config => Some(".config"),
+			Self::Data => Some(".local/share"),
+			Self::Cache => Some(".cache"),
+			Self::State => Some(".local/state"),
+			Self::Runtime => None,
+		}
+	}
+
+	/**
+	 * The directory, it may not exist yet
+	 *
+	 * # Errors
+	 *
+	 * `MissingDependencies` if neither the variable nor `$HOME` are set,
+	 * always for `Runtime` without `$XDG_RUNTIME_DIR`
+	 */
+	pub fn path(self) -> Result<String, Error>
+	{
+		if let Some(path) = absolute(self.variable()) { return Ok(path) }
+
+		let name = self.variable();
+		let Some(fallback) = self.fallback() else {
+			return Err(Error::new(WResponse::MissingDependencies).with_message(format!("{name} is not set")));
+		};
+		let home = absolute("HOME").ok_or_else(|| {
+			Error::new(WResponse::MissingDependencies).with_message(format!("neither {name} nor HOME are set"))
+		})?;
+		Ok(format!("{}/{fallback}", home.trim_end_matches('/')))
+	}
+
+	/**
+	 * The directory of `app` inside this one, created with `0700` if missing
+	 *
+	 * # Errors
+	 *
+	 * Same as `BaseDir::path` and `fs::create_dir_all`, `InvalidRequest` if `app` isn't a plain name
+	 */
+	pub fn app_dir(self, app: &str) -> Result<String, Error>
+	{
+		if app.is_empty() || app.contains('/') || app == "." || app == ".." {
+			return Err(Error::new(WResponse::InvalidRequest).with_message(format!("{app:?} isn't a directory name")));
+		}
+
+		let path = format!("{}/{app}", self.path()?.trim_end_matches('/'));
+		fs::create_dir_all(&path, DIR_MODE)?;
+		Ok(path)
+	}
+}
+
+/// Splits a list of paths, keeping the absolute ones
+fn search_path(name: &str, default: &str) -> Vec<String>
+{
+	let list = var(name).unwrap_or_else(|| String::from(default));
+	list.split(':').filter(|path| path.starts_with('/')).map(String::from).collect()
+}
+
+/// `$XDG_CONFIG_DIRS`, system config searched after `BaseDir::Config`, in order of preference
+#[must_use]
+pub fn config_dirs() -> Vec<String>
+	{ search_path("XDG_CONFIG_DIRS", "/etc/xdg") }
+
+/// `$XDG_DATA_DIRS`, system data searched after `BaseDir::Data`, in order of preference
+#[must_use]
+pub fn data_dirs() -> Vec<String>
+	{ search_path("XDG_DATA_DIRS", "/usr/local/share:/usr/share") }
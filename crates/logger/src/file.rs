@@ -0,0 +1,91 @@
+/*!
+ * Log file that rotates by size, `wt.log` is moved to `wt.log.1`, `wt.log.1` to `wt.log.2`
+ * and so on, the oldest one is removed
+ */
+use dirty::fs::{self, File};
+use dirty::{format, Error, String};
+
+/// The file being written and where the older ones go
+#[derive(Debug)]
+pub(crate) struct RotatingFile {
+	/// Path of the current file
+	path: String,
+	/// Open in append mode, `None` after a rotation failed to open the new one
+	file: Option<File>,
+	/// Bytes in the current file
+	size: u64,
+	/// Size that starts a new file
+	max_size: u64,
+	/// Old files kept next to the current one
+	keep: u32,
+}
+
+impl RotatingFile {
+	/**
+	 * Opens `dir/name`, appending to what is already there
+	 *
+	 * # Errors
+	 *
+	 * Same as `File::append` and `File::size`
+	 */
+	pub(crate) fn open(dir: &str, name: &str, max_size: u64, keep: u32) -> Result<Self, Error>
+	{
+		let path = format!("{dir}/{name}");
+		let file = File::append(&path)?;
+		let size = file.size()?;
+		Ok(Self { path, file: Some(file), size, max_size, keep })
+	}
+
+	/// Path of the `n`th old file, the current one for `0`
+	fn old(&self, n: u32) -> String
+	{
+		if n == 0 { return self.path.clone() }
+		format!("{}.{n}", self.path)
+	}
+
+	/// Shifts the old files and starts an empty one
+	fn rotate(&mut self) -> Result<(), Error>
+	{
+		// closed first, so nothing is written to a file that was renamed
+		self.file = None;
+
+		// missing files are fine, the log may not have rotated that many times yet
+		let _ = fs::remove_file(&self.old(self.keep));
+		for n in (0..self.keep).rev() {
+			let _ = fs::rename(&self.old(n), &self.old(n + 1));
+		}
+		if self.keep == 0 { let _ = fs::remove_file(&self.path); }
+
+		self.file = Some(File::append(&self.path)?);
+		self.size = 0;
+		Ok(())
+	}
+
+	/**
+	 * Appends a whole line, rotating first if it doesn't fit
+	 *
+	 * # Errors
+	 *
+	 * Same as `File::write_all`, or the reason the file couldn't be opened again after a rotation
+	 */
+	pub(crate) fn write(&mut self, line: &[u8]) -> Result<(), Error>
+	{
+		let len = line.len() as u64;
+		if self.file.is_none() || (self.size > 0 && self.size + len > self.max_size) { self.rotate()? }
+
+		let Some(file) = self.file.as_mut() else { return Ok(()) };
+		file.write_all(line)?;
+		self.size += len;
+		Ok(())
+	}
+
+	/**
+	 * Waits until the lines reached the disk
+	 *
+	 * # Errors
+	 *
+	 * Same as `File::sync`
+	 */
+	pub(crate) fn sync(&self) -> Result<(), Error>
+		{ self.file.as_ref().map_or(Ok(()), File::sync) }
+}
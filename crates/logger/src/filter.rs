@@ -0,0 +1,94 @@
+/*!
+ * Per module levels, parsed from directives like `WT_LOG=info,app=debug,dirty::dbus=trace`
+ *
+ * a directive is a level, a module, or `module=level`; a lone module logs everything.
+ * The most specific module wins, `dirty::dbus` before `dirty`, the lone level applies to the rest
+ */
+use dirty::{String, Vec};
+use log::LevelFilter;
+
+/// A module and its level
+#[derive(Debug, Clone)]
+struct Directive {
+	/// Module path, like `app::platform`
+	module: String,
+	/// Most verbose level logged for the module
+	level: LevelFilter,
+}
+
+/// Levels by module
+#[derive(Debug, Clone)]
+pub(crate) struct Filter {
+	/// Level of the modules without a directive
+	default: LevelFilter,
+	/// Sorted from the longest module to the shortest, so the first match is the most specific
+	directives: Vec<Directive>,
+}
+
+/// `module` is `target` or one of its parents
+fn contains(module: &str, target: &str) -> bool
+{
+	target.strip_prefix(module)
+		.is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+impl Filter {
+	/// Logs `default` and above everywhere
+	pub(crate) const fn new(default: LevelFilter) -> Self
+		{ Self { default, directives: Vec::new() } }
+
+	/// Sets the level of the modules without a directive
+	pub(crate) fn set_default(&mut self, level: LevelFilter)
+		{ self.default = level }
+
+	/// Sets the level of `module` and its children, replacing an older directive for it
+	pub(crate) fn set(&mut self, module: &str, level: LevelFilter)
+	{
+		self.directives.retain(|directive| directive.module != module);
+		self.directives.push(Directive { module: String::from(module), level });
+		self.directives.sort_by_key(|directive| core::cmp::Reverse(directive.module.len()));
+	}
+
+	/**
+	 * Applies a comma separated list of directives, returns the ones it didn't understand
+	 *
+	 * ```rust
+	 * let ignored = filter.parse("warn,app=debug,dirty::dbus=loud");
+	 * assert_eq!(ignored, ["dirty::dbus=loud"]);
+	 * ```
+	 */
+	pub(crate) fn parse<'a>(&mut self, spec: &'a str) -> Vec<&'a str>
+	{
+		let mut ignored = Vec::new();
+
+		for directive in spec.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+			match directive.split_once('=') {
+				Some((module, name)) => match name.trim().parse() {
+					Ok(level) if !module.trim().is_empty() => self.set(module.trim(), level),
+					_ => ignored.push(directive),
+				},
+				None => match directive.parse() {
+					Ok(level) => self.set_default(level),
+					Err(_) => self.set(directive, LevelFilter::Trace),
+				},
+			}
+		}
+		ignored
+	}
+
+	/// Most verbose level logged for `target`
+	pub(crate) fn level(&self, target: &str) -> LevelFilter
+	{
+		self.directives.iter()
+			.find(|directive| contains(&directive.module, target))
+			.map_or(self.default, |directive| directive.level)
+	}
+
+	/// Most verbose level logged anywhere, what `log` can skip before calling the logger
+	pub(crate) fn max(&self) -> LevelFilter
+	{
+		self.directives.iter()
+			.map(|directive| directive.level)
+			.fold(self.default, core::cmp::max)
+	}
+}
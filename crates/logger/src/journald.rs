@@ -0,0 +1,136 @@
+/*!
+ * Entries for the systemd journal, sent over its native protocol
+ * <https://systemd.io/JOURNAL_NATIVE_PROTOCOL/>
+ *
+ * every record is one datagram of `NAME=value` lines, values holding a newline are sent as
+ * `NAME\n`, their size as a little endian `u64`, the bytes and a newline.
+ * Entries too big for a datagram go in a sealed `memfd` sent alone
+ *
+ * the key-values of a record become fields, `log::info!(room_id = id; "joined")` adds `ROOM_ID`
+ */
+use dirty::shm;
+use dirty::socket::UnixDatagram;
+use dirty::sync::Mutex;
+use dirty::{format, Error, String, ToString, Vec, WResponse};
+use log::{kv, Level, Record};
+use crate::scrub;
+
+/// Where journald receives native entries
+const SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Longest field name journald accepts
+const NAME_LEN: usize = 64;
+
+/// syslog priority of a level, journald has nothing below debug
+const fn priority(level: Level) -> &'static str
+{
+	match level {
+		Level::Error => "3",
+		Level::Warn => "4",
+		Level::Info => "6",
+		Level::Debug | Level::Trace => "7",
+	}
+}
+
+/// Appends the field `name` to `entry`
+fn field(entry: &mut Vec<u8>, name: &str, value: &[u8])
+{
+	entry.extend_from_slice(name.as_bytes());
+	if value.contains(&b'\n') {
+		entry.push(b'\n');
+		entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+	} else {
+		entry.push(b'=');
+	}
+	entry.extend_from_slice(value);
+	entry.push(b'\n');
+}
+
+/**
+ * Field name of a key, journald only takes uppercase letters, digits and `_`,
+ * names starting with `_` are reserved for the fields it adds itself
+ */
+fn name(key: &str) -> Option<String>
+{
+	let upper: String = key.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+		.collect();
+	let trimmed = upper.trim_start_matches(|c: char| c == '_' || c.is_ascii_digit());
+	(!trimmed.is_empty()).then(|| trimmed.chars().take(NAME_LEN).collect())
+}
+
+/// Adds the key-values of a record to an entry
+struct Fields<'a>(&'a mut Vec<u8>);
+
+impl<'kvs> kv::VisitSource<'kvs> for Fields<'_> {
+	fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error>
+	{
+		if let Some(name) = name(key.as_str()) { field(self.0, &name, scrub(&value.to_string()).as_bytes()) }
+		Ok(())
+	}
+}
+
+/// Connection to journald
+#[derive(Debug)]
+pub(crate) struct Journald {
+	/// Connected to `SOCKET`, replaced when journald restarts
+	socket: Mutex<UnixDatagram>,
+	/// `SYSLOG_IDENTIFIER` of the entries
+	identifier: String,
+}
+
+impl Journald {
+	/**
+	 * Connects to the journal, entries are tagged with `identifier`
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` without journald, like on desktops without systemd
+	 */
+	pub(crate) fn connect(identifier: &str) -> Result<Self, Error>
+	{
+		let socket = UnixDatagram::connect(SOCKET)?;
+		Ok(Self { socket: Mutex::new(socket), identifier: String::from(identifier) })
+	}
+
+	/// The entry of `record`
+	fn entry(&self, record: &Record<'_>) -> Vec<u8>
+	{
+		let mut entry = Vec::new();
+		field(&mut entry, "MESSAGE", scrub(&format!("{}", record.args())).as_bytes());
+		field(&mut entry, "PRIORITY", priority(record.level()).as_bytes());
+		field(&mut entry, "SYSLOG_IDENTIFIER", self.identifier.as_bytes());
+		field(&mut entry, "TARGET", record.target().as_bytes());
+		if let Some(file) = record.file() { field(&mut entry, "CODE_FILE", file.as_bytes()) }
+		if let Some(line) = record.line() { field(&mut entry, "CODE_LINE", line.to_string().as_bytes()) }
+		let _ = record.key_values().visit(&mut Fields(&mut entry));
+		entry
+	}
+
+	/**
+	 * Sends `record` to the journal
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if journald is gone and didn't come back, `OutOfBounds` if the entry
+	 * didn't fit in a datagram nor in a `memfd`
+	 */
+	pub(crate) fn send(&self, record: &Record<'_>) -> Result<(), Error>
+	{
+		let entry = self.entry(record);
+		let mut socket = self.socket.lock();
+
+		match socket.send(&entry) {
+			Err(e) if e == WResponse::OutOfBounds => {
+				let fd = shm::sealed(c"journal-entry", &entry)?;
+				socket.send_fds(&[], &[fd.raw()]).map(|_| ())
+			},
+			// a restarted journald has a new socket on the same path
+			Err(e) if e == WResponse::ConnectionLost => {
+				*socket = UnixDatagram::connect(SOCKET)?;
+				socket.send(&entry).map(|_| ())
+			},
+			sent => sent.map(|_| ()),
+		}
+	}
+}
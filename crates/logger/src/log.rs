@@ -1,17 +1,397 @@
-use dirty::write;
-use log::{Metadata, Log, Record};
+#![no_std]
+#![deny(
+	deprecated,
+	rust_2018_idioms,
+	clippy::shadow_unrelated,
+	unreachable_code,
+	unused_imports,
+	unused_variables,
+	unsafe_op_in_unsafe_fn,
+	clippy::unwrap_used,
+	clippy::expect_used,
+	clippy::shadow_reuse,
+	clippy::shadow_same,
+	clippy::dbg_macro,
+	clippy::print_stdout,
+	clippy::print_stderr,
+	clippy::panic,
+	clippy::indexing_slicing,
+	clippy::unwrap_in_result,
+	clippy::exit,
+	clippy::wildcard_imports,
+	missing_docs,
+	clippy::all,
+	trivial_casts,
+	trivial_numeric_casts,
+	unused_extern_crates,
+	unused_import_braces,
+	unused_qualifications,
+	unused_results,
+)]
+#![forbid(unsafe_code)]
+#![allow(clippy::tabs_in_doc_comments)]
+#![doc = include_str!("../README.md")]
 
-pub struct Logger {}
+mod filter;
+mod time;
+mod secret;
+mod scrub;
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod file;
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod journald;
 
-impl Log for Logger
+use core::fmt;
+use dirty::{format, Box, Error, String, Vec, WResponse};
+use dirty::syscall::{self, STDOUT};
+use log::{kv, Level, LevelFilter, Log, Metadata, Record};
+use filter::Filter;
+use time::Timestamp;
+
+pub use secret::{Secret, REDACTED};
+pub use scrub::scrub;
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+use {dirty::sync::Mutex, dirty::xdg::BaseDir, file::RotatingFile, journald::Journald};
+
+/// Variable holding the directives of `Logger::env`, like `WT_LOG=info,app=debug,dirty::dbus=trace`
+pub const ENV: &str = "WT_LOG";
+
+/// Size that starts a new log file by default, 1 MiB
+const MAX_SIZE: u64 = 1 << 20;
+/// Old log files kept by default
+const KEEP: u32 = 3;
+
+/// Resets the terminal colors
+const RESET: &str = "\x1b[0m";
+/// Dims the timestamp
+const DIM: &str = "\x1b[2m";
+
+/// Terminal color of a level
+const fn color(level: Level) -> &'static str
 {
-	fn enabled(&self, metadata: &Metadata) -> bool { true }
+	match level {
+		Level::Error => "\x1b[1;31m",
+		Level::Warn => "\x1b[33m",
+		Level::Info => "\x1b[32m",
+		Level::Debug => "\x1b[36m",
+		Level::Trace => "\x1b[90m",
+	}
+}
+
+/// `true` if stdout is a terminal and `NO_COLOR` isn't set, <https://no-color.org>
+fn colored() -> bool
+{
+	#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+	return syscall::isatty(STDOUT) && dirty::env::var("NO_COLOR").is_none_or(|value| value.is_empty());
+
+	#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+	false
+}
+
+/// Writes the key-values of a record after the message, ` room_id=!abc:matrix.org`
+struct KeyValues<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl<'kvs> kv::VisitSource<'kvs> for KeyValues<'_, '_> {
+	fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error>
+		{ write!(self.0, " {key}={value}").map_err(kv::Error::from) }
+}
+
+/// The message of a record followed by its key-values
+struct Message<'a>(&'a Record<'a>);
+
+impl fmt::Display for Message<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		write!(f, "{}", self.0.args())?;
+		self.0.key_values().visit(&mut KeyValues(f)).map_err(|_| fmt::Error)
+	}
+}
+
+/// Where the log file goes, it is opened by `Logger::init`
+#[derive(Debug, Clone)]
+struct FileConfig {
+	/// Directory name under `$XDG_STATE_HOME`, the file is `<app>.log` inside it
+	app: String,
+	/// Size that starts a new file
+	max_size: u64,
+	/// Old files kept
+	keep: u32,
+}
+
+/**
+ * Logger for the `log` macros, lines look like
+ * `2026-01-31T18:04:05.123Z INFO  [app::platform] window created`
+ *
+ * ```rust
+ * Logger::new()
+ * 	.env(logger::ENV)
+ * 	.file("walkie-talkie")
+ * 	.init()?;
+ * ```
+ */
+#[derive(Debug)]
+pub struct Logger {
+	/// Levels by module
+	filter: Filter,
+	/// Writes the lines to stdout
+	stdout: bool,
+	/// Colors the levels on stdout
+	color: bool,
+	/// Starts the lines with the time
+	timestamps: bool,
+	/// Log file to open in `init`
+	file_config: Option<FileConfig>,
+	/// The open log file
+	#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+	file: Option<Mutex<RotatingFile>>,
+	/// `SYSLOG_IDENTIFIER` of the journal entries, the journal is connected in `init`
+	journald_identifier: Option<String>,
+	/// The connected journal
+	#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+	journald: Option<Journald>,
+	/// Problems found while setting up, logged once the logger is installed
+	warnings: Vec<String>,
+}
+
+impl Default for Logger {
+	fn default() -> Self { Self::new() }
+}
+
+impl Logger {
+	/// Logs `info` and above to stdout, colored if it is a terminal
+	#[must_use]
+	pub fn new() -> Self
+	{
+		Self {
+			filter: Filter::new(LevelFilter::Info),
+			stdout: true,
+			color: colored(),
+			timestamps: true,
+			file_config: None,
+			#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+			file: None,
+			journald_identifier: None,
+			#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+			journald: None,
+			warnings: Vec::new(),
+		}
+	}
+
+	/// Level of the modules without their own
+	#[must_use]
+	pub fn level(mut self, level: LevelFilter) -> Self
+	{
+		self.filter.set_default(level);
+		self
+	}
+
+	/// Level of `module` and its children, like `module("dirty::dbus", LevelFilter::Trace)`
+	#[must_use]
+	pub fn module(mut self, module: &str, level: LevelFilter) -> Self
+	{
+		self.filter.set(module, level);
+		self
+	}
+
+	/**
+	 * Reads the levels from the variable `name`, like `info,app=debug,dirty::dbus=trace`,
+	 * they override the ones set before
+	 *
+	 * a lone level applies to every module, a lone module logs everything;
+	 * directives that don't parse are reported as warnings once the logger is installed
+	 */
+	#[must_use]
+	pub fn env(mut self, name: &str) -> Self
+	{
+		#[cfg(target_os = "linux")]
+		if let Some(spec) = dirty::env::var(name) {
+			let ignored = self.filter.parse(&spec);
+			self.warnings.extend(ignored.into_iter().map(|directive| format!("{name}: ignored {directive:?}")));
+		}
+
+		#[cfg(not(target_os = "linux"))]
+		let _ = name;
+
+		self
+	}
+
+	/// Turns the lines on stdout on or off, the other outputs don't change
+	#[must_use]
+	pub fn stdout(mut self, stdout: bool) -> Self
+	{
+		self.stdout = stdout;
+		self
+	}
 
-	fn log(&self, record: &Record)
+	/// Forces the colors on or off
+	#[must_use]
+	pub fn color(mut self, color: bool) -> Self
 	{
-		let message = format!("{}\n", record.level().to_string());
-		write!("{}", message);
+		self.color = color;
+		self
 	}
 
-	fn flush(&self) {}
+	/// Starts the lines with the time or not, journald already adds it
+	#[must_use]
+	pub fn timestamps(mut self, timestamps: bool) -> Self
+	{
+		self.timestamps = timestamps;
+		self
+	}
+
+	/// Also writes to `$XDG_STATE_HOME/<app>/<app>.log`, keeping `KEEP` older files of 1 MiB
+	#[must_use]
+	pub fn file(mut self, app: &str) -> Self
+	{
+		self.file_config = Some(FileConfig { app: String::from(app), max_size: MAX_SIZE, keep: KEEP });
+		self
+	}
+
+	/// Starts a new log file past `max_size` bytes, keeping `keep` older ones, only used with `file`
+	#[must_use]
+	pub fn rotate(mut self, max_size: u64, keep: u32) -> Self
+	{
+		if let Some(config) = self.file_config.as_mut() {
+			config.max_size = max_size;
+			config.keep = keep;
+		}
+		self
+	}
+
+	/**
+	 * Also sends the records to the systemd journal, tagged with `identifier`, with their
+	 * key-values as fields; nothing happens on systems without journald
+	 *
+	 * when stdout already goes to the journal, `$JOURNAL_STREAM` is set and stdout is turned off
+	 */
+	#[must_use]
+	pub fn journald(mut self, identifier: &str) -> Self
+	{
+		self.journald_identifier = Some(String::from(identifier));
+		self
+	}
+
+	/// Connects to the journal, a missing journald isn't a problem
+	#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+	fn connect_journald(&mut self)
+	{
+		let Some(identifier) = self.journald_identifier.take() else { return };
+
+		match Journald::connect(&identifier) {
+			Ok(journal) => self.journald = Some(journal),
+			Err(e) if e == WResponse::ConnectionLost => return,
+			Err(e) => return self.warnings.push(format!("no journal: {e}")),
+		}
+		if dirty::env::var("JOURNAL_STREAM").is_some_and(|stream| !stream.is_empty()) { self.stdout = false }
+	}
+
+	/// There is no journald here
+	#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+	fn connect_journald(&mut self)
+		{ self.journald_identifier = None }
+
+	/// Opens the log file, the logger still works without it
+	#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+	fn open_file(&mut self)
+	{
+		let Some(config) = self.file_config.take() else { return };
+
+		let opened = BaseDir::State.app_dir(&config.app)
+			.and_then(|dir| RotatingFile::open(&dir, &format!("{}.log", config.app), config.max_size, config.keep));
+		match opened {
+			Ok(file) => self.file = Some(Mutex::new(file)),
+			Err(e) => self.warnings.push(format!("no log file: {e}")),
+		}
+	}
+
+	/// Log files need a filesystem this platform doesn't have yet
+	#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+	fn open_file(&mut self)
+	{
+		if self.file_config.take().is_some() {
+			self.warnings.push(String::from("no log file: not supported on this platform"));
+		}
+	}
+
+	/**
+	 * Installs the logger for the whole program, it lives until the end
+	 *
+	 * # Errors
+	 *
+	 * `ChannelInUse` if a logger was already installed
+	 */
+	pub fn init(mut self) -> Result<(), Error>
+	{
+		self.open_file();
+		self.connect_journald();
+		let warnings = core::mem::take(&mut self.warnings);
+		let max = self.filter.max();
+
+		log::set_logger(Box::leak(Box::new(self))).map_err(|_| {
+			Error::new(WResponse::ChannelInUse).with_message("a logger is already installed")
+		})?;
+		log::set_max_level(max);
+
+		for warning in warnings { log::warn!("{warning}") }
+		Ok(())
+	}
+
+	/// Writes a line to the log file, a broken file can't be reported anywhere but stdout
+	#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+	fn write_file(&self, time: Option<Timestamp>, record: &Record<'_>, message: &str)
+	{
+		let Some(file) = self.file.as_ref() else { return };
+
+		let line = match time {
+			Some(now) => format!("{now} {:<5} [{}] {}\n", record.level(), record.target(), message),
+			None => format!("{:<5} [{}] {}\n", record.level(), record.target(), message),
+		};
+		if let Err(e) = file.lock().write(line.as_bytes()) {
+			syscall::print_to(STDOUT, format_args!("logger: couldn't write the log file: {e}\n"));
+		}
+	}
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata<'_>) -> bool
+		{ metadata.level() <= self.filter.level(metadata.target()) }
+
+	fn log(&self, record: &Record<'_>)
+	{
+		if !self.enabled(record.metadata()) { return }
+
+		let time = if self.timestamps { Timestamp::now() } else { None };
+		let level = record.level();
+		// scrubbed once, every output gets the same text
+		let message = scrub(&format!("{}", Message(record)));
+		let (dim, color, reset) = if self.color { (DIM, color(level), RESET) } else { ("", "", "") };
+
+		// one write per line, lines from different threads don't mix
+		match time {
+			Some(now) if self.stdout => syscall::print_to(STDOUT, format_args!(
+				"{dim}{now}{reset} {color}{level:<5}{reset} [{}] {}\n", record.target(), message
+			)),
+			None if self.stdout => syscall::print_to(STDOUT, format_args!(
+				"{color}{level:<5}{reset} [{}] {}\n", record.target(), message
+			)),
+			_ => {},
+		}
+
+		#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+		{
+			self.write_file(time, record, &message);
+			if let Some(Err(e)) = self.journald.as_ref().map(|journal| journal.send(record)) {
+				syscall::print_to(STDOUT, format_args!("logger: couldn't send to the journal: {e}\n"));
+			}
+		}
+	}
+
+	fn flush(&self)
+	{
+		#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+		if let Some(file) = self.file.as_ref() {
+			let _ = file.lock().sync();
+		}
+	}
 }
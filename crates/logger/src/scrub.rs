@@ -0,0 +1,229 @@
+/*!
+ * Last line of defense against secrets formatted without `Secret`, every record goes through
+ * `scrub` before it is written anywhere
+ *
+ * - `syt_` access tokens and `syr_` refresh tokens keep their prefix, `syt_[redacted]`
+ * - values of keys like `access_token=`, `"refresh_token": "…"` or `session_key: "…"`,
+ *   also inside longer names like `user_access_token`
+ * - recovery keys, base58 in groups of 4 like `EsTc LW2K PGiF wKEA …`
+ * - `Bearer` authorization headers
+ * - long base64 blobs with mixed case and digits, like exported Megolm session keys;
+ *   public keys and signatures are shorter and stay readable
+ */
+use dirty::String;
+use crate::secret::REDACTED;
+
+/// Prefixes of the tokens made by Synapse
+const PREFIXES: [&str; 2] = ["syt_", "syr_"];
+
+/// Keys whose value is a secret, in JSON, query strings or `Debug` output
+const KEYS: [&str; 6] = ["access_token", "refresh_token", "session_key", "recovery_key", "passphrase", "password"];
+
+/// Authorization scheme followed by a token
+const BEARER: &str = "bearer ";
+
+/// Length from which a base64 blob is taken for a key, signatures are 86
+const BLOB_LEN: usize = 100;
+
+/// Start of every recovery key, the base58 of its `0x8b 0x01` prefix
+const RECOVERY_PREFIX: &str = "Es";
+/// Groups of a recovery key, the prefix, 32 key bytes and a parity byte make 48 characters
+const RECOVERY_GROUPS: usize = 12;
+/// Characters in a group of a recovery key
+const RECOVERY_GROUP_LEN: usize = 4;
+
+/// Characters of tokens and base64, `=` isn't one so `key=value` splits
+const fn is_token(byte: u8) -> bool
+	{ byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'+' | b'/') }
+
+/// Characters of base58, `0`, `O`, `I` and `l` are left out
+const fn is_base58(byte: u8) -> bool
+	{ byte.is_ascii_alphanumeric() && !matches!(byte, b'0' | b'O' | b'I' | b'l') }
+
+/// Length of the token at the start of `text`
+fn token_len(text: &str) -> usize
+	{ text.bytes().take_while(|&byte| is_token(byte)).count() }
+
+/// Skips quotes, backslashes of escaped quotes and spaces
+fn skip_quotes(text: &str) -> &str
+	{ text.trim_start_matches(['"', '\'', '\\', ' ']) }
+
+/// `text` starts with `prefix`, ignoring case
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool
+	{ text.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix)) }
+
+/// A `syt_` token, returns the prefix to keep and the length taken
+fn prefixed(text: &str) -> Option<(&str, usize)>
+{
+	let prefix = PREFIXES.into_iter().find(|prefix| text.starts_with(prefix))?;
+	let len = prefix.len() + token_len(text.get(prefix.len()..)?);
+	(len > prefix.len()).then_some((prefix, len))
+}
+
+/// `token` holds `key` anywhere, ignoring case
+fn contains_ignore_case(token: &str, key: &str) -> bool
+	{ token.as_bytes().windows(key.len()).any(|window| window.eq_ignore_ascii_case(key.as_bytes())) }
+
+/// A name holding a secret key followed by its value, returns what to keep before the value and the length taken
+fn keyed(text: &str) -> Option<(&str, usize)>
+{
+	let name = text.get(..token_len(text))?;
+	if !KEYS.into_iter().any(|key| contains_ignore_case(name, key)) { return None }
+	let after_key = skip_quotes(text.get(name.len()..)?);
+	let after_separator = skip_quotes(after_key.strip_prefix(['=', ':'])?);
+	let start = text.len() - after_separator.len();
+
+	let value = after_separator.bytes()
+		.take_while(|byte| !byte.is_ascii_whitespace() && !matches!(byte, b'"' | b'\'' | b'\\' | b'&' | b',' | b';' | b'}' | b')'))
+		.count();
+	if value == 0 { return None }
+	Some((text.get(..start)?, start + value))
+}
+
+/// A `Bearer` token, returns what to keep before the token and the length taken
+fn bearer(text: &str) -> Option<(&str, usize)>
+{
+	if !starts_with_ignore_case(text, BEARER) { return None }
+	let start = BEARER.len();
+	let len = token_len(text.get(start..)?);
+	(len > 0).then_some((text.get(..start)?, start + len))
+}
+
+/// A recovery key, returns the length taken, its groups are separated by single spaces
+fn recovery_key(text: &str) -> Option<usize>
+{
+	if !text.starts_with(RECOVERY_PREFIX) { return None }
+	let bytes = text.as_bytes();
+	let mut len = 0;
+
+	for group in 0..RECOVERY_GROUPS {
+		if group > 0 {
+			if bytes.get(len) != Some(&b' ') { return None }
+			len += 1;
+		}
+		if !bytes.get(len..len + RECOVERY_GROUP_LEN)?.iter().all(|&byte| is_base58(byte)) { return None }
+		len += RECOVERY_GROUP_LEN;
+	}
+
+	// a longer token is something else
+	(!bytes.get(len).is_some_and(|&byte| is_token(byte))).then_some(len)
+}
+
+/// A long base64 blob, random keys have every kind of character
+fn blob(token: &str) -> bool
+{
+	token.len() >= BLOB_LEN
+		&& token.bytes().any(|byte| byte.is_ascii_uppercase())
+		&& token.bytes().any(|byte| byte.is_ascii_lowercase())
+		&& token.bytes().any(|byte| byte.is_ascii_digit())
+}
+
+/**
+ * `text` with the secrets it seems to hold replaced by `[redacted]`
+ *
+ * ```rust
+ * let line = scrub("GET /sync?access_token=syt_YWxpY2U_abcdef_012345&since=s72");
+ * assert_eq!(line, "GET /sync?access_token=[redacted]&since=s72");
+ * ```
+ */
+#[must_use]
+pub fn scrub(text: &str) -> String
+{
+	let mut out = String::with_capacity(text.len());
+	let mut rest = text;
+
+	while !rest.is_empty() {
+		if let Some((keep, len)) = prefixed(rest)
+			.or_else(|| keyed(rest))
+			.or_else(|| bearer(rest))
+			.or_else(|| recovery_key(rest).map(|len| ("", len)))
+		{
+			out.push_str(keep);
+			out.push_str(REDACTED);
+			rest = rest.get(len..).unwrap_or_default();
+			continue;
+		}
+
+		// a token is copied whole, so the patterns only match at its start
+		let len = match token_len(rest) {
+			0 => rest.chars().next().map_or(1, char::len_utf8),
+			len => len,
+		};
+		let (token, tail) = rest.split_at_checked(len).unwrap_or((rest, ""));
+		if blob(token) {
+			out.push_str(REDACTED);
+			rest = tail.trim_start_matches('=');
+		} else {
+			out.push_str(token);
+			rest = tail;
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::scrub;
+
+	#[test]
+	fn synapse_tokens_keep_their_prefix()
+	{
+		assert_eq!(scrub("token syt_YWxpY2U_abcdef_012345 sent"), "token syt_[redacted] sent");
+		assert_eq!(scrub("refresh with syr_abc_DEF_123"), "refresh with syr_[redacted]");
+	}
+
+	#[test]
+	fn keys_in_query_strings_and_json()
+	{
+		assert_eq!(scrub("GET /sync?access_token=abcdef&since=s72"), "GET /sync?access_token=[redacted]&since=s72");
+		assert_eq!(scrub(r#"{"password": "hunter2", "user": "alice"}"#), r#"{"password": "[redacted]", "user": "alice"}"#);
+		assert_eq!(scrub("Session { session_key: \"abc\" }"), "Session { session_key: \"[redacted]\" }");
+		assert_eq!(scrub("PASSWORD=hunter2"), "PASSWORD=[redacted]");
+	}
+
+	#[test]
+	fn keys_inside_longer_names()
+	{
+		assert_eq!(scrub("my_access_token=hunter2hunter2"), "my_access_token=[redacted]");
+		assert_eq!(scrub(r#"{"user_access_token": "abcdefSECRET"}"#), r#"{"user_access_token": "[redacted]"}"#);
+		assert_eq!(scrub("backup_passphrase_v2: correct-horse"), "backup_passphrase_v2: [redacted]");
+	}
+
+	#[test]
+	fn keys_without_value_stay()
+	{
+		assert_eq!(scrub("the access_token expired"), "the access_token expired");
+		assert_eq!(scrub("password=\"\""), "password=\"\"");
+	}
+
+	#[test]
+	fn bearer_headers()
+		{ assert_eq!(scrub("Authorization: Bearer abc_DEF-123"), "Authorization: Bearer [redacted]") }
+
+	#[test]
+	fn recovery_keys()
+	{
+		let key = "EsTc LW2K PGiF wKEA 3As5 g1Uz Rhma gHiJ 1nxs 7xHK 4F3k BV4e";
+		assert_eq!(scrub(&dirty::format!("recovery key {key} saved")), "recovery key [redacted] saved");
+		assert_eq!(scrub(key), "[redacted]");
+
+		// too short, longer groups or letters outside base58 are something else
+		let short = "EsTc LW2K PGiF wKEA 3As5";
+		assert_eq!(scrub(short), short);
+		let long_group = "EsTc LW2K PGiF wKEA 3As5 g1Uz Rhma gHiJ 1nxs 7xHK 4F3k BV4ee";
+		assert_eq!(scrub(long_group), long_group);
+		let not_base58 = "EsTc LW2K PGiF wKEA 3As5 g1Uz Rhma gHiJ 1nxs 7xHK 4F3k BV0e";
+		assert_eq!(scrub(not_base58), not_base58);
+	}
+
+	#[test]
+	fn long_blobs_but_not_signatures()
+	{
+		let blob = "AbCdEfGh0123456789".repeat(6);
+		assert_eq!(scrub(&dirty::format!("key {blob}== end")), "key [redacted] end");
+
+		let signature = "AbCdEfGh0123456789".repeat(4);
+		assert_eq!(scrub(&signature), signature);
+		assert_eq!(scrub("plain text, nothing secret: é ü"), "plain text, nothing secret: é ü");
+	}
+}
@@ -0,0 +1,51 @@
+/*!
+ * Values that must never reach a log, like access tokens, refresh tokens and recovery keys
+ *
+ * ```rust
+ * let token = Secret::new(response.access_token);
+ * log::debug!("logged in with {token}");		// logged in with [redacted]
+ * log::debug!(token; "refreshed");				// refreshed token=[redacted]
+ * client.authorize(token.expose());
+ * ```
+ */
+use core::fmt;
+use log::kv;
+
+/// What is printed instead of a secret
+pub const REDACTED: &str = "[redacted]";
+
+/**
+ * A value printed as `[redacted]` by `Debug`, `Display` and the `log` key-values,
+ * `expose` gives it back where it is really needed
+ *
+ * there is no `PartialEq`, comparing secrets byte by byte tells how much of them matched
+ */
+#[derive(Clone, Copy, Default)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+	/// Hides `value`
+	pub const fn new(value: T) -> Self { Self(value) }
+
+	/// The value, keep it out of `format!` and the `log` macros
+	pub const fn expose(&self) -> &T { &self.0 }
+
+	/// Takes the value out
+	pub fn into_inner(self) -> T { self.0 }
+}
+
+impl<T> From<T> for Secret<T> {
+	fn from(value: T) -> Self { Self(value) }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(REDACTED) }
+}
+
+impl<T> fmt::Display for Secret<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(REDACTED) }
+}
+
+impl<T> kv::ToValue for Secret<T> {
+	fn to_value(&self) -> kv::Value<'_> { kv::Value::from_display(self) }
+}
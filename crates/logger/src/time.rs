@@ -0,0 +1,57 @@
+/*!
+ * UTC timestamps, `2026-01-31T18:04:05.123Z`
+ *
+ * there is no time zone database without libc, so the logs stay in UTC like journald shows them
+ */
+use core::fmt;
+use core::time::Duration;
+
+/// Seconds in a day
+const DAY: u64 = 86_400;
+
+/// A point in time since the unix epoch
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timestamp(Duration);
+
+impl Timestamp {
+	/// The current time, `None` where there is no clock to read
+	pub(crate) fn now() -> Option<Self>
+	{
+		#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+		return dirty::syscall::clock_gettime(dirty::syscall::CLOCK_REALTIME).ok().map(Self);
+
+		#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+		None
+	}
+}
+
+/**
+ * Year, month and day of a day counted from 1970-01-01
+ * <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+ */
+fn civil(since_epoch: u64) -> (u64, u64, u64)
+{
+	// eras of 400 years starting on 0000-03-01, so the leap day ends the year
+	let days = since_epoch + 719_468;
+	let era = days / 146_097;
+	let day_of_era = days % 146_097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	// months counted from march
+	let march = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * march + 2) / 5 + 1;
+	let month = if march < 10 { march + 3 } else { march - 9 };
+	let year = era * 400 + year_of_era + u64::from(month <= 2);
+	(year, month, day)
+}
+
+impl fmt::Display for Timestamp {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+	{
+		let secs = self.0.as_secs();
+		let (year, month, day) = civil(secs / DAY);
+		let time = secs % DAY;
+		write!(f, "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+			time / 3600, time / 60 % 60, time % 60, self.0.subsec_millis())
+	}
+}
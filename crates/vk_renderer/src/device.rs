@@ -0,0 +1,172 @@
+/*!
+ * Picking the GPU
+ * <https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Physical_devices_and_queue_families#:~:text=isDeviceSuitable>
+ *
+ * a device is usable if it has `VK_KHR_swapchain`, formats for the surface and a queue that
+ * draws and presents on it. The usable ones are ranked discrete > integrated > virtual > CPU,
+ * so software drivers like Mesa lavapipe are the last resort on machines without a GPU
+ *
+ * `WT_GPU` picks one by its index or a part of its name, like `WT_GPU=1` or `WT_GPU=llvmpipe`
+ */
+use ash::{khr, vk};
+use ash::vk::{PhysicalDevice, SurfaceKHR};
+use log::{debug, info, warn};
+use dirty::{Error, String, Vec, WResponse};
+use crate::response;
+
+/// Variable overriding the choice
+pub(crate) const ENV: &str = "WT_GPU";
+
+/// A device that can draw on the window
+#[derive(Debug, Clone)]
+pub(crate) struct Candidate {
+	/// The device
+	pub(crate) device: PhysicalDevice,
+	/// Queue family that draws and presents
+	pub(crate) queue_family: u32,
+	/// Position in the list of the driver, what `WT_GPU` takes as a number
+	index: usize,
+	/// Name given by the driver
+	name: String,
+	/// Discrete, integrated, virtual or CPU
+	kind: vk::PhysicalDeviceType,
+}
+
+impl Candidate {
+	/// Higher is better, a real GPU before a software one
+	fn score(&self) -> u32
+	{
+		match self.kind {
+			vk::PhysicalDeviceType::DISCRETE_GPU => 4,
+			vk::PhysicalDeviceType::INTEGRATED_GPU => 3,
+			vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+			vk::PhysicalDeviceType::CPU => 1,
+			_ => 0,
+		}
+	}
+
+	/// `wanted` is the index of the device or a part of its name, ignoring case
+	fn matches(&self, wanted: &str) -> bool
+	{
+		wanted.parse::<usize>().map_or_else(
+			|_| self.name.to_ascii_lowercase().contains(&wanted.to_ascii_lowercase()),
+			|index| index == self.index,
+		)
+	}
+}
+
+/// `device` has `VK_KHR_swapchain`
+fn has_swapchain(instance: &ash::Instance, device: PhysicalDevice) -> bool
+{
+	unsafe { instance.enumerate_device_extension_properties(device) }
+		.is_ok_and(|extensions| {
+			extensions.iter().any(|extension| extension.extension_name_as_c_str() == Ok(vk::KHR_SWAPCHAIN_NAME))
+		})
+}
+
+/// The first queue family of `device` that draws and presents on `surface`
+fn present_queue(
+	instance: &ash::Instance,
+	surface_loader: &khr::surface::Instance,
+	surface: SurfaceKHR,
+	device: PhysicalDevice,
+) -> Option<u32>
+{
+	let queue_families = unsafe { instance.get_physical_device_queue_family_properties(device) };
+
+	(0..).zip(queue_families)
+		.find(|&(index, family)| {
+			family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+				&& unsafe { surface_loader.get_physical_device_surface_support(device, index, surface) }.unwrap_or(false)
+		})
+		.map(|(index, _)| index)
+}
+
+/// `device` if it can draw on `surface`, the reason it can't is logged
+fn candidate(
+	instance: &ash::Instance,
+	surface_loader: &khr::surface::Instance,
+	surface: SurfaceKHR,
+	(index, device): (usize, PhysicalDevice),
+) -> Option<Candidate>
+{
+	let properties = unsafe { instance.get_physical_device_properties(device) };
+	let name = properties.device_name_as_c_str()
+		.map_or_else(|_| String::from("unknown"), |name| name.to_string_lossy().into_owned());
+
+	if !has_swapchain(instance, device) {
+		debug!("GPU {index} `{name}` skipped: no VK_KHR_swapchain");
+		return None;
+	}
+	let has_formats = unsafe { surface_loader.get_physical_device_surface_formats(device, surface) }
+		.is_ok_and(|formats| !formats.is_empty());
+	if !has_formats {
+		debug!("GPU {index} `{name}` skipped: no format for the surface");
+		return None;
+	}
+	let Some(queue_family) = present_queue(instance, surface_loader, surface, device) else {
+		debug!("GPU {index} `{name}` skipped: no queue presents on the surface");
+		return None;
+	};
+
+	Some(Candidate { device, queue_family, index, name, kind: properties.device_type })
+}
+
+/// The value of `WT_GPU`, there is no environment to read on every platform yet
+fn wanted() -> Option<String>
+{
+	#[cfg(target_os = "linux")]
+	return dirty::env::var(ENV).filter(|value| !value.is_empty());
+
+	#[cfg(not(target_os = "linux"))]
+	None
+}
+
+/**
+ * The device to draw on `surface` with: the one `WT_GPU` names, or the best ranked,
+ * the first listed by the driver among equals
+ *
+ * # Errors
+ *
+ * `MissingDependencies` if no device can present on the surface
+ */
+pub(crate) fn select(
+	instance: &ash::Instance,
+	surface_loader: &khr::surface::Instance,
+	surface: SurfaceKHR,
+) -> Result<Candidate, Error>
+{
+	/**
+	 * C++
+	 * `uint32_t deviceCount = 0;`
+	 * `vkEnumeratePhysicalDevices(instance, &deviceCount, nullptr);`
+	 * same idea from ours, but ash offers less control, no need to implement verifications of support
+	 */
+	let physical_devices = unsafe { instance.enumerate_physical_devices() }
+		.map_err(|result| response(result, "couldn't list the vulkan devices"))?;
+
+	let candidates: Vec<Candidate> = (0..).zip(physical_devices)
+		.filter_map(|device| candidate(instance, surface_loader, surface, device))
+		.collect();
+	for candidate in &candidates {
+		debug!("GPU {} `{}` ({:?}): score {}", candidate.index, candidate.name, candidate.kind, candidate.score());
+	}
+
+	if let Some(wanted) = wanted() {
+		match candidates.iter().find(|candidate| candidate.matches(&wanted)) {
+			Some(candidate) => {
+				info!("{ENV}={wanted}: using `{}`", candidate.name);
+				return Ok(candidate.clone());
+			},
+			None => warn!("{ENV}={wanted} matches no usable GPU, picking one"),
+		}
+	}
+
+	// `max_by_key` keeps the last of equals, reversed it is the first the driver listed
+	let best = candidates.into_iter()
+		.rev()
+		.max_by_key(Candidate::score)
+		.ok_or_else(|| Error::new(WResponse::MissingDependencies).with_message("no GPU can draw on the window"))?;
+	info!("using `{}` ({:?})", best.name, best.kind);
+	Ok(best)
+}
@@ -0,0 +1,78 @@
+/*!
+ * What a frame needs while the GPU works on it, `FRAMES_IN_FLIGHT` of them take turns
+ * so the next frame is recorded while the last one is drawn
+ * <https://vulkan-tutorial.com/Drawing_a_triangle/Drawing/Frames_in_flight>
+ */
+use ash::vk;
+use dirty::{Error, Vec};
+use crate::response;
+
+/// Frames recorded ahead of the GPU
+pub(crate) const FRAMES_IN_FLIGHT: u32 = 2;
+
+/// Command buffer and synchronization of one frame in flight
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Frame {
+	/// Recorded between `begin_frame` and `end_frame`
+	pub(crate) command_buffer: vk::CommandBuffer,
+	/// Signaled when the acquired image can be drawn on
+	pub(crate) image_available: vk::Semaphore,
+	/// Signaled when the GPU is done with the frame, created signaled so the first wait returns
+	pub(crate) in_flight: vk::Fence,
+}
+
+/**
+ * Creates the frames, their command buffers come from `pool`
+ *
+ * # Errors
+ *
+ * `OutOfBounds` without memory left, the frames made before the failure are destroyed
+ */
+pub(crate) fn create(device: &ash::Device, pool: vk::CommandPool) -> Result<Vec<Frame>, Error>
+{
+	let allocate_info = vk::CommandBufferAllocateInfo::default()
+		.command_pool(pool)
+		.level(vk::CommandBufferLevel::PRIMARY)
+		.command_buffer_count(FRAMES_IN_FLIGHT);
+	let command_buffers = unsafe { device.allocate_command_buffers(&allocate_info) }
+		.map_err(|result| response(result, "couldn't allocate the command buffers"))?;
+
+	let mut frames = Vec::new();
+	for command_buffer in command_buffers {
+		match sync_objects(device) {
+			Ok((image_available, in_flight)) => frames.push(Frame { command_buffer, image_available, in_flight }),
+			Err(e) => {
+				destroy(device, &frames);
+				return Err(e);
+			},
+		}
+	}
+	Ok(frames)
+}
+
+/// The semaphore and the fence of a frame
+fn sync_objects(device: &ash::Device) -> Result<(vk::Semaphore, vk::Fence), Error>
+{
+	let semaphore = unsafe { device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None) }
+		.map_err(|result| response(result, "couldn't create a semaphore"))?;
+
+	let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
+	match unsafe { device.create_fence(&fence_info, None) } {
+		Ok(fence) => Ok((semaphore, fence)),
+		Err(result) => {
+			unsafe { device.destroy_semaphore(semaphore, None) };
+			Err(response(result, "couldn't create a fence"))
+		},
+	}
+}
+
+/// Destroys the semaphores and fences, the command buffers go with their pool
+pub(crate) fn destroy(device: &ash::Device, frames: &[Frame])
+{
+	for frame in frames {
+		unsafe {
+			device.destroy_semaphore(frame.image_available, None);
+			device.destroy_fence(frame.in_flight, None);
+		}
+	}
+}
@@ -30,34 +30,105 @@
 )]
 #![doc = include_str!("../README.md")]
 
-use ash::Instance;
+mod device;
+mod frame;
+mod swapchain;
+
+use ash::{khr, Instance};
 use ash::vk::{self, SurfaceKHR, RenderPass, PhysicalDevice};
 use log::debug;
 #[allow(unused)]
-use core::{slice, ptr::NonNull, error::Error};
-use dirty::{Box, void, f8, SurfaceWrapper};
+use core::{slice, ptr::NonNull};
+use dirty::{void, f8, Color, Detail, Error, Handle, NativeWindow, RawWindow, SurfaceWrapper, Vec, WResponse};
+use frame::Frame;
+use swapchain::Swapchain;
+
+/// Maps a failed `VkResult` into the closest `WResponse`, the raw value is kept as detail
+fn response(result: vk::Result, message: &'static str) -> Error
+{
+	let kind = match result {
+		vk::Result::ERROR_INITIALIZATION_FAILED
+		| vk::Result::ERROR_INCOMPATIBLE_DRIVER
+		| vk::Result::ERROR_LAYER_NOT_PRESENT
+		| vk::Result::ERROR_EXTENSION_NOT_PRESENT => WResponse::MissingDependencies,
+		vk::Result::ERROR_FEATURE_NOT_PRESENT => WResponse::BinarySpecificLimitation,
+		vk::Result::ERROR_OUT_OF_HOST_MEMORY
+		| vk::Result::ERROR_OUT_OF_DEVICE_MEMORY
+		| vk::Result::ERROR_TOO_MANY_OBJECTS => WResponse::OutOfBounds,
+		vk::Result::ERROR_DEVICE_LOST | vk::Result::ERROR_SURFACE_LOST_KHR => WResponse::ConnectionLost,
+		vk::Result::ERROR_NATIVE_WINDOW_IN_USE_KHR => WResponse::ChannelInUse,
+		_ => WResponse::UnexpectedError,
+	};
+	Error::new(kind).with_message(message).with_detail(Detail::Vulkan(result.as_raw()))
+}
 
-mod wrapper;
-use wrapper::Wrapper;
+/// Turns a window size into an extent, negative sizes are empty
+fn window_extent(size: (f64, f64)) -> vk::Extent2D
+	{ vk::Extent2D { width: size.0 as u32, height: size.1 as u32 } }
+
+/// The format of the images, 8 bit sRGB if there is one
+fn surface_format(formats: &[vk::SurfaceFormatKHR]) -> Option<vk::SurfaceFormatKHR>
+{
+	let srgb = [vk::Format::B8G8R8A8_SRGB, vk::Format::R8G8B8A8_SRGB];
+	formats.iter()
+		.find(|format| srgb.contains(&format.format) && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR)
+		.or_else(|| formats.first())
+		.copied()
+}
 
 /// Default Renderer struct
 #[allow(dead_code)]
 pub struct Renderer {
 	/// Vulkan Surface
 	surface: SurfaceKHR,
+	/// Functions of `VK_KHR_surface`
+	surface_loader: khr::surface::Instance,
+	/// Format of the swapchain images, the render pass is made for it
+	format: vk::SurfaceFormatKHR,
 	renderpass: RenderPass,
+	/// Functions of `VK_KHR_swapchain`
+	swapchain_loader: khr::swapchain::Device,
+	/// `None` while the window has no area
+	swapchain: Option<Swapchain>,
+	/// Size of the window, the surface may not know it
+	size: vk::Extent2D,
+	/// The swapchain no longer fits the window, it is made again before the next frame
+	outdated: bool,
+	/// Owns the command buffers of the frames
+	command_pool: vk::CommandPool,
+	/// `frame::FRAMES_IN_FLIGHT` frames taking turns
+	frames: Vec<Frame>,
+	/// Index in `frames` of the next frame
+	current: usize,
+	/// Image acquired by `begin_frame`, presented by `end_frame`
+	acquired: Option<u32>,
+	/// What every frame starts with
+	clear_color: Color,
+	/// Draws and presents
+	queue: vk::Queue,
+	/// The GPU `device` was created on
+	physical_device: PhysicalDevice,
 	device: ash::Device,
 	instance: Instance,
+	/// The loaded vulkan library, the other objects are useless without it
+	entry: ash::Entry,
+	/// The window the surface was created on, the handle keeps it open until the surface is destroyed
+	window: Handle<NativeWindow>,
 }
 
 /// The rendring interface
 impl Renderer {
-	/// Creates a new Vulkan render
+	/// Creates a new Vulkan render for a window of `size`, the window stays open as long as the renderer
 	/// this will be our `initVulkan()` from the tutorial
 	/// <https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Base_code#:~:text=initVulkan()>
-	pub fn new(surface_backend: *mut void) -> Result<Renderer, Box<dyn Error>>
+	///
+	/// # Errors
+	///
+	/// `MissingDependencies` if the vulkan loader or a driver is missing,
+	/// `BinarySpecificLimitation` if this build can't draw on the kind of window,
+	/// the `VkResult` of the failed call is kept as detail
+	pub fn new(window: Handle<NativeWindow>, size: (f64, f64)) -> Result<Renderer, Error>
 	{
-		let backend: Wrapper = void::from_handle(surface_backend);
 		debug!("Creating new vulkan render");
 
 		/**
@@ -66,7 +137,9 @@ impl Renderer {
 		 * the solution for that problem is packaging the necessary files (dylib) inside the .app
 		 * <https://stackoverflow.com/questions/39204908/how-to-check-release-debug-builds-using-cfg-in-rust>
 		 */
-		let entry = unsafe { ash::Entry::load()? };
+		let entry = unsafe { ash::Entry::load() }.map_err(|_| {
+			Error::new(WResponse::MissingDependencies).with_message("couldn't load the vulkan loader")
+		})?;
 
 		/**
 		 * Create Instance
@@ -81,7 +154,9 @@ impl Renderer {
 			vk::KHR_PORTABILITY_ENUMERATION_NAME.as_ptr(),
 			vk::KHR_SURFACE_NAME.as_ptr(),
 			#[cfg(target_os = "macos")]
-			vk::EXT_METAL_SURFACE_NAME.as_ptr()
+			vk::EXT_METAL_SURFACE_NAME.as_ptr(),
+			#[cfg(target_os = "linux")]
+			vk::KHR_WAYLAND_SURFACE_NAME.as_ptr(),
 		];
 
 		/* */
@@ -99,112 +174,102 @@ impl Renderer {
 			 * `VK_ERROR_INCOMPATIBLE_DRIVER`:
 			 * <https://vulkan-tutorial.com/en/Drawing_a_triangle/Setup/Instance#:~:text=Encountered%20VK%5FERROR%5FINCOMPATIBLE%5FDRIVER>
 			 */
-			entry.create_instance(&instance_desc, None)?
+			entry.create_instance(&instance_desc, None)
+				.map_err(|result| response(result, "couldn't create the vulkan instance"))?
 		};
 
-		/**
-		 * Handlers
-		 */
-		let device = Self::get_device(&instance)?;
-		//let _logic = Self::create_logic_device();
-		let renderpass = Self::render_pass(&device)?;
-
 		/** <https://github.com/ash-rs/ash/blob/master/ash-examples/src/lib.rs>
 		 * The Headless backend will be used to implement tests
 		 * the repo uses `let surface_loader = surface::Instance::load(&entry, &instance);`
 		 * but the way it is created is different, using `SurfaceFactory`, for that I would need winit
 		 */
 
-		#[cfg(target_os = "macos")]
-		let Some(nn_view) =
-			NonNull::new(backend.ns_view)
-		else
-			{ return Err(Box::from("view shouldn't be null")) };
-
-		#[cfg(target_os = "macos")]
-		let surface = Self::new_surface(&instance, &entry, nn_view.cast())?;
-
-		#[cfg(target_os = "linux")]
-		let surface = Self::new_surface(&instance, &entry, backend.wl_surface, backend.wl_display)?;
-
-		#[cfg(target_os = "windows")]
-		let view: *mut void = todo!();
+		let created = match window.raw() {
+			#[cfg(target_os = "macos")]
+			RawWindow::AppKit { ns_view } => match NonNull::new(ns_view) {
+				Some(nn_view) => Self::new_surface(&instance, &entry, nn_view),
+				None => Err(Error::new(WResponse::InvalidRequest).with_message("view shouldn't be null")),
+			},
+			#[cfg(target_os = "linux")]
+			RawWindow::Wayland { display, surface } =>
+				Self::new_surface(&instance, &entry, surface, display),
+			_ => Err(
+				Error::new(WResponse::BinarySpecificLimitation).with_message("this build can't render on the window")
+			),
+		};
+		let surface = created.inspect_err(|_| unsafe { instance.destroy_instance(None) })?;
 
-		Ok(Renderer {
+		/**
+		 * Handlers
+		 * the device is picked after the surface, its queue has to present on it
+		 */
+		let surface_loader = khr::surface::Instance::new(&entry, &instance);
+		let (physical_device, queue_family, device) = Self::get_device(&instance, &surface_loader, surface)
+			.inspect_err(|_| unsafe {
+				surface_loader.destroy_surface(surface, None);
+				instance.destroy_instance(None);
+			})?;
+		let queue = unsafe { device.get_device_queue(queue_family, 0) };
+
+		// from here a failure drops the renderer, its `Drop` skips the null handles
+		let mut renderer = Renderer {
 			surface,
-			renderpass,
+			surface_loader,
+			format: vk::SurfaceFormatKHR::default(),
+			renderpass: RenderPass::null(),
+			swapchain_loader: khr::swapchain::Device::new(&instance, &device),
+			swapchain: None,
+			size: window_extent(size),
+			outdated: false,
+			command_pool: vk::CommandPool::null(),
+			frames: Vec::new(),
+			current: 0,
+			acquired: None,
+			clear_color: Color::BLACK,
+			queue,
+			physical_device,
 			device,
 			instance,
-		})
+			entry,
+			window,
+		};
+
+		let formats = unsafe {
+			renderer.surface_loader.get_physical_device_surface_formats(physical_device, surface)
+		}.map_err(|result| response(result, "couldn't list the surface formats"))?;
+		renderer.format = surface_format(&formats).ok_or_else(|| {
+			Error::new(WResponse::MissingDependencies).with_message("the surface has no format")
+		})?;
+		renderer.renderpass = Self::render_pass(&renderer.device, renderer.format.format)?;
+
+		let pool_info = vk::CommandPoolCreateInfo::default()
+			.flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+			.queue_family_index(queue_family);
+		renderer.command_pool = unsafe { renderer.device.create_command_pool(&pool_info, None) }
+			.map_err(|result| response(result, "couldn't create the command pool"))?;
+		renderer.frames = frame::create(&renderer.device, renderer.command_pool)?;
+
+		renderer.recreate_swapchain()?;
+		Ok(renderer)
 	}
 
 	/// Returns the Wrapper for the `SurfaceKHR`
 	pub fn get_surface(&self) -> SurfaceWrapper
 		{ SurfaceWrapper::new(self.surface) }
 
-	/**
-	 * in the future, implement a rate to pick the best device avaliable
-	 * get the device properties and check it can be used
-	 * <https://docs.vulkan.org/tutorial/latest/03_Drawing_a_triangle/00_Setup/03_Physical_devices_and_queue_families.html>
-	 * <https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Physical_devices_and_queue_families#:~:text=isDeviceSuitable>
-	 */
-	#[inline]
-	fn is_device_suitable(instance: &ash::Instance, device: PhysicalDevice) -> bool
-	{
-		let prop = unsafe { instance.get_physical_device_properties(device) };
-		let feat = unsafe { instance.get_physical_device_features(device) };
-
-		/**
-		 * `INTEGRATED_GPU` will be the case on `MacOS`
-		 * for some reason the constant value for that is not working, so im using `.as_raw() == 1`
-		 * "associated item not found in `PhysicalDevice`"
-		 */
-		if feat.geometry_shader == dirty::TRUE
-			&& prop.device_type == vk::PhysicalDeviceType::DISCRETE_GPU
-			|| prop.device_type.as_raw() == 1 // this represents Self(1) or INTEGRATED_GPU
-		{ return true; }
-
-		false
-	}
-
 	/**
 	 * <https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Physical_devices_and_queue_families>
-	 * this will get the first graphics card avaliable
+	 * creates the logical device on the GPU picked by `device::select`,
+	 * with the queue family used to draw
 	 */
-	fn get_device(instance: &ash::Instance) -> Result<ash::Device, Box<dyn Error>>
+	fn get_device(
+		instance: &ash::Instance,
+		surface_loader: &khr::surface::Instance,
+		surface: SurfaceKHR,
+	) -> Result<(PhysicalDevice, u32, ash::Device), Error>
 	{
-		/**
-		 * C++
-		 * `uint32_t deviceCount = 0;`
-		 * `vkEnumeratePhysicalDevices(instance, &deviceCount, nullptr);`
-		 * same idea from ours, but ash offers less control, no need to implement verifications of support
-		 */
-		let physical_devices = unsafe { instance.enumerate_physical_devices()? };
-
-		let mut maybe_selected_device: Option<PhysicalDevice> = None;
-		for device in physical_devices {
-			if !Self::is_device_suitable(instance, device) { continue }
-			debug!("found suitable device `{device:?}`!");
-			maybe_selected_device = Some(device);
-			break
-		}
-
-		let Some(selected_device) = maybe_selected_device else {
-			return Err(Box::from("failed to find a suitable GPU!"))
-		};
-
-		let queue_families = unsafe {
-			instance.get_physical_device_queue_family_properties(selected_device)
-		};
-
-		let graphics_queue_index: u32 = u32::try_from(
-			queue_families
-				.iter()
-				.enumerate()
-				.find(|(_, q)| q.queue_flags.contains(vk::QueueFlags::GRAPHICS))
-				.ok_or("no graphics queue found")?
-				.0
-		)?;
+		let candidate = device::select(instance, surface_loader, surface)?;
+		let (selected_device, graphics_queue_index) = (candidate.device, candidate.queue_family);
 
 		let queue_priority = 1.0;
 		let binding = [queue_priority];
@@ -224,9 +289,9 @@ impl Renderer {
 			.queue_create_infos(slice::from_ref(&queue_info))
 			.enabled_features(&device_features);
 
-		Ok(unsafe {
-			instance.create_device(selected_device, &device_create_info, None)?
-		})
+		let device = unsafe { instance.create_device(selected_device, &device_create_info, None) }
+			.map_err(|result| response(result, "couldn't create the logical device"))?;
+		Ok((selected_device, graphics_queue_index, device))
 	}
 
 	/// Creates a new surface
@@ -235,35 +300,31 @@ impl Renderer {
 		instance: &Instance,
 		entry: &ash::Entry,
 		window: NonNull<void>
-	) -> Result<SurfaceKHR, Box<dyn Error>>
+	) -> Result<SurfaceKHR, Error>
 	{
-		use objc2::{rc::Retained, msg_send, ClassType};
+		use objc2::{rc::Retained, msg_send};
 		use objc2_quartz_core::CALayer;
 		use objc2_foundation::NSObject;
 		use ash::ext::metal_surface;
 		debug!("creating metal surface");
 
-		let ns_view: &NSObject = void::from_handle(window.as_ptr());
+		// the view is owned by the window, it outlives the surface
+		let ns_view: &NSObject = unsafe { window.cast::<NSObject>().as_ref() };
 		let _: () = unsafe { msg_send![ns_view, setWantsLayer: true] };
 
 		let Some(layer_some): Option<Retained<CALayer>> = (
 			unsafe { msg_send![ns_view, layer] }
 		) else {
-			return Err(Box::from("failed making the view layer-backed"))
+			return Err(Error::new(WResponse::UnexpectedError).with_message("failed making the view layer-backed"))
 		};
-		let layer = void::to_handle(&mut layer_some.as_super());
+		let layer = Retained::as_ptr(&layer_some);
 
 		let surface_desc = vk::MetalSurfaceCreateInfoEXT::default()
 			.layer(layer.cast::<core::ffi::c_void>());
 		let surface = metal_surface::Instance::new(entry, instance);
 
-		let Ok(metal_surface) = (
-			unsafe { surface.create_metal_surface(&surface_desc, None) }
-		) else {
-			return Err(Box::from("couldn't create metal surface"))
-		};
-
-		Ok(metal_surface)
+		unsafe { surface.create_metal_surface(&surface_desc, None) }
+			.map_err(|result| response(result, "couldn't create metal surface"))
 	}
 
 	// WARN: this is just a model and is not complete. The code will fail.
@@ -274,7 +335,7 @@ impl Renderer {
 		entry: &ash::Entry,
 		wl_surface: *mut void,
 		wl_display: *mut void,
-	) -> Result<SurfaceKHR, Box<dyn Error>>
+	) -> Result<SurfaceKHR, Error>
 	{
 		debug!("creating linux wayland surface");
 		use ash::{khr::wayland_surface, vk::wl_display};
@@ -289,9 +350,8 @@ impl Renderer {
 			.surface(wl_surface as *mut core::ffi::c_void);
 
 		let surface = wayland_surface::Instance::new(entry, instance);
-		let result = unsafe { surface.create_wayland_surface(&surface_desc, None)? };
-
-		Ok(result)
+		unsafe { surface.create_wayland_surface(&surface_desc, None) }
+			.map_err(|result| response(result, "couldn't create the wayland surface"))
 	}
 
 	/// Creates a new surface
@@ -300,42 +360,43 @@ impl Renderer {
 		instance: &Instance,
 		entry: &ash::Entry,
 		window: NonNull<void>
-	) -> Result<SurfaceKHR, Box<dyn Error>>
+	) -> Result<SurfaceKHR, Error>
 	{
 		todo!();
 	}
 
-	/// Creates a new vulkan renderpass
+	/// Creates a new vulkan renderpass drawing on images of `format`
 	/// here's an oficial example: <https://github.com/ash-rs/ash/blob/master/ash-examples/src/bin/texture.rs>
-	pub fn render_pass(device: &ash::Device) -> Result<RenderPass, Box<dyn Error>>
+	///
+	/// # Errors
+	///
+	/// The `VkResult` of `vkCreateRenderPass` as detail
+	pub fn render_pass(device: &ash::Device, format: vk::Format) -> Result<RenderPass, Error>
 	{
-		// tbh, I have no idea what does this do
+		/**
+		 * a single color attachment, the swapchain image: cleared when the pass starts,
+		 * kept when it ends and left ready to be presented
+		 * the depth attachment comes back with the first pipeline that needs it, with an image behind it
+		 */
 		let renderpass_attachments = [
 			vk::AttachmentDescription {
+				format,
 				samples: vk::SampleCountFlags::TYPE_1,
 				load_op: vk::AttachmentLoadOp::CLEAR,
 				store_op: vk::AttachmentStoreOp::STORE,
+				stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+				stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+				initial_layout: vk::ImageLayout::UNDEFINED,
 				final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
 				..Default::default()
 			},
-			vk::AttachmentDescription {
-				format: vk::Format::D16_UNORM,
-				samples: vk::SampleCountFlags::TYPE_1,
-				load_op: vk::AttachmentLoadOp::CLEAR,
-				initial_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
-				final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
-				..Default::default()
-			},
 		];
 
 		let color_attachment_refs = [vk::AttachmentReference {
 			attachment: 0,
 			layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
 		}];
-		let depth_attachment_ref = vk::AttachmentReference {
-			attachment: 1,
-			layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
-		};
+		// waits for the presentation engine to release the image before writing to it
 		let dependencies = [vk::SubpassDependency {
 			src_subpass: vk::SUBPASS_EXTERNAL,
 			src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
@@ -347,7 +408,6 @@ impl Renderer {
 
 		let subpass = vk::SubpassDescription::default()
 			.color_attachments(&color_attachment_refs)
-			.depth_stencil_attachment(&depth_attachment_ref)
 			.pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS);
 
 		let renderpass_create_info = vk::RenderPassCreateInfo::default()
@@ -359,23 +419,231 @@ impl Renderer {
 			match device
 				.create_render_pass(&renderpass_create_info, None) {
 					Ok(d) => d,
-					Err(e) => return Err(Box::new(e)),
+					Err(result) => return Err(response(result, "couldn't create the render pass")),
 				}
 		};
 
 		Ok(renderpass)
 	}
 
-	// for now returns a generic value
-	/// Returns the surface size
+	/// Returns the surface size, the size of the images drawn on, `(0, 0)` while the window has no area
 	#[must_use]
-	pub fn get_surface_size(&self) -> (f32, f32) { (0.0, 0.0) }
+	pub fn get_surface_size(&self) -> (f32, f32)
+	{
+		self.swapchain.as_ref()
+			.map_or((0.0, 0.0), |swapchain| (swapchain.extent.width as f32, swapchain.extent.height as f32))
+	}
+
+	/// Color the frames are cleared with before anything is drawn
+	pub fn set_clear_color(&mut self, color: Color) { self.clear_color = color }
+
+	/// Takes the new size of the window, the swapchain is made again before the next frame
+	pub fn resize(&mut self, size: (f64, f64))
+	{
+		let extent = window_extent(size);
+		if extent == self.size { return }
+		self.size = extent;
+		self.outdated = true;
+	}
+
+	/**
+	 * Replaces the swapchain by one that fits the window
+	 * <https://vulkan-tutorial.com/Drawing_a_triangle/Swap_chain_recreation>
+	 *
+	 * # Errors
+	 *
+	 * Same as `Swapchain::new`, the renderer has no swapchain then and tries again next frame
+	 */
+	fn recreate_swapchain(&mut self) -> Result<(), Error>
+	{
+		// the old images may still be drawn or presented
+		unsafe { self.device.device_wait_idle() }
+			.map_err(|result| response(result, "couldn't wait for the device"))?;
+
+		self.outdated = false;
+		let old = self.swapchain.take();
+		let created = Swapchain::new(self, old.as_ref().map_or(vk::SwapchainKHR::null(), |swapchain| swapchain.handle));
+		if let Some(swapchain) = old { swapchain.destroy(self) }
+
+		self.swapchain = created?;
+		if self.swapchain.is_none() { debug!("the window has no area, frames are skipped") }
+		Ok(())
+	}
+
+	/// The clear value of the color attachment, swapchains in sRGB take linear colors
+	fn clear_value(&self) -> vk::ClearValue
+	{
+		let srgb = [vk::Format::B8G8R8A8_SRGB, vk::Format::R8G8B8A8_SRGB].contains(&self.format.format);
+		let (r, g, b, a) = if srgb { self.clear_color.to_linear() } else { self.clear_color.to_default() };
+		vk::ClearValue { color: vk::ClearColorValue { float32: [r as f32, g as f32, b as f32, a as f32] } }
+	}
+
+	/**
+	 * Starts a frame: waits for its turn, acquires an image and starts the render pass on it,
+	 * the returned command buffer records what is drawn until `end_frame`
+	 *
+	 * `None` means there is nothing to draw on this time, the window has no area or the
+	 * swapchain was out of date and was made again; skip the frame without calling `end_frame`
+	 *
+	 * ```rust
+	 * if let Some(commands) = renderer.begin_frame()? {
+	 * 	draw(commands);
+	 * 	renderer.end_frame()?;
+	 * }
+	 * ```
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if the last frame wasn't ended, `ConnectionLost` if the device or
+	 * the surface is lost, the `VkResult` of the failed call is kept as detail
+	 */
+	pub fn begin_frame(&mut self) -> Result<Option<vk::CommandBuffer>, Error>
+	{
+		if self.acquired.is_some() {
+			return Err(Error::new(WResponse::InvalidRequest).with_message("the last frame wasn't ended"));
+		}
+		if self.outdated || self.swapchain.is_none() { self.recreate_swapchain()? }
+
+		let Some(swapchain) = self.swapchain.as_ref() else { return Ok(None) };
+		let frame = *self.frames.get(self.current).ok_or_else(|| {
+			Error::new(WResponse::UnexpectedError).with_message("the renderer has no frames")
+		})?;
+
+		unsafe { self.device.wait_for_fences(&[frame.in_flight], true, u64::MAX) }
+			.map_err(|result| response(result, "couldn't wait for the frame"))?;
+
+		let acquired = unsafe {
+			self.swapchain_loader.acquire_next_image(swapchain.handle, u64::MAX, frame.image_available, vk::Fence::null())
+		};
+		let index = match acquired {
+			// a suboptimal swapchain can still present, it is made again after this frame
+			Ok((index, suboptimal)) => {
+				self.outdated |= suboptimal;
+				index
+			},
+			Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+				self.recreate_swapchain()?;
+				return Ok(None);
+			},
+			Err(result) => return Err(response(result, "couldn't acquire a swapchain image")),
+		};
+		let Some(&framebuffer) = usize::try_from(index).ok().and_then(|slot| swapchain.framebuffers.get(slot)) else {
+			self.skip_acquired(frame);
+			return Err(Error::new(WResponse::OutOfBounds).with_message("the swapchain gave an unknown image"));
+		};
+
+		let begin_info = vk::CommandBufferBeginInfo::default()
+			.flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+		let clear_values = [self.clear_value()];
+		let pass_info = vk::RenderPassBeginInfo::default()
+			.render_pass(self.renderpass)
+			.framebuffer(framebuffer)
+			.render_area(vk::Rect2D { offset: vk::Offset2D::default(), extent: swapchain.extent })
+			.clear_values(&clear_values);
+		let recording = unsafe {
+			self.device.reset_command_buffer(frame.command_buffer, vk::CommandBufferResetFlags::empty())
+				.map_err(|result| response(result, "couldn't reset the command buffer"))
+				.and_then(|()| self.device.begin_command_buffer(frame.command_buffer, &begin_info)
+					.map_err(|result| response(result, "couldn't begin the command buffer")))
+		};
+		if let Err(e) = recording {
+			self.skip_acquired(frame);
+			return Err(e);
+		}
+		unsafe { self.device.cmd_begin_render_pass(frame.command_buffer, &pass_info, vk::SubpassContents::INLINE) };
+
+		self.acquired = Some(index);
+		Ok(Some(frame.command_buffer))
+	}
+
+	/**
+	 * Ends the frame started by `begin_frame`, submits it and presents the image,
+	 * the swapchain is made again if it no longer fits the window
+	 *
+	 * # Errors
+	 *
+	 * `InvalidRequest` if no frame was started, `ConnectionLost` if the device or
+	 * the surface is lost, the `VkResult` of the failed call is kept as detail
+	 */
+	pub fn end_frame(&mut self) -> Result<(), Error>
+	{
+		let Some(index) = self.acquired.take() else {
+			return Err(Error::new(WResponse::InvalidRequest).with_message("no frame was started"));
+		};
+		let (Some(frame), Some(swapchain)) = (self.frames.get(self.current).copied(), self.swapchain.as_ref()) else {
+			return Err(Error::new(WResponse::UnexpectedError).with_message("the frame lost its swapchain"));
+		};
+		let Some(&render_finished) = usize::try_from(index).ok().and_then(|slot| swapchain.render_finished.get(slot)) else {
+			self.skip_acquired(frame);
+			return Err(Error::new(WResponse::OutOfBounds).with_message("the swapchain gave an unknown image"));
+		};
+
+		unsafe { self.device.cmd_end_render_pass(frame.command_buffer) };
+		if let Err(result) = unsafe { self.device.end_command_buffer(frame.command_buffer) } {
+			self.skip_acquired(frame);
+			return Err(response(result, "couldn't record the frame"));
+		}
+
+		let wait = [frame.image_available];
+		let stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+		let commands = [frame.command_buffer];
+		let signal = [render_finished];
+		let submit = vk::SubmitInfo::default()
+			.wait_semaphores(&wait)
+			.wait_dst_stage_mask(&stages)
+			.command_buffers(&commands)
+			.signal_semaphores(&signal);
+		// reset only right before the submit, a frame failing earlier leaves it signaled for the next wait
+		if let Err(result) = unsafe { self.device.reset_fences(&[frame.in_flight]) } {
+			self.skip_acquired(frame);
+			return Err(response(result, "couldn't reset the frame fence"));
+		}
+		if let Err(result) = unsafe { self.device.queue_submit(self.queue, &[submit], frame.in_flight) } {
+			// a batch without commands signals the fence again and unsignals `image_available`,
+			// the next frame would wait on them forever otherwise
+			let skipped = vk::SubmitInfo::default()
+				.wait_semaphores(&wait)
+				.wait_dst_stage_mask(&stages);
+			let _ = unsafe { self.device.queue_submit(self.queue, &[skipped], frame.in_flight) };
+			self.outdated = true;
+			return Err(response(result, "couldn't submit the frame"));
+		}
 
-	/// Stop the rendering and cleanup everything
-	#[allow(unused)]
-	pub fn cleanup(&self)
+		let swapchains = [swapchain.handle];
+		let indices = [index];
+		let present_info = vk::PresentInfoKHR::default()
+			.wait_semaphores(&signal)
+			.swapchains(&swapchains)
+			.image_indices(&indices);
+		let presented = unsafe { self.swapchain_loader.queue_present(self.queue, &present_info) };
+
+		self.current = self.current.wrapping_add(1).checked_rem(self.frames.len()).unwrap_or(0);
+		match presented {
+			Ok(suboptimal) => self.outdated |= suboptimal,
+			Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => self.outdated = true,
+			Err(result) => return Err(response(result, "couldn't present the frame")),
+		}
+		if self.outdated { self.recreate_swapchain()? }
+		Ok(())
+	}
+
+	/**
+	 * Gives up the image acquired for `frame` when it won't be submitted: `image_available` stays
+	 * signaled after the acquire and the next acquire of the frame needs it unsignaled, so an empty
+	 * batch waits on it and the queue is drained; the image itself is released with the swapchain
+	 */
+	fn skip_acquired(&mut self, frame: Frame)
 	{
-		unsafe { self.instance.destroy_instance(None); }
+		let wait = [frame.image_available];
+		let stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+		let submit = vk::SubmitInfo::default()
+			.wait_semaphores(&wait)
+			.wait_dst_stage_mask(&stages);
+		unsafe {
+			let _ = self.device.queue_submit(self.queue, &[submit], vk::Fence::null());
+			let _ = self.device.queue_wait_idle(self.queue);
+		}
+		self.outdated = true;
 	}
 
 	// TODO: make this work (yes, I know it's ugly)
@@ -428,3 +696,20 @@ impl Renderer {
 		}
 	}*/
 }
+
+/// Stop the rendering and cleanup everything, children before their parents
+impl Drop for Renderer {
+	fn drop(&mut self)
+	{
+		unsafe {
+			let _ = self.device.device_wait_idle();
+			if let Some(swapchain) = self.swapchain.take() { swapchain.destroy(self) }
+			frame::destroy(&self.device, &self.frames);
+			self.device.destroy_command_pool(self.command_pool, None);
+			self.device.destroy_render_pass(self.renderpass, None);
+			self.device.destroy_device(None);
+			self.surface_loader.destroy_surface(self.surface, None);
+			self.instance.destroy_instance(None);
+		}
+	}
+}
@@ -0,0 +1,173 @@
+/*!
+ * The images presented on the window, with what is needed to draw into them
+ * <https://vulkan-tutorial.com/Drawing_a_triangle/Presentation/Swap_chain>
+ *
+ * a swapchain only fits one size of window, `Renderer` builds a new one passing the old
+ * as `old_swapchain` whenever the window changes
+ */
+use ash::vk;
+use dirty::{Error, Vec};
+use crate::{response, Renderer};
+
+/// A swapchain and the objects made for each of its images
+pub(crate) struct Swapchain {
+	/// The swapchain itself
+	pub(crate) handle: vk::SwapchainKHR,
+	/// Size of the images
+	pub(crate) extent: vk::Extent2D,
+	/// Views of the images, owned by the swapchain
+	views: Vec<vk::ImageView>,
+	/// One per image, for the render pass of `Renderer`
+	pub(crate) framebuffers: Vec<vk::Framebuffer>,
+	/// Signaled when the drawing on an image is done, presenting waits on it
+	/// one per image, a semaphore can't be reused before the image it was presented with comes back
+	pub(crate) render_finished: Vec<vk::Semaphore>,
+}
+
+/**
+ * Size of the images, the surface decides unless it leaves it to the window like on wayland,
+ * `u32::MAX` means the window decides
+ */
+fn extent(capabilities: &vk::SurfaceCapabilitiesKHR, window: vk::Extent2D) -> vk::Extent2D
+{
+	if capabilities.current_extent.width != u32::MAX { return capabilities.current_extent }
+
+	let (min, max) = (capabilities.min_image_extent, capabilities.max_image_extent);
+	vk::Extent2D {
+		width: window.width.clamp(min.width, max.width.max(min.width)),
+		height: window.height.clamp(min.height, max.height.max(min.height)),
+	}
+}
+
+/// One more image than the minimum so the driver never waits for us, `0` means no maximum
+fn image_count(capabilities: &vk::SurfaceCapabilitiesKHR) -> u32
+{
+	let wanted = capabilities.min_image_count.saturating_add(1);
+	if capabilities.max_image_count == 0 { return wanted }
+	wanted.min(capabilities.max_image_count)
+}
+
+/// The first way of blending with the desktop the surface supports, opaque if possible
+fn composite_alpha(capabilities: &vk::SurfaceCapabilitiesKHR) -> vk::CompositeAlphaFlagsKHR
+{
+	[
+		vk::CompositeAlphaFlagsKHR::OPAQUE,
+		vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+		vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+		vk::CompositeAlphaFlagsKHR::INHERIT,
+	]
+		.into_iter()
+		.find(|&mode| capabilities.supported_composite_alpha.contains(mode))
+		.unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE)
+}
+
+impl Swapchain {
+	/**
+	 * Creates a swapchain for the current size of the window of `renderer`,
+	 * `None` while the window has no area, like when it is minimized
+	 *
+	 * `old` is the swapchain being replaced, or null, it must still be destroyed after
+	 *
+	 * # Errors
+	 *
+	 * `ConnectionLost` if the surface is gone, `OutOfBounds` without memory left,
+	 * the `VkResult` of the failed call is kept as detail
+	 */
+	pub(crate) fn new(renderer: &Renderer, old: vk::SwapchainKHR) -> Result<Option<Self>, Error>
+	{
+		let capabilities = unsafe {
+			renderer.surface_loader.get_physical_device_surface_capabilities(renderer.physical_device, renderer.surface)
+		}.map_err(|result| response(result, "couldn't read the surface capabilities"))?;
+
+		let extent = extent(&capabilities, renderer.size);
+		if extent.width == 0 || extent.height == 0 { return Ok(None) }
+
+		/**
+		 * FIFO waits for the vertical blank, it is the only mode every driver has
+		 * and a chat client has no reason to draw faster than the screen
+		 */
+		let create_info = vk::SwapchainCreateInfoKHR::default()
+			.surface(renderer.surface)
+			.min_image_count(image_count(&capabilities))
+			.image_format(renderer.format.format)
+			.image_color_space(renderer.format.color_space)
+			.image_extent(extent)
+			.image_array_layers(1)
+			.image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+			.image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+			.pre_transform(capabilities.current_transform)
+			.composite_alpha(composite_alpha(&capabilities))
+			.present_mode(vk::PresentModeKHR::FIFO)
+			.clipped(true)
+			.old_swapchain(old);
+
+		let handle = unsafe { renderer.swapchain_loader.create_swapchain(&create_info, None) }
+			.map_err(|result| response(result, "couldn't create the swapchain"))?;
+
+		let mut swapchain = Self {
+			handle,
+			extent,
+			views: Vec::new(),
+			framebuffers: Vec::new(),
+			render_finished: Vec::new(),
+		};
+		if let Err(e) = swapchain.create_images(renderer) {
+			swapchain.destroy(renderer);
+			return Err(e);
+		}
+		Ok(Some(swapchain))
+	}
+
+	/// Creates the views, framebuffers and semaphores of every image
+	fn create_images(&mut self, renderer: &Renderer) -> Result<(), Error>
+	{
+		let device = &renderer.device;
+		let images = unsafe { renderer.swapchain_loader.get_swapchain_images(self.handle) }
+			.map_err(|result| response(result, "couldn't get the swapchain images"))?;
+
+		for image in images {
+			let view_info = vk::ImageViewCreateInfo::default()
+				.image(image)
+				.view_type(vk::ImageViewType::TYPE_2D)
+				.format(renderer.format.format)
+				.subresource_range(vk::ImageSubresourceRange {
+					aspect_mask: vk::ImageAspectFlags::COLOR,
+					base_mip_level: 0,
+					level_count: 1,
+					base_array_layer: 0,
+					layer_count: 1,
+				});
+			let view = unsafe { device.create_image_view(&view_info, None) }
+				.map_err(|result| response(result, "couldn't create a swapchain image view"))?;
+			self.views.push(view);
+
+			let attachments = [view];
+			let framebuffer_info = vk::FramebufferCreateInfo::default()
+				.render_pass(renderer.renderpass)
+				.attachments(&attachments)
+				.width(self.extent.width)
+				.height(self.extent.height)
+				.layers(1);
+			let framebuffer = unsafe { device.create_framebuffer(&framebuffer_info, None) }
+				.map_err(|result| response(result, "couldn't create a framebuffer"))?;
+			self.framebuffers.push(framebuffer);
+
+			let semaphore = unsafe { device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None) }
+				.map_err(|result| response(result, "couldn't create a semaphore"))?;
+			self.render_finished.push(semaphore);
+		}
+		Ok(())
+	}
+
+	/// Destroys everything, the device must be done with it
+	pub(crate) fn destroy(&self, renderer: &Renderer)
+	{
+		let device = &renderer.device;
+		unsafe {
+			for &semaphore in &self.render_finished { device.destroy_semaphore(semaphore, None) }
+			for &framebuffer in &self.framebuffers { device.destroy_framebuffer(framebuffer, None) }
+			for &view in &self.views { device.destroy_image_view(view, None) }
+			renderer.swapchain_loader.destroy_swapchain(self.handle, None);
+		}
+	}
+}
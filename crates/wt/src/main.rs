@@ -3,17 +3,43 @@
 #![doc = include_str!("../README.md")]
 #![doc(issue_tracker_base_url = "https://github.com/SCLorentz/Walkie-Talkie/issues")]
 
-struct MatrixClient;
+#[derive(Default)]
+struct MatrixClient {
+	/// Renderer of the main window, redrawn on resizes
+	renderer: Option<vk_renderer::Renderer>,
+}
+
+/// Draws a frame, the window shows up once something is presented on it
+fn draw(renderer: &mut vk_renderer::Renderer)
+{
+	match renderer.begin_frame() {
+		Ok(Some(_)) => if let Err(e) = renderer.end_frame() { log::warn!("couldn't draw: {e}") },
+		Ok(None) => {},
+		Err(e) => log::warn!("couldn't draw: {e}"),
+	}
+}
 
 impl app::EventHandler for MatrixClient
 {
-	fn handle_events(e: app::Event)
+	fn handle_events(&mut self, e: app::Event)
 	{
 		use app::Event;
 		match e {
 			Event::CloseRequest => log::info!("closing now"),
-			Event::WindowResized { window: w, .. } => log::info!("Resizing window: {:?}", w.title),
+			Event::WindowResized { window: w, new_size } => {
+				log::info!("Resizing window: {:?}", w.title);
+				if let Some(renderer) = self.renderer.as_mut() {
+					renderer.resize(new_size);
+					draw(renderer);
+				}
+			},
 			Event::OsThemeChange { new_theme: theme } => log::info!("changed: {:?}", theme),
+			Event::ConnectionLost { reason } => log::error!("lost connection: {:?}", reason),
+			Event::NotificationAction { id, action, .. } => log::info!("notification {}: {}", id, action),
+			Event::TrayActivated { .. } => log::info!("tray icon clicked"),
+			Event::MenuAction { id } => log::info!("menu item: {}", id),
+			Event::FileDialogResult { id, files } => log::info!("file dialog {}: {:?}", id, files),
+			Event::ExternalRequest { args } => log::info!("launched again with {:?}", args),
 			_ => {}
 		}
 	}
@@ -21,23 +47,62 @@ impl app::EventHandler for MatrixClient
 
 fn main()
 {
-	use app::App;
-	simple_logger::SimpleLogger::new()
-		.init()
-		.unwrap();
+	use app::{App, Tray, Menu, MenuItem};
+	// `WT_LOG=debug,app=trace` for more, the file is in `~/.local/state/walkie-talkie`
+	// and `journalctl -t walkie-talkie` shows the journal on systemd
+	let log = logger::Logger::new()
+		.env(logger::ENV)
+		.file("walkie-talkie")
+		.journald("walkie-talkie");
+	if let Err(e) = log.init() {
+		dirty::eprintln!("couldn't start the logger: {e}");
+	}
+
+	let mut app = App::new(MatrixClient::default(), "Walkie Talkie");
+
+	// `matrix:` uris and `--room` go to the window that is already open
+	#[cfg(target_os = "linux")]
+	let args: dirty::Vec<dirty::String> = dirty::env::args().skip(1).collect();
+	#[cfg(not(target_os = "linux"))]
+	let args: dirty::Vec<dirty::String> = dirty::Vec::new();
+	let forwarded: dirty::Vec<&str> = args.iter().map(dirty::String::as_str).collect();
+
+	match app.single_instance(&forwarded) {
+		Err(e) if e == dirty::WResponse::ChannelInUse => return log::info!("already running, raising the other window"),
+		Err(e) => log::warn!("couldn't check for other instances: {e}"),
+		Ok(()) => {},
+	}
 
-	let mut app = App::new(MatrixClient, "Walkie Talkie");
 	let mut theme = app.get_global_theme();
 			theme.blur = false;
 			theme.has_title = true;
+	let background = theme.background_color.clone();
 	app.set_global_theme(theme);
 
-	if let Ok(mut window) = app.new_window("walkie talkie", (600.0, 500.0))
-	{
-		let renderer = vk_renderer::Renderer::new(window.get_backend())
+	let tray = Tray::new("Walkie Talkie").menu(Menu::new(dirty::Vec::from([
+		MenuItem::new("toggle-window", "Show/Hide window"),
+		MenuItem::submenu("Presence", dirty::Vec::from([
+			MenuItem::new("presence-online", "Online").radio(true),
+			MenuItem::new("presence-unavailable", "Away").radio(false),
+			MenuItem::new("presence-offline", "Offline").radio(false),
+		])),
+		MenuItem::separator(),
+		MenuItem::new("quit", "Quit"),
+	])));
+	if let Err(e) = app.set_tray(tray) { log::warn!("no tray icon: {e}") }
+
+	let size = (600.0, 500.0);
+	// kept until the app quits, the renderer goes to the handler that redraws it
+	let _window = app.new_window("walkie talkie", size).ok().map(|mut window| {
+		let mut renderer = vk_renderer::Renderer::new(window.get_backend(), size)
 			.expect("Vulkan inicialization failed");
 		let _ = window.connect_surface(renderer.get_surface());
-	};
+
+		renderer.set_clear_color(background);
+		draw(&mut renderer);
+		app.handler().renderer = Some(renderer);
+		window
+	});
 
 	//let _ = app.new_window("window 2", (500.0, 500.0));
 
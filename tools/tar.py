@@ -7,5 +7,7 @@ def create_generic_package(release):
 
 	with tarfile.open(archive_name, mode="w:gz") as tar_file:
 		tar_file.add("wt")
+		# themed icon looked up by `app::DEFAULT_ICON`
+		tar_file.add("sources/icons/wt.svg", arcname="share/icons/hicolor/scalable/apps/walkie-talkie.svg")
 		os.remove("wt")
 		return archive_name